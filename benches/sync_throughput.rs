@@ -0,0 +1,90 @@
+//! Sync throughput of the configured wallet on 1k to 100k block chains.
+//!
+//! `cargo bench` writes the results to `bonecoin-bench.json` in the target directory, which is
+//! `CARGO_TARGET_DIR` when set, or to the path in `BONECOIN_BENCH_OUT`. Set
+//! `BONECOIN_BENCH_MAX_BLOCKS` to skip the longer chains. The run fails if sync time grows
+//! super-linearly with the chain length.
+
+use bonecoin_core::*;
+use bonecoin_testsuite::bench::{run_benchmarks, Workload, SUPER_LINEAR_EXPONENT};
+use bonecoin_testsuite::WalletFactory;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+struct ConfiguredWallet;
+
+impl WalletFactory for ConfiguredWallet {
+    type Wallet = utxo_wallet_assignment::Wallet;
+
+    fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet {
+        utxo_wallet_assignment::Wallet::new(addresses)
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn main() -> ExitCode {
+    let max_blocks: u64 = env_var("BONECOIN_BENCH_MAX_BLOCKS")
+        .map(|max| {
+            max.parse()
+                .expect("BONECOIN_BENCH_MAX_BLOCKS must be a number")
+        })
+        .unwrap_or(u64::MAX);
+    let target_dir = env_var("CARGO_TARGET_DIR")
+        .unwrap_or_else(|| concat!(env!("CARGO_MANIFEST_DIR"), "/target").to_string());
+    let out = env_var("BONECOIN_BENCH_OUT").unwrap_or_else(|| {
+        Path::new(&target_dir)
+            .join("bonecoin-bench.json")
+            .display()
+            .to_string()
+    });
+
+    let workloads: Vec<Workload> = Workload::default_matrix()
+        .into_iter()
+        .filter(|workload| workload.blocks <= max_blocks)
+        .collect();
+    let report = run_benchmarks::<ConfiguredWallet>("utxo-wallet-assignment", &workloads);
+
+    println!(
+        "{:>8} {:>7} {:>8} {:>12} {:>10} {:>12} {:>10}",
+        "blocks", "txs/blk", "utxos", "ns/block", "q/block", "tip ns/blk", "tip q/blk"
+    );
+    for result in &report.results {
+        let workload = result.workload;
+        println!(
+            "{:>8} {:>7} {:>8} {:>12.0} {:>10.2} {:>12.0} {:>10.2}",
+            workload.blocks,
+            workload.txs_per_block,
+            workload.utxo_set,
+            result.ns_per_block,
+            result.queries_per_block,
+            result.tip_ns_per_block,
+            result.tip_queries_per_block
+        );
+    }
+    for fit in &report.fits {
+        println!(
+            "{} txs/block, {} standing utxos: time ~ blocks^{:.2}",
+            fit.txs_per_block, fit.utxo_set, fit.exponent
+        );
+    }
+
+    if let Some(dir) = Path::new(&out).parent() {
+        fs::create_dir_all(dir)
+            .unwrap_or_else(|e| panic!("cannot create {}: {}", dir.display(), e));
+    }
+    fs::write(&out, report.to_json()).unwrap_or_else(|e| panic!("cannot write {}: {}", out, e));
+    println!("results written to {}", out);
+
+    if report.super_linear().next().is_some() {
+        eprintln!(
+            "sync time grows faster than blocks^{} in at least one series",
+            SUPER_LINEAR_EXPONENT
+        );
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
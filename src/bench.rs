@@ -0,0 +1,285 @@
+//! Sync throughput measurements and scaling fits.
+//!
+//! A [`Workload`] is a best chain of a given length, built before any timing starts. [`RUNS`]
+//! fresh wallets sync it from genesis, then sync [`TIP_BLOCKS`] more blocks on top. Both syncs
+//! are timed and their node queries counted, and the median run is reported. Workloads
+//! differing only in length form a series, and the time of each series is fitted to
+//! `time = c * blocks^exponent`: an exponent well above 1 means sync does more work per block
+//! the longer the chain is.
+//!
+//! The `sync_throughput` bench target runs the default matrix against the configured wallet and
+//! writes a [`BenchReport`] as JSON, so runs can be compared across implementations and revisions.
+
+use crate::harness::WalletFactory;
+use bonecoin_core::*;
+use serde::Serialize;
+use std::collections::*;
+use std::time::{Duration, Instant};
+
+/// Blocks added on top of a synced workload to time tip syncs.
+pub const TIP_BLOCKS: u64 = 100;
+
+/// Wallets timed on every workload. Their median time is reported, so one slow run does not
+/// skew the fit.
+pub const RUNS: usize = 5;
+
+/// Fitted exponents above this count as super-linear.
+pub const SUPER_LINEAR_EXPONENT: f64 = 1.25;
+
+/// One chain to sync.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Workload {
+    pub blocks: u64,
+    pub txs_per_block: u64,
+    /// Coins minted to the wallet in block 1 and kept alive for the whole chain. When 0, every
+    /// transaction mints a new coin instead, so the UTXO set grows with the chain.
+    pub utxo_set: u64,
+}
+
+impl Workload {
+    /// Every combination of the given lengths, transactions per block and UTXO set sizes.
+    pub fn matrix(blocks: &[u64], txs_per_block: &[u64], utxo_sets: &[u64]) -> Vec<Workload> {
+        let mut workloads = Vec::new();
+        for &txs_per_block in txs_per_block {
+            for &utxo_set in utxo_sets {
+                for &blocks in blocks {
+                    workloads.push(Workload {
+                        blocks,
+                        txs_per_block,
+                        utxo_set,
+                    });
+                }
+            }
+        }
+        workloads
+    }
+
+    /// 1k, 10k and 100k blocks, with one or eight transactions per block, and a UTXO set that
+    /// either grows with the chain or stays at 10k coins.
+    pub fn default_matrix() -> Vec<Workload> {
+        Self::matrix(&[1_000, 10_000, 100_000], &[1, 8], &[0, 10_000])
+    }
+}
+
+/// Builds the chain of a workload, block by block.
+struct ChainGenerator {
+    workload: Workload,
+    /// Unspent coins of the standing UTXO set, oldest first.
+    pool: VecDeque<CoinId>,
+    tip: BlockId,
+    height: u64,
+}
+
+impl ChainGenerator {
+    fn new(node: &mut MockNode, workload: Workload) -> Self {
+        let mut generator = ChainGenerator {
+            workload,
+            pool: VecDeque::new(),
+            tip: Block::genesis().id(),
+            height: 0,
+        };
+        if workload.utxo_set > 0 {
+            let mint = Transaction {
+                inputs: vec![],
+                outputs: (0..workload.utxo_set)
+                    .map(|i| Coin {
+                        value: 1 + i,
+                        owner: Address::Alice,
+                    })
+                    .collect(),
+            };
+            generator.pool = (0..workload.utxo_set as usize)
+                .map(|i| mint.coin_id(1, i))
+                .collect();
+            generator.push(node, vec![mint]);
+        }
+        generator
+    }
+
+    fn push(&mut self, node: &mut MockNode, transactions: Vec<Transaction>) {
+        self.tip = node.add_block_as_best(self.tip, transactions);
+        self.height += 1;
+    }
+
+    /// Add one block of the workload on top of the tip.
+    fn extend(&mut self, node: &mut MockNode) {
+        let height = self.height + 1;
+        let mut transactions = Vec::new();
+        for i in 0..self.workload.txs_per_block {
+            let owner = if i % 2 == 0 {
+                Address::Alice
+            } else {
+                Address::Bob
+            };
+            let output = Coin {
+                value: height * 1_000 + i + 1,
+                owner,
+            };
+            let tx = match self.pool.pop_front() {
+                Some(spent) => {
+                    let tx = Transaction {
+                        inputs: vec![Input {
+                            coin_id: spent,
+                            signature: Signature::Valid(Address::Alice),
+                        }],
+                        outputs: vec![Coin {
+                            owner: Address::Alice,
+                            ..output
+                        }],
+                    };
+                    self.pool.push_back(tx.coin_id(height, 0));
+                    tx
+                }
+                None => Transaction {
+                    inputs: vec![],
+                    outputs: vec![output],
+                },
+            };
+            transactions.push(tx);
+        }
+        self.push(node, transactions);
+    }
+}
+
+/// Measurements of one workload.
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchResult {
+    pub workload: Workload,
+    pub sync_seconds: f64,
+    pub ns_per_block: f64,
+    pub queries_per_block: f64,
+    /// Average cost of syncing [`TIP_BLOCKS`] more blocks once the chain is synced.
+    pub tip_ns_per_block: f64,
+    pub tip_queries_per_block: f64,
+}
+
+/// Sync time of one series against chain length.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScalingFit {
+    pub txs_per_block: u64,
+    pub utxo_set: u64,
+    /// `exponent` in `time = c * blocks^exponent`, fitted by least squares in log-log space.
+    pub exponent: f64,
+    pub super_linear: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchReport {
+    pub wallet: String,
+    pub results: Vec<BenchResult>,
+    pub fits: Vec<ScalingFit>,
+}
+
+impl BenchReport {
+    pub fn super_linear(&self) -> impl Iterator<Item = &ScalingFit> {
+        self.fits.iter().filter(|fit| fit.super_linear)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("bench report is always serializable")
+    }
+}
+
+/// Sync `wallet` on `node`, returning how long it took and how many queries it made.
+fn timed_sync(wallet: &mut impl WalletApi, node: &MockNode) -> (Duration, f64) {
+    let queries = node.how_many_queries();
+    let start = Instant::now();
+    wallet.sync(node);
+    let elapsed = start.elapsed();
+    (elapsed, (node.how_many_queries() - queries) as f64)
+}
+
+/// The median of `runs`, by time.
+fn median(mut runs: Vec<(Duration, f64)>) -> (Duration, f64) {
+    runs.sort_by_key(|(elapsed, _)| *elapsed);
+    runs[runs.len() / 2]
+}
+
+/// Build `workload`, sync [`RUNS`] fresh wallets from `F` on it and measure the median sync.
+pub fn measure<F: WalletFactory>(workload: Workload) -> BenchResult {
+    let mut node = MockNode::new();
+    let mut generator = ChainGenerator::new(&mut node, workload);
+    while generator.height < workload.blocks {
+        generator.extend(&mut node);
+    }
+    let mut wallets: Vec<F::Wallet> = (0..RUNS)
+        .map(|_| F::new_wallet(vec![Address::Alice, Address::Bob].into_iter()))
+        .collect();
+
+    let (elapsed, queries) = median(
+        wallets
+            .iter_mut()
+            .map(|wallet| timed_sync(wallet, &node))
+            .collect(),
+    );
+    for wallet in &wallets {
+        assert_eq!(wallet.best_height(), workload.blocks, "{:?}", workload);
+    }
+
+    for _ in 0..TIP_BLOCKS {
+        generator.extend(&mut node);
+    }
+    let (tip_elapsed, tip_queries) = median(
+        wallets
+            .iter_mut()
+            .map(|wallet| timed_sync(wallet, &node))
+            .collect(),
+    );
+
+    let blocks = workload.blocks as f64;
+    BenchResult {
+        workload,
+        sync_seconds: elapsed.as_secs_f64(),
+        ns_per_block: elapsed.as_nanos() as f64 / blocks,
+        queries_per_block: queries / blocks,
+        tip_ns_per_block: tip_elapsed.as_nanos() as f64 / TIP_BLOCKS as f64,
+        tip_queries_per_block: tip_queries / TIP_BLOCKS as f64,
+    }
+}
+
+/// Least squares slope of `ln(y)` against `ln(x)`.
+fn log_log_slope(points: &[(f64, f64)]) -> f64 {
+    let logs: Vec<(f64, f64)> = points.iter().map(|(x, y)| (x.ln(), y.ln())).collect();
+    let n = logs.len() as f64;
+    let mean_x = logs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = logs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = logs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = logs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    covariance / variance
+}
+
+/// Fit every series of at least two lengths in `results`.
+pub fn fit(results: &[BenchResult]) -> Vec<ScalingFit> {
+    let mut series: BTreeMap<(u64, u64), Vec<(f64, f64)>> = BTreeMap::new();
+    for result in results {
+        let workload = result.workload;
+        series
+            .entry((workload.txs_per_block, workload.utxo_set))
+            .or_default()
+            .push((workload.blocks as f64, result.sync_seconds.max(1e-9)));
+    }
+
+    series
+        .into_iter()
+        .filter(|(_, points)| points.len() >= 2)
+        .map(|((txs_per_block, utxo_set), points)| {
+            let exponent = log_log_slope(&points);
+            ScalingFit {
+                txs_per_block,
+                utxo_set,
+                exponent,
+                super_linear: exponent > SUPER_LINEAR_EXPONENT,
+            }
+        })
+        .collect()
+}
+
+/// Measure every workload against wallets from `F` and fit the results.
+pub fn run_benchmarks<F: WalletFactory>(wallet: &str, workloads: &[Workload]) -> BenchReport {
+    let results: Vec<BenchResult> = workloads.iter().map(|w| measure::<F>(*w)).collect();
+    BenchReport {
+        wallet: wallet.to_string(),
+        fits: fit(&results),
+        results,
+    }
+}
@@ -2,16 +2,15 @@
 
 use std::collections::*;
 use bonecoin_core::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
 
-fn wallet_with_alice() -> Wallet {
-    Wallet::new(vec![Address::Alice].into_iter())
+fn wallet_with_alice<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice].into_iter())
 }
 
-#[test]
-fn test_reorgs_with_utxos_in_chain_history() {
+pub fn test_reorgs_with_utxos_in_chain_history<F: WalletFactory>() {
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     let coin_1 = Coin { value: 50, owner: Address::Alice };
     let coin_2 = Coin { value: 100, owner: Address::Alice };
@@ -0,0 +1,69 @@
+//! Run the whole battery against the configured wallet and print a scorecard.
+//!
+//! ```text
+//! cargo run --release --features score --bin bonecoin-score -- [--json] [--output <file>]
+//! ```
+//!
+//! Markdown goes to stdout by default. `BONECOIN_TAGS` limits the run to some of the tests, see
+//! [`tags`](bonecoin_testsuite::tags). The exit code is non-zero if any test failed.
+
+use bonecoin_core::*;
+use bonecoin_testsuite::scorecard::score;
+use bonecoin_testsuite::{install_counting_allocator, suite_tests_with, WalletFactory};
+use std::process::ExitCode;
+
+install_counting_allocator!();
+
+struct ConfiguredWallet;
+
+impl WalletFactory for ConfiguredWallet {
+    type Wallet = utxo_wallet_assignment::Wallet;
+
+    fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet {
+        utxo_wallet_assignment::Wallet::new(addresses)
+    }
+}
+
+fn main() -> ExitCode {
+    let mut json = false;
+    let mut output = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--markdown" => json = false,
+            "--output" => output = Some(args.next().expect("--output needs a file name")),
+            other => {
+                eprintln!("unknown argument {}", other);
+                eprintln!("usage: bonecoin-score [--json | --markdown] [--output <file>]");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    // List the extensions the wallet implements after it, as in `wallet_test_suite!`, to score
+    // their tests too.
+    let tests = suite_tests_with!(ConfiguredWallet);
+    let scorecard = score("utxo-wallet-assignment", tests, |outcome| {
+        let status = if outcome.passed { "ok" } else { "FAILED" };
+        eprintln!("{}::{} ... {}", outcome.module, outcome.name, status);
+    });
+    let report = if json {
+        scorecard.to_json()
+    } else {
+        scorecard.to_markdown()
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, report).unwrap_or_else(|e| panic!("cannot write {}: {}", path, e))
+        }
+        None => print!("{}", report),
+    }
+
+    if scorecard.passed == scorecard.total {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,169 @@
+//! Write `trees/forked_10k.tree`, a 10,000 block chain with a fork every 90 blocks of the main
+//! chain.
+//!
+//! ```text
+//! cargo run --release --bin forked-tree
+//! ```
+//!
+//! The blocks are drawn from a fixed seed and written with
+//! [`export_tree`](bonecoin_testsuite::tree_file::export_tree), so every run writes the same
+//! file. Every block mints its own number to Alice, Bob or Charlie, so sibling blocks never share
+//! an id. About a third of the blocks also spend one or two owned coins that are unspent on their
+//! branch, paying a random address and sending the change back. Forks leave the main chain up to
+//! 30 blocks below its tip and grow up to 20 blocks, spending the coins of their own branch.
+
+use bonecoin_core::*;
+use bonecoin_testsuite::random_scenario::Rng;
+use bonecoin_testsuite::recording_node::RecordingNode;
+use bonecoin_testsuite::tree_file::{export_tree, TREE_DIR};
+use std::collections::*;
+use std::path::Path;
+
+const SEED: u64 = 20;
+const TOTAL_BLOCKS: u64 = 10_000;
+const OWNED: [Address; 2] = [Address::Alice, Address::Bob];
+const PAYEES: [Address; 6] = [
+    Address::Charlie,
+    Address::Dave,
+    Address::Eve,
+    Address::Custom(7),
+    Address::Alice,
+    Address::Bob,
+];
+
+/// A block to add: its transactions, the owned coins they spend and the ones they create.
+struct NewBlock {
+    transactions: Vec<Transaction>,
+    spent: Vec<CoinId>,
+    created: Vec<(CoinId, Coin)>,
+}
+
+/// Draw block number `number` at `height`, spending from `unspent`, the owned coins unspent on
+/// its branch in the order they were created.
+fn draw_block(rng: &mut Rng, number: u64, height: u64, unspent: &[(CoinId, Coin)]) -> NewBlock {
+    let minters = [Address::Alice, Address::Bob, Address::Charlie];
+    let mint = Transaction {
+        inputs: vec![Input::dummy()],
+        outputs: vec![Coin {
+            value: number,
+            owner: *rng.pick(&minters),
+        }],
+    };
+    let mut block = NewBlock {
+        spent: Vec::new(),
+        created: vec![(mint.coin_id(height, 0), mint.outputs[0].clone())],
+        transactions: vec![mint],
+    };
+
+    if unspent.is_empty() || !rng.percent(30) {
+        return block;
+    }
+    let mut candidates = unspent.to_vec();
+    let mut inputs = Vec::new();
+    for _ in 0..(1 + rng.below(2)).min(candidates.len() as u64) {
+        inputs.push(candidates.swap_remove(rng.below(candidates.len() as u64) as usize));
+    }
+    let total: u64 = inputs.iter().map(|(_, coin)| coin.value).sum();
+    let pay = 1 + rng.below(total);
+    let burn = rng.below((total - pay).min(2) + 1);
+    let change = total - pay - burn;
+
+    let mut outputs = vec![Coin {
+        value: pay,
+        owner: *rng.pick(&PAYEES),
+    }];
+    if change > 0 {
+        outputs.push(Coin {
+            value: change,
+            owner: inputs[0].1.owner,
+        });
+    }
+    let tx = Transaction {
+        inputs: inputs
+            .iter()
+            .map(|(coin_id, coin)| Input {
+                coin_id: *coin_id,
+                signature: Signature::Valid(coin.owner),
+            })
+            .collect(),
+        outputs,
+    };
+    block.spent = inputs.iter().map(|(coin_id, _)| *coin_id).collect();
+    for (index, coin) in tx.outputs.iter().enumerate() {
+        block
+            .created
+            .push((tx.coin_id(height, index), coin.clone()));
+    }
+    block.transactions.push(tx);
+    block
+}
+
+/// Apply `block` to the owned coins unspent on its branch.
+fn update(unspent: &mut Vec<(CoinId, Coin)>, block: &NewBlock) {
+    unspent.retain(|(coin_id, _)| !block.spent.contains(coin_id));
+    unspent.extend(
+        block
+            .created
+            .iter()
+            .filter(|(_, coin)| OWNED.contains(&coin.owner))
+            .cloned(),
+    );
+}
+
+fn main() {
+    let mut rng = Rng::new(SEED);
+    let mut node = RecordingNode::new();
+    let mut blocks = 0;
+
+    // The main chain by height, its owned unspent coins, and every owned coin it ever created
+    // with the height that created it and the one that spent it.
+    let mut main = vec![Block::genesis().id()];
+    let mut main_unspent = Vec::new();
+    let mut main_coins: Vec<(CoinId, Coin, u64)> = Vec::new();
+    let mut spent_at: HashMap<CoinId, u64> = HashMap::new();
+
+    while blocks < TOTAL_BLOCKS {
+        blocks += 1;
+        let height = main.len() as u64;
+        let block = draw_block(&mut rng, blocks, height, &main_unspent);
+        for coin_id in &block.spent {
+            spent_at.insert(*coin_id, height);
+        }
+        for (coin_id, coin) in &block.created {
+            if OWNED.contains(&coin.owner) {
+                main_coins.push((*coin_id, coin.clone(), height));
+            }
+        }
+        update(&mut main_unspent, &block);
+        let tip = *main.last().unwrap();
+        main.push(node.add_block_as_best(tip, block.transactions));
+
+        if height % 90 != 0 || blocks >= TOTAL_BLOCKS - 30 {
+            continue;
+        }
+        let point = height.saturating_sub(1 + rng.below(30)).max(1);
+        let mut unspent: Vec<(CoinId, Coin)> = main_coins
+            .iter()
+            .filter(|(coin_id, _, created)| {
+                *created <= point && spent_at.get(coin_id).map_or(true, |spent| *spent > point)
+            })
+            .map(|(coin_id, coin, _)| (*coin_id, coin.clone()))
+            .collect();
+        let mut parent = main[point as usize];
+        let length = 1 + rng.below(20u64.min(height - point + 5));
+        for fork_height in point + 1..=point + length {
+            if blocks >= TOTAL_BLOCKS - 1 {
+                break;
+            }
+            blocks += 1;
+            let block = draw_block(&mut rng, blocks, fork_height, &unspent);
+            update(&mut unspent, &block);
+            parent = node.add_block(parent, block.transactions);
+        }
+    }
+
+    let path = Path::new(TREE_DIR).join("forked_10k.tree");
+    std::fs::write(&path, export_tree(&node.record().borrow()))
+        .unwrap_or_else(|e| panic!("cannot write {}: {}", path.display(), e));
+    eprintln!("wrote {} blocks to {}", blocks, path.display());
+}
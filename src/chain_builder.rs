@@ -0,0 +1,167 @@
+//! Fluent construction of `MockNode` block trees.
+//!
+//! Blocks are never named one by one. A chain is made of named branches, each with a tip that
+//! moves as blocks are added. Any block can then be referred to as:
+//!
+//! - `"G"` for genesis,
+//! - `"<branch>"` for the current tip of a branch,
+//! - `"<branch>:<height>"` for the block at that height on the way to the branch tip.
+//!
+//! ```ignore
+//! let mut chain = ChainBuilder::new();
+//! let mint = chain.extend("main", 2).tx("main", mint_tx);
+//! chain.fork("side", "main:1").extend("side", 3).set_best("side");
+//! wallet.sync(chain.node());
+//! assert!(wallet.coin_details(&mint.coin_id(0)).is_err());
+//! ```
+
+use crate::recording_node::RecordingNode;
+use bonecoin_core::*;
+use std::collections::*;
+
+/// The branch every builder starts with, rooted at genesis.
+pub const MAIN: &str = "main";
+
+/// A transaction together with the block it was mined in.
+#[derive(Clone, Debug)]
+pub struct LandedTx {
+    pub transaction: Transaction,
+    pub block_id: BlockId,
+    pub height: u64,
+}
+
+impl LandedTx {
+    /// Id of the coin created by output `index` of this transaction where it actually landed.
+    pub fn coin_id(&self, index: usize) -> CoinId {
+        self.transaction.coin_id(self.height, index)
+    }
+}
+
+pub struct ChainBuilder {
+    node: RecordingNode,
+    tips: HashMap<String, BlockId>,
+}
+
+impl ChainBuilder {
+    /// A node containing only genesis, with an empty [`MAIN`] branch.
+    pub fn new() -> Self {
+        let mut tips = HashMap::new();
+        tips.insert(MAIN.to_string(), Block::genesis().id());
+        ChainBuilder {
+            node: RecordingNode::new(),
+            tips,
+        }
+    }
+
+    /// Start a new branch whose tip is the block `from` refers to.
+    pub fn fork(&mut self, branch: &str, from: &str) -> &mut Self {
+        assert!(
+            !self.tips.contains_key(branch),
+            "branch {} already exists",
+            branch
+        );
+        let from_id = self.id(from);
+        self.tips.insert(branch.to_string(), from_id);
+        self
+    }
+
+    /// Add `blocks` empty blocks on top of `branch`.
+    ///
+    /// Remember that empty blocks with the same parent are the same block; put a marker
+    /// transaction on one side of a fork if both sides must be distinct.
+    pub fn extend(&mut self, branch: &str, blocks: u64) -> &mut Self {
+        for _ in 0..blocks {
+            self.block(branch, vec![]);
+        }
+        self
+    }
+
+    /// Add one block with `transactions` on top of `branch`.
+    pub fn block(&mut self, branch: &str, transactions: Vec<Transaction>) -> Vec<LandedTx> {
+        let parent = self.tip(branch);
+        let height = self.height_of(parent) + 1;
+        let block_id = self.node.add_block(parent, transactions.clone());
+        self.tips.insert(branch.to_string(), block_id);
+
+        transactions
+            .into_iter()
+            .map(|transaction| LandedTx {
+                transaction,
+                block_id,
+                height,
+            })
+            .collect()
+    }
+
+    /// Add one block holding just `transaction` on top of `branch`.
+    pub fn tx(&mut self, branch: &str, transaction: Transaction) -> LandedTx {
+        self.block(branch, vec![transaction]).remove(0)
+    }
+
+    /// Make the block `name` refers to the node's best block.
+    pub fn set_best(&mut self, name: &str) -> &mut Self {
+        let block_id = self.id(name);
+        self.node.set_best(block_id);
+        self
+    }
+
+    /// Resolve a block name, see the module documentation for the syntax.
+    pub fn id(&self, name: &str) -> BlockId {
+        if name == "G" {
+            return Block::genesis().id();
+        }
+        match name.split_once(':') {
+            None => self.tip(name),
+            Some((branch, height)) => {
+                let height: u64 = height
+                    .parse()
+                    .unwrap_or_else(|_| panic!("invalid height in block name {}", name));
+                self.ancestor_at(self.tip(branch), height)
+                    .unwrap_or_else(|| panic!("branch {} does not reach height {}", branch, height))
+            }
+        }
+    }
+
+    /// Height of the block `name` refers to.
+    pub fn height(&self, name: &str) -> u64 {
+        self.height_of(self.id(name))
+    }
+
+    /// The recorded node, to be passed to `wallet.sync`.
+    pub fn node(&self) -> &RecordingNode {
+        &self.node
+    }
+
+    pub fn node_mut(&mut self) -> &mut RecordingNode {
+        &mut self.node
+    }
+
+    fn tip(&self, branch: &str) -> BlockId {
+        *self
+            .tips
+            .get(branch)
+            .unwrap_or_else(|| panic!("unknown branch {}", branch))
+    }
+
+    fn height_of(&self, block_id: BlockId) -> u64 {
+        self.node.record().borrow().height_of(block_id)
+    }
+
+    fn ancestor_at(&self, mut block_id: BlockId, height: u64) -> Option<BlockId> {
+        let record = self.node.record();
+        let record = record.borrow();
+        if record.height_of(block_id) < height {
+            return None;
+        }
+        while record.height_of(block_id) > height {
+            block_id = record.blocks[&block_id].parent;
+        }
+        Some(block_id)
+    }
+}
+
+impl Default for ChainBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,92 @@
+//! Tests for the bonecoin wallet written with the chain builder
+
+use crate::chain_builder::{ChainBuilder, MAIN};
+use crate::differential::DifferentialWallet;
+use crate::harness::WalletFactory;
+use bonecoin_core::*;
+use std::collections::*;
+
+fn mint(value: u64, owner: Address) -> Transaction {
+    Transaction {
+        inputs: vec![],
+        outputs: vec![Coin { value, owner }],
+    }
+}
+
+//    G - main:1 - main:2 - main:3 - main:4 (pay lands at height 4)
+//              \
+//                side:2 (pay lands at height 2, new wallet state)
+pub fn coin_ids_follow_where_tx_landed<F: WalletFactory>() {
+    let mut chain = ChainBuilder::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
+
+    let minted = chain.tx(MAIN, mint(100, Address::Alice));
+    chain.fork("side", MAIN);
+
+    let pay = Transaction {
+        inputs: vec![Input {
+            coin_id: minted.coin_id(0),
+            signature: Signature::Valid(Address::Alice),
+        }],
+        outputs: vec![Coin {
+            value: 100,
+            owner: Address::Bob,
+        }],
+    };
+    let paid_on_main = chain.extend(MAIN, 2).tx(MAIN, pay.clone());
+    chain.set_best(MAIN);
+    wallet.sync(chain.node());
+
+    assert_eq!(paid_on_main.height, 4);
+    assert_eq!(wallet.best_hash(), chain.id("main:4"));
+    assert_eq!(
+        wallet.all_coins_of(Address::Bob),
+        Ok(HashSet::from_iter([(paid_on_main.coin_id(0), 100)]))
+    );
+
+    let paid_on_side = chain.tx("side", pay);
+    chain.set_best("side");
+    wallet.sync(chain.node());
+
+    assert_eq!(paid_on_side.height, 2);
+    assert_eq!(chain.id("side:1"), chain.id("main:1"));
+    assert_eq!(
+        wallet.coin_details(&paid_on_main.coin_id(0)),
+        Err(WalletError::UnknownCoin)
+    );
+    assert_eq!(
+        wallet.all_coins_of(Address::Bob),
+        Ok(HashSet::from_iter([(paid_on_side.coin_id(0), 100)]))
+    );
+}
+
+pub fn builder_chains_match_oracle<F: WalletFactory>() {
+    let mut chain = ChainBuilder::new();
+    let addresses = vec![Address::Alice, Address::Bob];
+    let wallet = F::new_wallet(addresses.clone().into_iter());
+    let mut wallet = DifferentialWallet::new(wallet, addresses, chain.node());
+
+    let minted = chain.extend(MAIN, 3).tx(MAIN, mint(40, Address::Bob));
+    chain.extend(MAIN, 2).set_best(MAIN);
+    wallet.sync(chain.node());
+
+    chain.fork("short", "main:4");
+    chain.block(
+        "short",
+        vec![Transaction {
+            inputs: vec![Input {
+                coin_id: minted.coin_id(0),
+                signature: Signature::Valid(Address::Bob),
+            }],
+            outputs: vec![Coin {
+                value: 30,
+                owner: Address::Alice,
+            }],
+        }],
+    );
+    chain.set_best("short");
+    wallet.sync(chain.node());
+
+    chain.set_best("main:2");
+    wallet.sync(chain.node());
+}
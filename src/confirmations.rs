@@ -0,0 +1,84 @@
+//! Optional wallet extension: balances that only count coins buried deep enough.
+//!
+//! A coin has one confirmation once the block creating it is on the best chain, and one more
+//! for every block on top of it: `best_height - height + 1`. Coins of the tip block are the
+//! first to go in a reorg, so a wallet implementing [`ConfirmationsWalletApi`] can leave out
+//! coins with fewer than a given number of confirmations. At most 1 confirmation, every owned
+//! coin counts, as with `total_assets_of` and `net_worth`.
+//!
+//! Enable the tests with `wallet_test_suite!(name, factory, confirmations)`.
+
+use crate::recording_node::ChainRecord;
+use crate::tx_validation::ValidatedWallet;
+use bonecoin_core::*;
+use std::collections::*;
+
+pub trait ConfirmationsWalletApi: WalletApi {
+    /// Value of the coins of `address` with at least `min_confirmations` confirmations.
+    fn total_assets_of_with_confirmations(
+        &self,
+        address: Address,
+        min_confirmations: u64,
+    ) -> Result<u64, WalletError>;
+
+    /// Value of the owned coins with at least `min_confirmations` confirmations.
+    fn net_worth_with_confirmations(&self, min_confirmations: u64) -> u64;
+
+    /// Like `all_coins_of`, with the confirmations of each coin after its value.
+    fn coins_with_confirmations_of(
+        &self,
+        address: Address,
+    ) -> Result<HashSet<(CoinId, u64, u64)>, WalletError>;
+}
+
+impl<W: ConfirmationsWalletApi> ConfirmationsWalletApi for ValidatedWallet<W> {
+    fn total_assets_of_with_confirmations(
+        &self,
+        address: Address,
+        min_confirmations: u64,
+    ) -> Result<u64, WalletError> {
+        self.wallet()
+            .total_assets_of_with_confirmations(address, min_confirmations)
+    }
+
+    fn net_worth_with_confirmations(&self, min_confirmations: u64) -> u64 {
+        self.wallet()
+            .net_worth_with_confirmations(min_confirmations)
+    }
+
+    fn coins_with_confirmations_of(
+        &self,
+        address: Address,
+    ) -> Result<HashSet<(CoinId, u64, u64)>, WalletError> {
+        self.wallet().coins_with_confirmations_of(address)
+    }
+}
+
+/// The unspent coins of `address` on the best chain of `record`, with their value and
+/// confirmations, replayed from genesis.
+pub fn expected_coins_with_confirmations(
+    record: &ChainRecord,
+    address: Address,
+) -> HashSet<(CoinId, u64, u64)> {
+    let best_height = record.height_of(record.best);
+    let mut unspent: HashMap<CoinId, (u64, u64)> = HashMap::new();
+
+    for block_id in record.best_chain() {
+        let block = &record.blocks[&block_id];
+        for tx in &block.transactions {
+            for input in &tx.inputs {
+                unspent.remove(&input.coin_id);
+            }
+            for (index, coin) in tx.outputs.iter().enumerate() {
+                if coin.owner == address {
+                    let confirmations = best_height - block.height + 1;
+                    unspent.insert(tx.coin_id(block.height, index), (coin.value, confirmations));
+                }
+            }
+        }
+    }
+    unspent
+        .into_iter()
+        .map(|(coin_id, (value, confirmations))| (coin_id, value, confirmations))
+        .collect()
+}
@@ -0,0 +1,194 @@
+//! Balances counting only coins with enough confirmations, across reorgs
+//!
+//! Only for wallets implementing [`ConfirmationsWalletApi`].
+
+use crate::chain_builder::{ChainBuilder, MAIN};
+use crate::confirmations::{expected_coins_with_confirmations, ConfirmationsWalletApi};
+use crate::harness::WalletFactory;
+use crate::random_scenario::{for_each_seed, RandomConfig, Scenario, OWNED};
+use crate::recording_node::RecordingNode;
+use crate::test_helpers::pay;
+use bonecoin_core::*;
+use std::collections::*;
+
+/// Compare every confirmation query for `addresses` with the ones replayed from `node`.
+fn check_confirmations(
+    wallet: &impl ConfirmationsWalletApi,
+    addresses: &[Address],
+    node: &RecordingNode,
+    context: &str,
+) {
+    let record = node.record();
+    let record = record.borrow();
+    let mut all = Vec::new();
+    for address in addresses {
+        let expected = expected_coins_with_confirmations(&record, *address);
+        assert_eq!(
+            wallet.coins_with_confirmations_of(*address),
+            Ok(expected.clone()),
+            "{}: coins of {:?}",
+            context,
+            address
+        );
+        for min_confirmations in 0..=record.height_of(record.best) + 1 {
+            let total = expected
+                .iter()
+                .filter(|(_, _, confirmations)| *confirmations >= min_confirmations)
+                .fold(0u64, |total, (_, value, _)| total.saturating_add(*value));
+            assert_eq!(
+                wallet.total_assets_of_with_confirmations(*address, min_confirmations),
+                Ok(total),
+                "{}: assets of {:?} with {} confirmations",
+                context,
+                address,
+                min_confirmations
+            );
+        }
+        all.extend(expected);
+    }
+    for min_confirmations in 0..=record.height_of(record.best) + 1 {
+        let total = all
+            .iter()
+            .filter(|(_, _, confirmations)| *confirmations >= min_confirmations)
+            .fold(0u64, |total, (_, value, _)| total.saturating_add(*value));
+        assert_eq!(
+            wallet.net_worth_with_confirmations(min_confirmations),
+            total,
+            "{}: net worth with {} confirmations",
+            context,
+            min_confirmations
+        );
+    }
+    assert_eq!(
+        wallet.total_assets_of_with_confirmations(Address::Custom(404), 0),
+        Err(WalletError::ForeignAddress)
+    );
+    assert_eq!(
+        wallet.coins_with_confirmations_of(Address::Custom(404)),
+        Err(WalletError::ForeignAddress)
+    );
+}
+
+// The reorgs of `tommy97_tests::reorg_hard_test_hehe`.
+pub fn confirmed_balances_across_reorg_hard_test_hehe<F: WalletFactory>()
+where
+    F::Wallet: ConfirmationsWalletApi,
+{
+    let (alice, bob) = (Address::Alice, Address::Bob);
+    let mut node = RecordingNode::new();
+    let mut wallet = F::new_wallet(vec![alice, bob].into_iter());
+
+    let mint = pay(
+        &[],
+        &[
+            (100, alice),
+            (90, alice),
+            (80, bob),
+            (70, bob),
+            (800, alice),
+            (15, alice),
+        ],
+    );
+    let c: Vec<CoinId> = (0..6).map(|index| mint.coin_id(1, index)).collect();
+    let b1 = node.add_block(Block::genesis().id(), vec![mint]);
+    let to_bob = pay(&[c[0]], &[(50, bob)]);
+    let bob_50 = to_bob.coin_id(2, 0);
+    let b2 = node.add_block(b1, vec![to_bob]);
+    let merge = pay(&[c[2], c[4]], &[(880, alice)]);
+    let alice_880 = merge.coin_id(3, 0);
+    let to_bob = pay(&[alice_880], &[(300, bob)]);
+    let bob_300 = to_bob.coin_id(3, 0);
+    let b3 = node.add_block(b2, vec![merge.clone(), to_bob]);
+    let change = pay(&[c[5]], &[(10, alice)]);
+    let alice_10 = change.coin_id(4, 0);
+    node.add_block_as_best(b3, vec![change]);
+    wallet.sync(&node);
+    check_confirmations(&wallet, &[alice, bob], &node, "at height 4");
+    assert_eq!(
+        wallet.coins_with_confirmations_of(alice),
+        Ok(HashSet::from([(c[1], 90, 4), (alice_10, 10, 1)]))
+    );
+    assert_eq!(
+        wallet.coins_with_confirmations_of(bob),
+        Ok(HashSet::from([
+            (c[3], 70, 4),
+            (bob_50, 50, 3),
+            (bob_300, 300, 2)
+        ]))
+    );
+    assert_eq!(wallet.total_assets_of_with_confirmations(alice, 2), Ok(90));
+    assert_eq!(wallet.total_assets_of_with_confirmations(bob, 3), Ok(120));
+    assert_eq!(wallet.net_worth_with_confirmations(1), 520);
+    assert_eq!(wallet.net_worth_with_confirmations(2), 510);
+
+    // A shorter fork drops blocks 3 and 4: what they spent is back, with 3 confirmations.
+    let marker = pay(&[], &[(123, Address::Custom(123))]);
+    node.add_block_as_best(b2, vec![marker]);
+    wallet.sync(&node);
+    check_confirmations(&wallet, &[alice, bob], &node, "at the marker block");
+    assert_eq!(wallet.total_assets_of_with_confirmations(alice, 3), Ok(905));
+    assert_eq!(wallet.total_assets_of_with_confirmations(bob, 3), Ok(150));
+    assert_eq!(wallet.net_worth_with_confirmations(3), 1055);
+
+    // Block 3 comes back without the payment to Bob, and the 880 is 2 blocks deep.
+    let b3 = node.add_block(b2, vec![merge]);
+    let change = pay(&[c[1]], &[(30, alice)]);
+    node.add_block_as_best(b3, vec![change]);
+    wallet.sync(&node);
+    check_confirmations(&wallet, &[alice, bob], &node, "after the reorg");
+    assert_eq!(wallet.total_assets_of_with_confirmations(alice, 2), Ok(895));
+    assert_eq!(wallet.total_assets_of_with_confirmations(alice, 3), Ok(15));
+    assert_eq!(wallet.net_worth_with_confirmations(3), 135);
+    assert_eq!(wallet.net_worth_with_confirmations(0), 1045);
+}
+
+// The reorg of `krayt78_2_tests::deep_reorg_to_short_chain`, with a coin minted to Alice in
+// every block.
+pub fn confirmed_balances_across_a_deep_reorg_to_a_short_chain<F: WalletFactory>()
+where
+    F::Wallet: ConfirmationsWalletApi,
+{
+    let mut chain = ChainBuilder::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    for value in 1..=7 {
+        chain.tx(MAIN, pay(&[], &[(value, Address::Alice)]));
+    }
+    chain.set_best(MAIN);
+    wallet.sync(chain.node());
+    check_confirmations(&wallet, &[Address::Alice], chain.node(), "at height 7");
+    // The coin of block h has 8 - h confirmations.
+    assert_eq!(
+        wallet.total_assets_of_with_confirmations(Address::Alice, 6),
+        Ok(3)
+    );
+    assert_eq!(wallet.net_worth_with_confirmations(7), 1);
+    assert_eq!(wallet.net_worth_with_confirmations(8), 0);
+
+    chain.fork("new", "G");
+    chain.block("new", vec![pay(&[], &[(123, Address::Custom(123))])]);
+    for value in [10, 20, 30] {
+        chain.tx("new", pay(&[], &[(value, Address::Alice)]));
+    }
+    chain.set_best("new");
+    wallet.sync(chain.node());
+    check_confirmations(&wallet, &[Address::Alice], chain.node(), "at height 4");
+    assert_eq!(wallet.net_worth_with_confirmations(1), 60);
+    assert_eq!(wallet.net_worth_with_confirmations(2), 30);
+    assert_eq!(wallet.net_worth_with_confirmations(3), 10);
+    assert_eq!(wallet.net_worth_with_confirmations(4), 0);
+}
+
+pub fn confirmed_balances_follow_random_reorgs<F: WalletFactory>()
+where
+    F::Wallet: ConfirmationsWalletApi,
+{
+    let config = RandomConfig::reorg_heavy();
+    for_each_seed(20, |seed| {
+        let scenario = Scenario::generate(seed, &config);
+        let mut wallet = F::new_wallet(OWNED.into_iter());
+        scenario.replay(&mut RecordingNode::new(), |node| {
+            wallet.sync(node);
+            check_confirmations(&wallet, &OWNED, node, &format!("seed {}", seed));
+        });
+    });
+}
@@ -0,0 +1,96 @@
+//! A global allocator that counts the heap bytes each thread keeps alive.
+//!
+//! A library cannot pick the global allocator of the binaries linking it, so it is only installed
+//! where [`install_counting_allocator!`](crate::install_counting_allocator) is invoked, once per
+//! binary. Without it, [`bytes_retained_by`] returns `None` and the memory tests skip. Counters
+//! are per thread because the test harness runs tests in parallel, which means only allocations
+//! made and freed on the calling thread are seen. Wallets are synced on the test's own thread, so
+//! that covers everything they keep.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Wraps the system allocator and updates the counters of the allocating thread.
+pub struct CountingAllocator;
+
+/// Install [`CountingAllocator`] as the global allocator of the crate it is invoked in.
+///
+/// Binaries that already have a `#[global_allocator]` cannot have both; they skip the memory
+/// tests instead.
+#[macro_export]
+macro_rules! install_counting_allocator {
+    () => {
+        #[global_allocator]
+        static BONECOIN_COUNTING_ALLOCATOR: $crate::counting_alloc::CountingAllocator =
+            $crate::counting_alloc::CountingAllocator;
+    };
+}
+
+thread_local! {
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+fn record(delta: isize) {
+    // `try_with` because allocations still happen while thread locals are torn down.
+    let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + delta));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+/// Heap bytes allocated and not yet freed by the current thread, since it started.
+pub fn live_bytes() -> isize {
+    LIVE_BYTES.with(|live| live.get())
+}
+
+/// Whether [`CountingAllocator`] is the global allocator of this binary.
+pub fn is_installed() -> bool {
+    let before = live_bytes();
+    let probe = std::hint::black_box(Box::new(0u64));
+    let installed = live_bytes() != before;
+    drop(probe);
+    installed
+}
+
+/// Run `f` and return how many more heap bytes the current thread holds afterwards, or `None`
+/// if the counting allocator is not installed, in which case `f` still runs.
+///
+/// Whatever `f` stores in values that outlive it, like a wallet it syncs, is counted; whatever
+/// it allocates and frees before returning is not.
+pub fn bytes_retained_by(f: impl FnOnce()) -> Option<isize> {
+    if !is_installed() {
+        f();
+        return None;
+    }
+    let before = live_bytes();
+    f();
+    Some(live_bytes() - before)
+}
@@ -0,0 +1,270 @@
+//! Build chains from the ASCII fork diagrams the tests already draw.
+//!
+//! ```text
+//!           B1 - B2 - B3 (discard)
+//!         /
+//!     G
+//!         \
+//!           C1 - C2 - C3 - C4 - C5* (new wallet state)
+//! ```
+//!
+//! The rules are:
+//!
+//! - A label is a run of letters, digits and `_`. `G` is genesis and must appear exactly once.
+//! - Labels on the same line joined by `-` (any number of them) form a chain, left to right.
+//! - A `/` or `\` forks a line off another one. `\` hangs the nearest line below it off the
+//!   nearest line above, `/` does the opposite. The parent is the rightmost label of the parent
+//!   line that starts at or before the slash. A slash may also sit on the child line itself,
+//!   before its first label.
+//! - Exactly one label is followed by `*`; that block becomes the best block.
+//! - Anything in parentheses is a comment. A leading `//` on every line is ignored, so diagrams
+//!   can be copied straight out of a test.
+//!
+//! Blocks that get no transactions from the table receive a marker transaction, so two empty
+//! blocks with the same parent are still distinct blocks, like the diagram says.
+
+use crate::chain_builder::ChainBuilder;
+use bonecoin_core::*;
+use std::collections::*;
+
+/// Owner of the marker coins put into otherwise empty diagram blocks.
+pub const DIAGRAM_MARKER_OWNER: Address = Address::Custom(0xD1A6);
+
+#[derive(Clone, Debug)]
+struct DiagramBlock {
+    name: String,
+    parent: String,
+}
+
+/// A parsed fork diagram.
+#[derive(Clone, Debug)]
+pub struct Diagram {
+    /// Blocks ordered so that every parent comes before its children, in reading order
+    /// otherwise.
+    blocks: Vec<DiagramBlock>,
+    heights: HashMap<String, u64>,
+    best: String,
+}
+
+struct Label {
+    name: String,
+    column: usize,
+    best: bool,
+}
+
+struct Line {
+    labels: Vec<Label>,
+    connectors: Vec<(char, usize)>,
+}
+
+fn tokenize(line: &str) -> Line {
+    let chars: Vec<char> = line.chars().collect();
+    let mut labels: Vec<Label> = Vec::new();
+    let mut connectors = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_ascii_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            labels.push(Label {
+                name: chars[start..i].iter().collect(),
+                column: start,
+                best: false,
+            });
+            continue;
+        }
+        match c {
+            '*' => {
+                labels
+                    .last_mut()
+                    .unwrap_or_else(|| panic!("`*` must follow a label: {}", line))
+                    .best = true;
+            }
+            '(' => {
+                while i < chars.len() && chars[i] != ')' {
+                    i += 1;
+                }
+            }
+            '/' | '\\' => {
+                assert!(
+                    labels.is_empty(),
+                    "a fork must come before the labels of its line: {}",
+                    line
+                );
+                connectors.push((c, i));
+            }
+            '-' | ' ' | '\t' => {}
+            other => panic!("unexpected character {:?} in diagram line: {}", other, line),
+        }
+        i += 1;
+    }
+
+    Line { labels, connectors }
+}
+
+impl Diagram {
+    /// Parse a diagram, panicking with a description of the problem if it is malformed.
+    pub fn parse(text: &str) -> Diagram {
+        let lines: Vec<Line> = text
+            .lines()
+            .map(|line| line.trim_start().strip_prefix("//").unwrap_or(line))
+            .map(tokenize)
+            .collect();
+
+        let label_lines: Vec<usize> = (0..lines.len())
+            .filter(|&i| !lines[i].labels.is_empty())
+            .collect();
+        let nearest_above = |i: usize| label_lines.iter().rev().find(|&&l| l < i).copied();
+        let nearest_below = |i: usize| label_lines.iter().find(|&&l| l > i).copied();
+
+        // Parent of the first label of every label line, found through the slashes.
+        let mut line_parents: HashMap<usize, String> = HashMap::new();
+        for (index, line) in lines.iter().enumerate() {
+            for &(connector, column) in &line.connectors {
+                let on_label_line = !line.labels.is_empty();
+                let (parent_line, child_line) = match (connector, on_label_line) {
+                    ('\\', false) => (nearest_above(index), nearest_below(index)),
+                    ('/', false) => (nearest_below(index), nearest_above(index)),
+                    ('\\', true) => (nearest_above(index), Some(index)),
+                    _ => (nearest_below(index), Some(index)),
+                };
+                let (parent_line, child_line) = match (parent_line, child_line) {
+                    (Some(parent), Some(child)) => (parent, child),
+                    _ => panic!("fork {:?} in column {} connects nothing", connector, column),
+                };
+                let parent = lines[parent_line]
+                    .labels
+                    .iter()
+                    .rev()
+                    .find(|label| label.column <= column)
+                    .unwrap_or_else(|| {
+                        panic!("fork {:?} in column {} has no parent", connector, column)
+                    });
+                let previous = line_parents.insert(child_line, parent.name.clone());
+                assert!(
+                    previous.is_none(),
+                    "line starting with {} is forked off twice",
+                    lines[child_line].labels[0].name
+                );
+            }
+        }
+
+        // In reading order, so blocks are built in the same order on every run.
+        let mut parents: Vec<(String, String)> = Vec::new();
+        let mut best = Vec::new();
+        let mut has_genesis = false;
+        for &index in &label_lines {
+            let labels = &lines[index].labels;
+            for (position, label) in labels.iter().enumerate() {
+                if label.best {
+                    best.push(label.name.clone());
+                }
+                if label.name == "G" {
+                    assert!(
+                        position == 0 && !has_genesis,
+                        "G must appear once, at the start of its line"
+                    );
+                    has_genesis = true;
+                    continue;
+                }
+                let parent = if position == 0 {
+                    line_parents
+                        .get(&index)
+                        .cloned()
+                        .unwrap_or_else(|| panic!("{} is not connected to anything", label.name))
+                } else {
+                    labels[position - 1].name.clone()
+                };
+                assert!(
+                    parents.iter().all(|(name, _)| *name != label.name),
+                    "label {} appears twice",
+                    label.name
+                );
+                parents.push((label.name.clone(), parent));
+            }
+        }
+        assert!(has_genesis, "the diagram has no G");
+        assert!(
+            best.len() == 1,
+            "exactly one block must be marked as best with `*`, found {:?}",
+            best
+        );
+
+        let mut heights: HashMap<String, u64> = HashMap::new();
+        heights.insert("G".to_string(), 0);
+        let mut blocks = Vec::new();
+        while blocks.len() < parents.len() {
+            let ready: Vec<(String, String)> = parents
+                .iter()
+                .filter(|(name, parent)| {
+                    !heights.contains_key(name) && heights.contains_key(parent)
+                })
+                .cloned()
+                .collect();
+            assert!(
+                !ready.is_empty(),
+                "the diagram has a block that never reaches G"
+            );
+            for (name, parent) in ready {
+                heights.insert(name.clone(), heights[&parent] + 1);
+                blocks.push(DiagramBlock { name, parent });
+            }
+        }
+
+        Diagram {
+            blocks,
+            heights,
+            best: best.remove(0),
+        }
+    }
+
+    /// Height the block labelled `name` will have, useful to compute coin ids before building.
+    pub fn height(&self, name: &str) -> u64 {
+        *self
+            .heights
+            .get(name)
+            .unwrap_or_else(|| panic!("no block labelled {} in the diagram", name))
+    }
+
+    /// Label of the block marked with `*`.
+    pub fn best(&self) -> &str {
+        &self.best
+    }
+
+    /// Build the block tree with the transactions of `transactions` in the labelled blocks and
+    /// the marked block as best.
+    ///
+    /// In the returned builder every label is a branch whose tip is that block, so
+    /// `chain.id("C5")` and `chain.set_best("B3")` work with the diagram labels.
+    pub fn build(&self, transactions: Vec<(&str, Vec<Transaction>)>) -> ChainBuilder {
+        let mut transactions: HashMap<&str, Vec<Transaction>> = transactions.into_iter().collect();
+        for name in transactions.keys() {
+            self.height(name);
+        }
+
+        let mut chain = ChainBuilder::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let body = transactions
+                .remove(block.name.as_str())
+                .unwrap_or_else(|| vec![marker_tx(index as u64)]);
+            chain.fork(&block.name, &block.parent);
+            chain.block(&block.name, body);
+        }
+        chain.set_best(&self.best);
+        chain
+    }
+}
+
+fn marker_tx(index: u64) -> Transaction {
+    Transaction {
+        inputs: vec![Input::dummy()],
+        outputs: vec![Coin {
+            value: index + 1,
+            owner: DIAGRAM_MARKER_OWNER,
+        }],
+    }
+}
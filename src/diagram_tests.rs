@@ -0,0 +1,94 @@
+//! Tests for the bonecoin wallet whose chains come straight from their fork diagrams
+
+use crate::diagram::Diagram;
+use crate::harness::WalletFactory;
+use bonecoin_core::*;
+use std::collections::*;
+
+pub fn same_diagram_builds_the_same_blocks<F: WalletFactory>() {
+    // Three empty blocks on top of A1 and two on top of C2, told apart by marker transactions
+    // only.
+    let text = r"
+        //              B2 - B3
+        //            /
+        //    G - A1 - A2 - A3
+        //        \
+        //          C2 - C3
+        //            \   D3 - D4*
+        ";
+    let first = Diagram::parse(text).build(vec![]);
+    let second = Diagram::parse(text).build(vec![]);
+    for label in ["A1", "A2", "A3", "B2", "B3", "C2", "C3", "D3", "D4"] {
+        assert_eq!(first.id(label), second.id(label), "block {}", label);
+    }
+
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    wallet.sync(first.node());
+    assert_eq!(wallet.best_height(), 4);
+    assert_eq!(wallet.best_hash(), second.id("D4"));
+}
+
+pub fn diagram_reorg_in_the_middle_with_utxos<F: WalletFactory>() {
+    let diagram = Diagram::parse(
+        r"
+        //                         Old_B4 - Old_B5 (discard)
+        //                       /
+        //     G - B1 -- B2 -- B3
+        //                       \  B4    --  B5* (should reorg the chain here)
+        ",
+    );
+
+    let tx_mint = Transaction {
+        inputs: vec![],
+        outputs: vec![Coin {
+            value: 100,
+            owner: Address::Alice,
+        }],
+    };
+    let minted = tx_mint.coin_id(diagram.height("B2"), 0);
+    let tx_pay = Transaction {
+        inputs: vec![Input {
+            coin_id: minted,
+            signature: Signature::Valid(Address::Alice),
+        }],
+        outputs: vec![
+            Coin {
+                value: 40,
+                owner: Address::Bob,
+            },
+            Coin {
+                value: 60,
+                owner: Address::Alice,
+            },
+        ],
+    };
+    let bob_coin = tx_pay.coin_id(diagram.height("Old_B5"), 0);
+    let alice_change = tx_pay.coin_id(diagram.height("Old_B5"), 1);
+
+    let mut chain = diagram.build(vec![("B2", vec![tx_mint]), ("Old_B5", vec![tx_pay])]);
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
+
+    chain.set_best("Old_B5");
+    wallet.sync(chain.node());
+    assert_eq!(
+        wallet.all_coins_of(Address::Alice),
+        Ok(HashSet::from_iter([(alice_change, 60)]))
+    );
+    assert_eq!(
+        wallet.all_coins_of(Address::Bob),
+        Ok(HashSet::from_iter([(bob_coin, 40)]))
+    );
+
+    chain.set_best(diagram.best());
+    wallet.sync(chain.node());
+    assert_eq!(wallet.best_height(), 5);
+    assert_eq!(
+        wallet.all_coins_of(Address::Alice),
+        Ok(HashSet::from_iter([(minted, 100)]))
+    );
+    assert_eq!(wallet.total_assets_of(Address::Bob), Ok(0));
+    assert_eq!(
+        wallet.coin_details(&bob_coin),
+        Err(WalletError::UnknownCoin)
+    );
+}
@@ -0,0 +1,153 @@
+//! Differential testing of a wallet against the [`OracleWallet`].
+//!
+//! Instead of hand-computing expected balances, a test drives a [`RecordingNode`] and syncs a
+//! [`DifferentialWallet`]. After every sync all observable state of the wallet under test is
+//! compared with the oracle's.
+
+use crate::oracle::OracleWallet;
+use crate::recording_node::{ChainRecord, RecordingNode};
+use bonecoin_core::*;
+use std::collections::*;
+
+/// Part of the message of every panic of [`assert_matches_oracle`], which tells a wallet that
+/// disagrees with the oracle apart from anything else that panics.
+pub const MISMATCH: &str = "differs from oracle";
+
+/// Compare every query of `WalletApi` between `wallet` and `oracle`.
+///
+/// Balances are checked for the owned `addresses` and for every address that ever received a
+/// coin in the recorded tree, so `ForeignAddress` handling is covered too. Coin details are
+/// checked for every coin created anywhere in the tree, including ones on abandoned forks.
+pub fn assert_matches_oracle(
+    wallet: &impl WalletApi,
+    oracle: &OracleWallet,
+    addresses: &[Address],
+    record: &ChainRecord,
+) {
+    assert_eq!(
+        wallet.best_height(),
+        oracle.best_height(),
+        "best_height {}",
+        MISMATCH
+    );
+    assert_eq!(
+        wallet.best_hash(),
+        oracle.best_hash(),
+        "best_hash {}",
+        MISMATCH
+    );
+    assert_eq!(
+        wallet.net_worth(),
+        oracle.net_worth(),
+        "net_worth {}",
+        MISMATCH
+    );
+
+    let created = record.all_created_coins();
+
+    let mut probed: Vec<Address> = addresses.to_vec();
+    for (_, coin) in &created {
+        if !probed.contains(&coin.owner) {
+            probed.push(coin.owner);
+        }
+    }
+    for address in probed {
+        assert_eq!(
+            wallet.total_assets_of(address),
+            oracle.total_assets_of(address),
+            "total_assets_of({:?}) {}",
+            address,
+            MISMATCH
+        );
+        assert_eq!(
+            wallet.all_coins_of(address),
+            oracle.all_coins_of(address),
+            "all_coins_of({:?}) {}",
+            address,
+            MISMATCH
+        );
+    }
+
+    for (coin_id, _) in &created {
+        assert_eq!(
+            wallet.coin_details(coin_id),
+            oracle.coin_details(coin_id),
+            "coin_details({:?}) {}",
+            coin_id,
+            MISMATCH
+        );
+    }
+}
+
+/// A wallet under test paired with an oracle that follows the same node.
+pub struct DifferentialWallet<W: WalletApi> {
+    wallet: W,
+    oracle: OracleWallet,
+    addresses: Vec<Address>,
+}
+
+impl<W: WalletApi> DifferentialWallet<W> {
+    /// Pair `wallet`, which must own exactly `addresses`, with a fresh oracle reading `node`.
+    pub fn new(wallet: W, addresses: Vec<Address>, node: &RecordingNode) -> Self {
+        let oracle = OracleWallet::new(addresses.clone().into_iter(), node.record());
+        DifferentialWallet {
+            wallet,
+            oracle,
+            addresses,
+        }
+    }
+
+    /// Sync both wallets and panic on the first observable difference.
+    pub fn sync(&mut self, node: &RecordingNode) {
+        self.wallet.sync(node);
+        self.oracle.sync(node);
+        self.check(node);
+    }
+
+    /// Compare both wallets without syncing.
+    pub fn check(&self, node: &RecordingNode) {
+        let record = node.record();
+        assert_matches_oracle(
+            &self.wallet,
+            &self.oracle,
+            &self.addresses,
+            &record.borrow(),
+        );
+    }
+
+    pub fn wallet(&self) -> &W {
+        &self.wallet
+    }
+
+    pub fn oracle(&self) -> &OracleWallet {
+        &self.oracle
+    }
+}
+
+/// Everything observable about a wallet, for the given addresses.
+#[derive(Debug, PartialEq)]
+pub struct WalletState {
+    pub best_height: u64,
+    pub best_hash: BlockId,
+    pub net_worth: u64,
+    pub balances: Vec<(Address, Result<u64, WalletError>)>,
+    pub coins: Vec<(Address, Result<HashSet<(CoinId, u64)>, WalletError>)>,
+}
+
+impl WalletState {
+    pub fn of(wallet: &impl WalletApi, addresses: &[Address]) -> Self {
+        WalletState {
+            best_height: wallet.best_height(),
+            best_hash: wallet.best_hash(),
+            net_worth: wallet.net_worth(),
+            balances: addresses
+                .iter()
+                .map(|address| (*address, wallet.total_assets_of(*address)))
+                .collect(),
+            coins: addresses
+                .iter()
+                .map(|address| (*address, wallet.all_coins_of(*address)))
+                .collect(),
+        }
+    }
+}
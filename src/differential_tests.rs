@@ -0,0 +1,223 @@
+//! Scenarios checked against the oracle wallet instead of hand-computed expectations
+
+use crate::differential::DifferentialWallet;
+use crate::harness::WalletFactory;
+use crate::recording_node::RecordingNode;
+use bonecoin_core::*;
+
+fn differential_wallet<F: WalletFactory>(
+    addresses: Vec<Address>,
+    node: &RecordingNode,
+) -> DifferentialWallet<F::Wallet> {
+    let wallet = F::new_wallet(addresses.clone().into_iter());
+    DifferentialWallet::new(wallet, addresses, node)
+}
+
+fn mint(value: u64, owner: Address) -> Transaction {
+    Transaction {
+        inputs: vec![Input::dummy()],
+        outputs: vec![Coin { value, owner }],
+    }
+}
+
+fn spend(coin_id: CoinId, outputs: Vec<Coin>) -> Transaction {
+    Transaction {
+        inputs: vec![Input {
+            coin_id,
+            signature: Signature::Invalid,
+        }],
+        outputs,
+    }
+}
+
+pub fn oracle_agrees_with_hand_computed_values<F: WalletFactory>() {
+    let mut node = RecordingNode::new();
+    let mut wallet = differential_wallet::<F>(vec![Address::Alice, Address::Bob], &node);
+
+    let tx_mint = Transaction {
+        inputs: vec![],
+        outputs: vec![
+            Coin {
+                value: 100,
+                owner: Address::Alice,
+            },
+            Coin {
+                value: 15,
+                owner: Address::Alice,
+            },
+            Coin {
+                value: 120,
+                owner: Address::Bob,
+            },
+        ],
+    };
+    let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint]);
+    wallet.sync(&node);
+
+    // Same numbers as kwar13_tests::blockchain_creation, this time asked of the oracle.
+    assert_eq!(wallet.oracle().best_height(), 1);
+    assert_eq!(wallet.oracle().best_hash(), b1_id);
+    assert_eq!(
+        wallet.oracle().total_assets_of(Address::Alice),
+        Ok(100 + 15)
+    );
+    assert_eq!(wallet.oracle().total_assets_of(Address::Bob), Ok(120));
+    assert_eq!(wallet.oracle().net_worth(), 100 + 15 + 120);
+}
+
+//    G - B1 - B2 - B3 - Old_B4 - Old_B5 - Old_B6 (discard)
+//                   \
+//                     B4 - B5 (new wallet state)
+pub fn reorg_with_utxos_in_chain_history<F: WalletFactory>() {
+    let mut node = RecordingNode::new();
+    let mut wallet = differential_wallet::<F>(vec![Address::Alice], &node);
+
+    let b1_id = node.add_block(Block::genesis().id(), vec![]);
+    let b2_id = node.add_block(b1_id, vec![]);
+    let b3_id = node.add_block(b2_id, vec![mint(50, Address::Alice)]);
+    let old_b4_id = node.add_block(b3_id, vec![]);
+    let old_b5_id = node.add_block(old_b4_id, vec![]);
+    node.add_block_as_best(old_b5_id, vec![mint(100, Address::Alice)]);
+    wallet.sync(&node);
+
+    let b4_id = node.add_block_as_best(b3_id, vec![mint(200, Address::Alice)]);
+    node.add_block_as_best(b4_id, vec![]);
+    wallet.sync(&node);
+}
+
+pub fn spend_and_reorg_back_and_forth<F: WalletFactory>() {
+    let mut node = RecordingNode::new();
+    let mut wallet = differential_wallet::<F>(vec![Address::Alice, Address::Bob], &node);
+
+    let tx_mint = mint(100, Address::Alice);
+    let alice_coin = tx_mint.coin_id(1, 0);
+    let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint]);
+    wallet.sync(&node);
+
+    // Alice pays Bob and Charlie on one fork
+    let tx_pay = spend(
+        alice_coin,
+        vec![
+            Coin {
+                value: 60,
+                owner: Address::Bob,
+            },
+            Coin {
+                value: 30,
+                owner: Address::Charlie,
+            },
+        ],
+    );
+    let bob_coin = tx_pay.coin_id(2, 0);
+    let a2_id = node.add_block_as_best(b1_id, vec![tx_pay]);
+    let a3_id = node.add_block_as_best(
+        a2_id,
+        vec![spend(
+            bob_coin,
+            vec![Coin {
+                value: 55,
+                owner: Address::Alice,
+            }],
+        )],
+    );
+    wallet.sync(&node);
+
+    // ... which gets reorged away by a longer fork where the coin is burned
+    let c2_id = node.add_block(b1_id, vec![spend(alice_coin, vec![])]);
+    let c3_id = node.add_block(c2_id, vec![mint(7, Address::Bob)]);
+    node.add_block_as_best(c3_id, vec![]);
+    wallet.sync(&node);
+
+    // ... and then back again
+    node.add_block_as_best(a3_id, vec![mint(1, Address::Alice)]);
+    wallet.sync(&node);
+
+    // Syncing twice must not change anything
+    wallet.sync(&node);
+}
+
+pub fn spend_in_same_block_and_reorg_to_genesis<F: WalletFactory>() {
+    let mut node = RecordingNode::new();
+    let mut wallet = differential_wallet::<F>(vec![Address::Alice, Address::Bob], &node);
+
+    let tx_mint = mint(100, Address::Alice);
+    let tx_pay = spend(
+        tx_mint.coin_id(1, 0),
+        vec![Coin {
+            value: 100,
+            owner: Address::Bob,
+        }],
+    );
+    let tx_away = spend(
+        tx_pay.coin_id(1, 0),
+        vec![Coin {
+            value: 100,
+            owner: Address::Custom(100),
+        }],
+    );
+    node.add_block_as_best(Block::genesis().id(), vec![tx_mint, tx_pay, tx_away]);
+    wallet.sync(&node);
+
+    node.set_best(Block::genesis().id());
+    wallet.sync(&node);
+}
+
+pub fn overflowing_balances<F: WalletFactory>() {
+    let mut node = RecordingNode::new();
+    let mut wallet = differential_wallet::<F>(vec![Address::Alice, Address::Bob], &node);
+
+    let tx_mint = Transaction {
+        inputs: vec![Input::dummy()],
+        outputs: vec![
+            Coin {
+                value: 12,
+                owner: Address::Alice,
+            },
+            Coin {
+                value: u64::MAX,
+                owner: Address::Alice,
+            },
+            Coin {
+                value: 53,
+                owner: Address::Bob,
+            },
+        ],
+    };
+    node.add_block_as_best(Block::genesis().id(), vec![tx_mint]);
+    wallet.sync(&node);
+}
+
+pub fn wallet_created_transactions_round_trip<F: WalletFactory>() {
+    let mut node = RecordingNode::new();
+    let mut wallet = differential_wallet::<F>(vec![Address::Alice, Address::Bob], &node);
+
+    let tx_mint = Transaction {
+        inputs: vec![],
+        outputs: vec![
+            Coin {
+                value: 20,
+                owner: Address::Alice,
+            },
+            Coin {
+                value: 12,
+                owner: Address::Alice,
+            },
+            Coin {
+                value: 53,
+                owner: Address::Bob,
+            },
+        ],
+    };
+    let mut best = node.add_block_as_best(Block::genesis().id(), vec![tx_mint]);
+    wallet.sync(&node);
+
+    // The change address is up to the wallet, so only the wallet's own transactions are mined.
+    for (value, burn) in [(30, 3), (11, 0), (40, 1)] {
+        let tx = wallet
+            .wallet()
+            .create_automatic_transaction(Address::Charlie, value, burn)
+            .unwrap();
+        best = node.add_block_as_best(best, vec![tx]);
+        wallet.sync(&node);
+    }
+}
@@ -1,17 +1,17 @@
 // Written by esteblock
 
 use bonecoin_core::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
 use std::collections::HashSet;
 
 /// Simple helper to initialize a wallet with just one account.
-fn wallet_with_alice() -> Wallet {
-    Wallet::new(vec![Address::Alice].into_iter())
+fn wallet_with_alice<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice].into_iter())
 }
 
 /// Simple helper to initialize a wallet with just one account.
-fn wallet_with_alice_and_bob() -> Wallet {
-    Wallet::new(vec![Address::Alice, Address::Bob].into_iter())
+fn wallet_with_alice_and_bob<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice, Address::Bob].into_iter())
 }
 
 
@@ -29,8 +29,7 @@ fn marker_tx(value: u64) -> Transaction {
 //                       /
 //     G - B1 -- B2 -- B3
 //                       \  B4    --  B5 (should reorg the chain here)
-#[test]
-fn reports_correct_ancestors_even_after_reorg_in_the_middle() {
+pub fn reports_correct_ancestors_even_after_reorg_in_the_middle<F: WalletFactory>() {
     let mut node = MockNode::new();
 
     // Build the permanent blocks
@@ -71,8 +70,7 @@ fn reports_correct_ancestors_even_after_reorg_in_the_middle() {
 }
 
 
-#[test]
-fn reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic() {
+pub fn reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic<F: WalletFactory>() {
     let mut node = MockNode::new();
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![]); // B1 is EMPTY
 
@@ -112,7 +110,7 @@ fn reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic() {
 
     let b3_id = node.add_block_as_best(b2_id, vec![tx_alice_bob_0.clone()]); // B3 WITH TXS
 
-    let mut wallet = wallet_with_alice_and_bob();
+    let mut wallet = wallet_with_alice_and_bob::<F>();
     wallet.sync(&node);
     assert_eq!(wallet.best_height(), 3);
     assert_eq!(wallet.best_hash(), b3_id);
@@ -274,8 +272,7 @@ fn reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic() {
 
 
 
-#[test]
-fn reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic_and_reor_again_to_previous() {
+pub fn reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic_and_reor_again_to_previous<F: WalletFactory>() {
     let mut node = MockNode::new();
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![]); // B1 is EMPTY
 
@@ -317,7 +314,7 @@ fn reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic_and_reor
 
     let b3_id = node.add_block_as_best(b2_id, vec![tx_alice_bob_0.clone()]); // B3 WITH TXS
 
-    let mut wallet = wallet_with_alice_and_bob();
+    let mut wallet = wallet_with_alice_and_bob::<F>();
     wallet.sync(&node);
     assert_eq!(wallet.best_height(), 3);
     assert_eq!(wallet.best_hash(), b3_id);
@@ -0,0 +1,153 @@
+//! Optional wallet extension: what changed during `sync`, as a queue of events.
+//!
+//! A wallet implementing [`EventWalletApi`] queues [`WalletEvent`]s while it syncs, and the
+//! application drains them instead of polling the balances after every sync. For every block
+//! that leaves the best chain, from the old tip down to the fork point, it queues
+//! `BlockDisconnected` followed by the coin events of connecting that block, reversed and with
+//! `CoinReceived` and `CoinSpent` swapped. Then, for every block that joins the best chain from
+//! the fork point up, `BlockConnected` followed by the coin events of its transactions in order:
+//! for each transaction, `CoinSpent` for the owned coins it spends in input order, then
+//! `CoinReceived` for the owned coins it creates in output order. A sync that disconnected any
+//! block ends with `ReorgCompleted`. A sync that changes nothing queues nothing.
+//!
+//! Replaying the coin events alone therefore gives the owned coins of the best chain.
+//!
+//! Enable the tests with `wallet_test_suite!(name, factory, events)`.
+
+use crate::recording_node::{BlockRecord, ChainRecord};
+use crate::tx_validation::ValidatedWallet;
+use bonecoin_core::*;
+use std::collections::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WalletEvent {
+    BlockConnected {
+        height: u64,
+        block: BlockId,
+    },
+    BlockDisconnected {
+        height: u64,
+        block: BlockId,
+    },
+    /// The coin joined the owned coins of the best chain.
+    CoinReceived {
+        coin_id: CoinId,
+        coin: Coin,
+    },
+    /// The coin left the owned coins of the best chain.
+    CoinSpent {
+        coin_id: CoinId,
+        coin: Coin,
+    },
+    /// Blocks down to `fork_point` were disconnected, and the ones up to `new_tip` connected.
+    ReorgCompleted {
+        fork_point: BlockId,
+        old_tip: BlockId,
+        new_tip: BlockId,
+    },
+}
+
+pub trait EventWalletApi: WalletApi {
+    /// The events queued since the last call, oldest first. The queue is empty afterwards.
+    fn drain_events(&mut self) -> Vec<WalletEvent>;
+}
+
+impl<W: EventWalletApi> EventWalletApi for ValidatedWallet<W> {
+    fn drain_events(&mut self) -> Vec<WalletEvent> {
+        self.wallet_mut().drain_events()
+    }
+}
+
+/// Apply the block to `owned` and return its coin events.
+fn connect(
+    block: &BlockRecord,
+    addresses: &[Address],
+    owned: &mut HashMap<CoinId, Coin>,
+) -> Vec<WalletEvent> {
+    let mut events = Vec::new();
+    for tx in &block.transactions {
+        for input in &tx.inputs {
+            if let Some(coin) = owned.remove(&input.coin_id) {
+                events.push(WalletEvent::CoinSpent {
+                    coin_id: input.coin_id,
+                    coin,
+                });
+            }
+        }
+        for (index, coin) in tx.outputs.iter().enumerate() {
+            if addresses.contains(&coin.owner) {
+                let coin_id = tx.coin_id(block.height, index);
+                owned.insert(coin_id, coin.clone());
+                events.push(WalletEvent::CoinReceived {
+                    coin_id,
+                    coin: coin.clone(),
+                });
+            }
+        }
+    }
+    events
+}
+
+/// The events of a wallet owning `addresses` that syncs from `old_tip` to `new_tip` of
+/// `record`, replayed from genesis.
+pub fn expected_events(
+    record: &ChainRecord,
+    addresses: &[Address],
+    old_tip: BlockId,
+    new_tip: BlockId,
+) -> Vec<WalletEvent> {
+    let old_chain = record.chain_to(old_tip);
+    let new_chain = record.chain_to(new_tip);
+    let common = old_chain
+        .iter()
+        .zip(&new_chain)
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let mut owned = HashMap::new();
+    for block_id in &old_chain[..common] {
+        connect(&record.blocks[block_id], addresses, &mut owned);
+    }
+    let mut abandoned = owned.clone();
+    let disconnected: Vec<(BlockId, Vec<WalletEvent>)> = old_chain[common..]
+        .iter()
+        .map(|block_id| {
+            let block_events = connect(&record.blocks[block_id], addresses, &mut abandoned);
+            (*block_id, block_events)
+        })
+        .collect();
+
+    let mut events = Vec::new();
+    for (block_id, block_events) in disconnected.into_iter().rev() {
+        events.push(WalletEvent::BlockDisconnected {
+            height: record.height_of(block_id),
+            block: block_id,
+        });
+        events.extend(block_events.into_iter().rev().map(|event| match event {
+            WalletEvent::CoinReceived { coin_id, coin } => WalletEvent::CoinSpent { coin_id, coin },
+            WalletEvent::CoinSpent { coin_id, coin } => WalletEvent::CoinReceived { coin_id, coin },
+            other => other,
+        }));
+    }
+    for block_id in &new_chain[common..] {
+        let block = &record.blocks[block_id];
+        events.push(WalletEvent::BlockConnected {
+            height: block.height,
+            block: *block_id,
+        });
+        events.extend(connect(block, addresses, &mut owned));
+    }
+
+    if common < old_chain.len() {
+        events.push(WalletEvent::ReorgCompleted {
+            fork_point: if common == 0 {
+                Block::genesis().id()
+            } else {
+                old_chain[common - 1]
+            },
+            old_tip,
+            new_tip,
+        });
+    }
+    events
+}
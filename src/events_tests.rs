@@ -0,0 +1,212 @@
+//! Events queued by `sync`, in order
+//!
+//! Only for wallets implementing [`EventWalletApi`].
+
+use crate::events::{expected_events, EventWalletApi, WalletEvent};
+use crate::harness::WalletFactory;
+use crate::random_scenario::{for_each_seed, RandomConfig, Scenario, OWNED};
+use crate::recording_node::RecordingNode;
+use bonecoin_core::*;
+use std::collections::*;
+
+fn connected(height: u64, block: BlockId) -> WalletEvent {
+    WalletEvent::BlockConnected { height, block }
+}
+
+fn disconnected(height: u64, block: BlockId) -> WalletEvent {
+    WalletEvent::BlockDisconnected { height, block }
+}
+
+fn received(tx: &Transaction, height: u64, index: usize) -> WalletEvent {
+    WalletEvent::CoinReceived {
+        coin_id: tx.coin_id(height, index),
+        coin: tx.outputs[index].clone(),
+    }
+}
+
+fn spent(tx: &Transaction, height: u64, index: usize) -> WalletEvent {
+    WalletEvent::CoinSpent {
+        coin_id: tx.coin_id(height, index),
+        coin: tx.outputs[index].clone(),
+    }
+}
+
+fn mint(value: u64) -> Transaction {
+    Transaction {
+        inputs: vec![Input::dummy()],
+        outputs: vec![Coin {
+            value,
+            owner: Address::Alice,
+        }],
+    }
+}
+
+// The topology of `bigtava_tests::test_reorgs_with_utxos_in_chain_history`:
+//
+//                 Old_B4 - Old_B5 - Old_B6 (mints 100)
+//               /
+//     G - B1 - B2 - B3 (mints 50)
+//               \
+//                 B4 (mints 200) - B5
+pub fn events_of_a_reorg_with_utxos_in_chain_history<F: WalletFactory>()
+where
+    F::Wallet: EventWalletApi,
+{
+    let mut node = RecordingNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    let (tx_1, tx_2, tx_new) = (mint(50), mint(100), mint(200));
+
+    let b1 = node.add_block(Block::genesis().id(), vec![]);
+    let b2 = node.add_block(b1, vec![]);
+    let b3 = node.add_block(b2, vec![tx_1.clone()]);
+    let old_b4 = node.add_block(b3, vec![]);
+    let old_b5 = node.add_block(old_b4, vec![]);
+    let old_b6 = node.add_block_as_best(old_b5, vec![tx_2.clone()]);
+    assert!(wallet.drain_events().is_empty());
+    wallet.sync(&node);
+    assert_eq!(
+        wallet.drain_events(),
+        vec![
+            connected(1, b1),
+            connected(2, b2),
+            connected(3, b3),
+            received(&tx_1, 3, 0),
+            connected(4, old_b4),
+            connected(5, old_b5),
+            connected(6, old_b6),
+            received(&tx_2, 6, 0),
+        ]
+    );
+
+    // Drained means empty, and a sync without news queues nothing.
+    assert!(wallet.drain_events().is_empty());
+    wallet.sync(&node);
+    assert!(wallet.drain_events().is_empty());
+
+    let new_b4 = node.add_block_as_best(b3, vec![tx_new.clone()]);
+    let new_b5 = node.add_block_as_best(new_b4, vec![]);
+    wallet.sync(&node);
+    assert_eq!(
+        wallet.drain_events(),
+        vec![
+            disconnected(6, old_b6),
+            spent(&tx_2, 6, 0),
+            disconnected(5, old_b5),
+            disconnected(4, old_b4),
+            connected(4, new_b4),
+            received(&tx_new, 4, 0),
+            connected(5, new_b5),
+            WalletEvent::ReorgCompleted {
+                fork_point: b3,
+                old_tip: old_b6,
+                new_tip: new_b5,
+            },
+        ]
+    );
+}
+
+pub fn events_of_coins_spent_in_the_block_that_creates_them<F: WalletFactory>()
+where
+    F::Wallet: EventWalletApi,
+{
+    let mut node = RecordingNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    let b1 = node.add_block_as_best(Block::genesis().id(), vec![]);
+    wallet.sync(&node);
+    wallet.drain_events();
+
+    // Alice receives 100, pays 70 to Bob and keeps 30, all in the same block.
+    let funding = mint(100);
+    let payment = Transaction {
+        inputs: vec![Input {
+            coin_id: funding.coin_id(2, 0),
+            signature: Signature::Valid(Address::Alice),
+        }],
+        outputs: vec![
+            Coin {
+                value: 70,
+                owner: Address::Bob,
+            },
+            Coin {
+                value: 30,
+                owner: Address::Alice,
+            },
+        ],
+    };
+    let b2 = node.add_block_as_best(b1, vec![funding.clone(), payment.clone()]);
+    wallet.sync(&node);
+    assert_eq!(
+        wallet.drain_events(),
+        vec![
+            connected(2, b2),
+            received(&funding, 2, 0),
+            spent(&funding, 2, 0),
+            received(&payment, 2, 1),
+        ]
+    );
+
+    // Going back to B1 undoes the block in reverse.
+    node.set_best(b1);
+    wallet.sync(&node);
+    assert_eq!(
+        wallet.drain_events(),
+        vec![
+            disconnected(2, b2),
+            spent(&payment, 2, 1),
+            received(&funding, 2, 0),
+            spent(&funding, 2, 0),
+            WalletEvent::ReorgCompleted {
+                fork_point: b1,
+                old_tip: b2,
+                new_tip: b1,
+            },
+        ]
+    );
+}
+
+pub fn events_follow_random_reorgs<F: WalletFactory>()
+where
+    F::Wallet: EventWalletApi,
+{
+    let config = RandomConfig::reorg_heavy();
+    for_each_seed(20, |seed| {
+        let scenario = Scenario::generate(seed, &config);
+        let mut wallet = F::new_wallet(OWNED.into_iter());
+        let mut tip = Block::genesis().id();
+        let mut owned: HashSet<(CoinId, u64)> = HashSet::new();
+
+        scenario.replay(&mut RecordingNode::new(), |node| {
+            wallet.sync(node);
+            let record = node.record();
+            let best = record.borrow().best;
+            let events = wallet.drain_events();
+            assert_eq!(
+                events,
+                expected_events(&record.borrow(), &OWNED, tip, best),
+                "seed {}: events of the sync from {:?} to {:?}",
+                seed,
+                tip,
+                best
+            );
+            tip = best;
+
+            // The coin events alone keep track of the owned coins.
+            for event in events {
+                match event {
+                    WalletEvent::CoinReceived { coin_id, coin } => {
+                        owned.insert((coin_id, coin.value));
+                    }
+                    WalletEvent::CoinSpent { coin_id, coin } => {
+                        owned.remove(&(coin_id, coin.value));
+                    }
+                    _ => {}
+                }
+            }
+            let mut coins = HashSet::new();
+            for address in OWNED {
+                coins.extend(wallet.all_coins_of(address).unwrap());
+            }
+            assert_eq!(owned, coins, "seed {}: coins replayed from events", seed);
+        });
+    });
+}
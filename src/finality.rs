@@ -0,0 +1,84 @@
+//! Optional wallet extension: a finality depth that bounds the reorg undo data.
+//!
+//! A plain wallet must be able to undo any block, back to genesis, so the undo data it keeps
+//! grows with the chain. A wallet implementing [`FinalityWalletApi`] can be given a finality
+//! depth instead: once its best block is at height `h`, every block at height `h - depth` or
+//! below is final and its undo data may be dropped. The finalized height never goes down, not
+//! even when a reorg moves the wallet to a shorter chain. Nothing else the wallet keeps may grow
+//! with the chain either: a wallet that also keeps a history or an event queue drops the entries
+//! and the undrained events of final blocks.
+//!
+//! A best chain that forks from the wallet's below the finalized height cannot be followed any
+//! more. `try_sync` reports it with [`FinalityError::ReorgTooDeep`] and leaves the wallet as it
+//! was, so it keeps answering as of its old best block until the node comes back to a chain it
+//! can follow. Without a finality depth, only genesis is final.
+//!
+//! Enable the tests with `wallet_test_suite!(name, factory, finality)`.
+
+use crate::recording_node::ChainRecord;
+use crate::tx_validation::ValidatedWallet;
+use bonecoin_core::*;
+use std::fmt;
+
+/// Why `try_sync` did not follow the node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FinalityError {
+    /// The node's best chain forks from the wallet's at `fork_height`, below the final block
+    /// at `finalized_height`.
+    ReorgTooDeep {
+        fork_height: u64,
+        finalized_height: u64,
+    },
+}
+
+impl fmt::Display for FinalityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FinalityError::ReorgTooDeep {
+                fork_height,
+                finalized_height,
+            } => write!(
+                f,
+                "the best chain forks at height {}, below the final block at height {}",
+                fork_height, finalized_height
+            ),
+        }
+    }
+}
+
+pub trait FinalityWalletApi: WalletApi {
+    /// Keep undo data for the last `depth` blocks only. Blocks `depth` or more below the best
+    /// block become final, starting with the current one.
+    fn set_finality_depth(&mut self, depth: u64);
+
+    /// Height of the highest final block, 0 when no finality depth is set.
+    fn finalized_height(&self) -> u64;
+
+    /// Like `sync`, unless the node's best chain forks below the finalized height. Then the
+    /// wallet stays as it was and the reorg is reported as too deep.
+    fn try_sync(&mut self, node: &MockNode) -> Result<(), FinalityError>;
+}
+
+impl<W: FinalityWalletApi> FinalityWalletApi for ValidatedWallet<W> {
+    fn set_finality_depth(&mut self, depth: u64) {
+        self.wallet_mut().set_finality_depth(depth)
+    }
+
+    fn finalized_height(&self) -> u64 {
+        self.wallet().finalized_height()
+    }
+
+    fn try_sync(&mut self, node: &MockNode) -> Result<(), FinalityError> {
+        self.wallet_mut().try_sync(node)
+    }
+}
+
+/// Height of the last block the chains up to `a` and `b` of `record` have in common.
+pub fn fork_height(record: &ChainRecord, a: BlockId, b: BlockId) -> u64 {
+    record
+        .chain_to(a)
+        .iter()
+        .zip(&record.chain_to(b))
+        .take_while(|(a, b)| a == b)
+        .count() as u64
+}
@@ -0,0 +1,241 @@
+//! Finality depth: bounded undo data, and reorgs too deep to follow
+//!
+//! Only for wallets implementing [`FinalityWalletApi`].
+
+use crate::counting_alloc::bytes_retained_by;
+use crate::differential::WalletState;
+use crate::finality::{fork_height, FinalityError, FinalityWalletApi};
+use crate::harness::WalletFactory;
+use crate::oracle::OracleWallet;
+use crate::random_scenario::{for_each_seed, RandomConfig, Scenario, OWNED};
+use crate::recording_node::RecordingNode;
+use crate::test_helpers::mint_chain;
+use bonecoin_core::*;
+
+/// Finality depth of the reorg tests.
+const DEPTH: u64 = 3;
+
+/// Block 1 mints `utxos` coins to Alice, every later block spends the oldest of them and
+/// creates a new one, so both the UTXO set and the undo data of each block keep their size.
+fn rotating_utxo_set(blocks: u64, utxos: u64) -> MockNode {
+    let mut node = MockNode::new();
+    let mint = Transaction {
+        inputs: vec![],
+        outputs: (0..utxos)
+            .map(|i| Coin {
+                value: 1_000_000 + i,
+                owner: Address::Alice,
+            })
+            .collect(),
+    };
+    let mut unspent: Vec<CoinId> = (0..utxos as usize).map(|i| mint.coin_id(1, i)).collect();
+    let mut parent = node.add_block_as_best(Block::genesis().id(), vec![mint]);
+
+    for height in 2..=blocks {
+        let spent = unspent.remove(0);
+        let tx = Transaction {
+            inputs: vec![Input {
+                coin_id: spent,
+                signature: Signature::Valid(Address::Alice),
+            }],
+            outputs: vec![Coin {
+                value: height,
+                owner: Address::Alice,
+            }],
+        };
+        unspent.push(tx.coin_id(height, 0));
+        parent = node.add_block_as_best(parent, vec![tx]);
+    }
+    node
+}
+
+// `tarekkma_tests::spend_utxo_in_same_block` goes back to genesis, which only a wallet keeping
+// the undo data of every block can do.
+pub fn reorg_to_genesis_below_the_finalized_height<F: WalletFactory>()
+where
+    F::Wallet: FinalityWalletApi,
+{
+    let mut node = RecordingNode::new();
+    let b3 = mint_chain(&mut node, Block::genesis().id(), 3, 0);
+
+    // Without a finality depth only genesis is final, so the wallet follows.
+    let mut unbounded = F::new_wallet(OWNED.into_iter());
+    assert_eq!(unbounded.try_sync(&node), Ok(()));
+    assert_eq!(unbounded.finalized_height(), 0);
+
+    let mut wallet = F::new_wallet(OWNED.into_iter());
+    wallet.set_finality_depth(2);
+    assert_eq!(wallet.try_sync(&node), Ok(()));
+    assert_eq!(wallet.finalized_height(), 1);
+    let synced = WalletState::of(&wallet, &OWNED);
+
+    node.set_best(Block::genesis().id());
+    assert_eq!(unbounded.try_sync(&node), Ok(()));
+    assert_eq!(unbounded.best_height(), 0);
+    assert_eq!(unbounded.net_worth(), 0);
+    assert_eq!(
+        wallet.try_sync(&node),
+        Err(FinalityError::ReorgTooDeep {
+            fork_height: 0,
+            finalized_height: 1,
+        })
+    );
+    assert_eq!(
+        WalletState::of(&wallet, &OWNED),
+        synced,
+        "a reorg too deep to follow changed the wallet"
+    );
+
+    // Back on its own chain, the wallet carries on as if nothing happened.
+    node.set_best(b3);
+    mint_chain(&mut node, b3, 1, 0);
+    assert_eq!(wallet.try_sync(&node), Ok(()));
+    assert_eq!(wallet.best_height(), 4);
+    assert_eq!(wallet.finalized_height(), 2);
+    assert_eq!(wallet.net_worth(), 10);
+}
+
+//     G - B1 - B2 - B3 - B4 - B5 - B6
+//                    \
+//                      B4' - B5' - B6' - B7'
+//                    \
+//                      B4" - B5" - B6" - B7" - B8"
+pub fn reorgs_down_to_the_finalized_height<F: WalletFactory>()
+where
+    F::Wallet: FinalityWalletApi,
+{
+    let mut node = RecordingNode::new();
+    let mut wallet = F::new_wallet(OWNED.into_iter());
+    let mut oracle = OracleWallet::new(OWNED.into_iter(), node.record());
+    wallet.set_finality_depth(DEPTH);
+    assert_eq!(wallet.finalized_height(), 0);
+
+    let b3 = mint_chain(&mut node, Block::genesis().id(), 3, 0);
+    mint_chain(&mut node, b3, 3, 0);
+    assert_eq!(wallet.try_sync(&node), Ok(()));
+    assert_eq!(wallet.finalized_height(), 3);
+
+    // Disconnecting exactly `DEPTH` blocks is still allowed.
+    let b7_prime = mint_chain(&mut node, b3, 4, 100);
+    assert_eq!(wallet.try_sync(&node), Ok(()));
+    oracle.sync_to(b7_prime);
+    assert_eq!(
+        WalletState::of(&wallet, &OWNED),
+        WalletState::of(&oracle, &OWNED)
+    );
+    assert_eq!(wallet.finalized_height(), 4);
+
+    // B3 is no longer the last final block, so forking from it again is too deep.
+    mint_chain(&mut node, b3, 5, 200);
+    assert_eq!(
+        wallet.try_sync(&node),
+        Err(FinalityError::ReorgTooDeep {
+            fork_height: 3,
+            finalized_height: 4,
+        })
+    );
+    assert_eq!(
+        WalletState::of(&wallet, &OWNED),
+        WalletState::of(&oracle, &OWNED),
+        "a reorg too deep to follow changed the wallet"
+    );
+    assert_eq!(wallet.finalized_height(), 4);
+
+    // A shorter chain forking at the finalized height is followed, and finality stays put.
+    let b4_prime = node.record().borrow().chain_to(b7_prime)[3];
+    node.set_best(b4_prime);
+    assert_eq!(wallet.try_sync(&node), Ok(()));
+    oracle.sync_to(b4_prime);
+    assert_eq!(
+        WalletState::of(&wallet, &OWNED),
+        WalletState::of(&oracle, &OWNED)
+    );
+    assert_eq!(wallet.finalized_height(), 4);
+}
+
+pub fn finality_follows_random_reorgs<F: WalletFactory>()
+where
+    F::Wallet: FinalityWalletApi,
+{
+    let config = RandomConfig::reorg_heavy();
+    for_each_seed(20, |seed| {
+        let scenario = Scenario::generate(seed, &config);
+        let mut node = RecordingNode::new();
+        let mut wallet = F::new_wallet(OWNED.into_iter());
+        let mut oracle = OracleWallet::new(OWNED.into_iter(), node.record());
+        wallet.set_finality_depth(DEPTH);
+        let mut tip = Block::genesis().id();
+        let mut finalized_height = 0;
+
+        scenario.replay(&mut node, |node| {
+            let record = node.record();
+            let best = record.borrow().best;
+            let fork_height = fork_height(&record.borrow(), tip, best);
+            let result = wallet.try_sync(node);
+            if fork_height >= finalized_height {
+                assert_eq!(result, Ok(()), "seed {}: reorg within the depth", seed);
+                tip = best;
+                let height = record.borrow().height_of(best);
+                finalized_height = finalized_height.max(height.saturating_sub(DEPTH));
+                oracle.sync_to(tip);
+            } else {
+                assert_eq!(
+                    result,
+                    Err(FinalityError::ReorgTooDeep {
+                        fork_height,
+                        finalized_height,
+                    }),
+                    "seed {}: reorg below the finalized height",
+                    seed
+                );
+            }
+            assert_eq!(
+                wallet.finalized_height(),
+                finalized_height,
+                "seed {}: finalized height",
+                seed
+            );
+            assert_eq!(
+                WalletState::of(&wallet, &OWNED),
+                WalletState::of(&oracle, &OWNED),
+                "seed {}: wallet synced to {:?}",
+                seed,
+                tip
+            );
+        });
+    });
+}
+
+/// Sync a fresh wallet owning Alice with a finality depth of 10 on `node` and return the heap
+/// it kept, or `None` if the counting allocator is not installed.
+fn retained_with_finality<F: WalletFactory>(node: &MockNode) -> Option<isize>
+where
+    F::Wallet: FinalityWalletApi,
+{
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    wallet.set_finality_depth(10);
+    let retained = bytes_retained_by(|| wallet.try_sync(node).unwrap())?;
+    assert!(retained > 0, "the wallet kept no heap at all");
+    Some(retained)
+}
+
+pub fn memory_stays_bounded_with_a_finality_depth<F: WalletFactory>()
+where
+    F::Wallet: FinalityWalletApi,
+{
+    // The UTXO set and the last 10 blocks look the same on both chains, so nothing else may
+    // grow with the length of the chain.
+    let (Some(short), Some(long)) = (
+        retained_with_finality::<F>(&rotating_utxo_set(250, 50)),
+        retained_with_finality::<F>(&rotating_utxo_set(1000, 50)),
+    ) else {
+        eprintln!("skipped: the counting allocator is not installed");
+        return;
+    };
+    assert!(
+        long <= short + short / 2,
+        "{} bytes kept for 250 blocks but {} bytes for 1000 blocks, undo data is not pruned",
+        short,
+        long
+    );
+}
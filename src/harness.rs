@@ -0,0 +1,450 @@
+//! Plumbing that lets the whole battery run against any `WalletApi` implementation.
+//!
+//! Every test in the suite is a plain function generic over a [`WalletFactory`]. The
+//! [`wallet_test_suite!`](crate::wallet_test_suite) macro turns all of them into `#[test]`s for
+//! one concrete wallet, so several wallet crates can be checked side by side in one workspace.
+
+use crate::spec_profile::SpecProfile;
+use bonecoin_core::*;
+
+/// Knows how to build the wallet under test.
+///
+/// Implement it on a unit struct next to the wallet you want to check, for example:
+///
+/// ```ignore
+/// struct MyWallet;
+///
+/// impl WalletFactory for MyWallet {
+///     type Wallet = my_wallet::Wallet;
+///
+///     fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet {
+///         my_wallet::Wallet::new(addresses)
+///     }
+/// }
+///
+/// bonecoin_testsuite::wallet_test_suite!(my_wallet_tests, MyWallet);
+/// ```
+pub trait WalletFactory {
+    type Wallet: WalletApi;
+
+    /// The reading of the ambiguous parts of the spec the wallet follows, which the tests tagged
+    /// `spec-ambiguous` check strictly.
+    const PROFILE: SpecProfile = SpecProfile::DEFAULT;
+
+    /// Create a fresh wallet that owns exactly the given addresses and has not synced yet.
+    fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet;
+}
+
+/// Instantiate every test of the suite as a `#[test]` inside a new module `$name`, running
+/// against the wallet built by `$factory`. Every transaction the wallet creates is
+/// [validated](crate::tx_validation) on the way.
+///
+/// Wallets implementing optional extensions of the API list them after the factory, which adds
+/// their tests too:
+///
+/// ```ignore
+/// wallet_test_suite!(my_wallet_tests, MyWallet, pending);
+/// ```
+///
+/// The extensions are `pending` ([`PendingWalletApi`](crate::pending::PendingWalletApi)),
+/// `persistence` ([`PersistentWalletApi`](crate::persistence::PersistentWalletApi)), `history`
+/// ([`HistoryWalletApi`](crate::history::HistoryWalletApi)), `events`
+/// ([`EventWalletApi`](crate::events::EventWalletApi)), `incremental`
+/// ([`IncrementalWalletApi`](crate::incremental::IncrementalWalletApi)), `confirmations`
+/// ([`ConfirmationsWalletApi`](crate::confirmations::ConfirmationsWalletApi)) and `finality`
+/// ([`FinalityWalletApi`](crate::finality::FinalityWalletApi)).
+#[macro_export]
+macro_rules! wallet_test_suite {
+    ($name:ident, $factory:ty $(, $extension:ident)* $(,)?) => {
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $crate::__wallet_tests!(__instantiate_wallet_tests; $factory);
+            $(
+                $crate::__extension_tests!($extension; __instantiate_wallet_tests; $factory);
+            )*
+        }
+    };
+}
+
+/// The one list of every test in the suite. It is handed to the macro `$callback` of this crate
+/// as `$factory; module { test [tags], ... } ...`; add new tests here, with their
+/// [tags](crate::tags).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __wallet_tests {
+    ($callback:ident; $factory:ty) => {
+        $crate::$callback! {
+            $factory;
+            esteblock_tests {
+                reports_correct_ancestors_even_after_reorg_in_the_middle ["reorg"],
+                reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic ["reorg"],
+                reports_correct_ancestors_even_after_reorg_in_the_middle_with_atomic_and_reor_again_to_previous ["reorg"],
+            }
+            krayt78_tests {
+                correct_genesis_values ["sync"],
+                foreign_address_error ["utxo"],
+                sync_two_blocks ["sync"],
+                short_reorg ["reorg"],
+                deep_reorg ["reorg"],
+                reorg_to_shorter_chain ["reorg"],
+                tracks_single_utxo ["utxo"],
+                consumes_own_utxo ["utxo"],
+                tracks_multiple_utxos ["utxo"],
+                track_utxos_to_multiple_users ["utxo"],
+                check_manual_transaction_with_missing_input ["manual-tx"],
+                check_manual_transaction_with_wrong_input_addresses ["manual-tx"],
+                check_manual_transaction_with_too_much_output ["manual-tx"],
+                check_manual_transaction_with_zero_output_value ["manual-tx"],
+                check_automatic_transaction_with_too_much_output ["auto-tx"],
+                check_automatic_transaction_with_zero_change ["auto-tx"],
+                reorg_performance ["perf"],
+                deep_reorg_to_short_chain ["reorg"],
+                dont_save_coins_not_owned_by_our_wallet_addresses ["utxo"],
+            }
+            sinzii_tests {
+                reorg_in_the_middle_with_tx_changes ["reorg"],
+            }
+            bigtava_tests {
+                test_reorgs_with_utxos_in_chain_history ["reorg"],
+            }
+            tommy97_tests {
+                reorg_hard_test_hehe ["reorg"],
+            }
+            trantorian_tests {
+                correct_genesis_values ["sync"],
+                foreign_address_error ["utxo"],
+                sync_two_blocks ["sync"],
+                short_reorg ["reorg"],
+                deep_reorg ["reorg"],
+                reorg_to_shorter_chain ["reorg"],
+                tracks_single_utxo ["utxo"],
+                consumes_own_utxo ["utxo"],
+                extra_track_two_utxo ["utxo"],
+                extra_utxo_to_multiple_users ["utxo"],
+                extra_best_height_and_hash ["sync"],
+                extra_best_height_and_hash_fork1 ["reorg"],
+                extra_best_height_and_hash_fork2 ["reorg"],
+                extra_total_assets_of_simple ["utxo"],
+                extra_total_assets_of_fork ["reorg"],
+                extra_total_assets_of_overflow ["utxo", "overflow"],
+                extra_total_assets_of_empty ["utxo"],
+                extra_total_assets_of_foreign_address ["utxo"],
+                extra_net_worth_simple ["utxo"],
+                extra_net_worth_fork ["reorg"],
+                extra_net_worth_overflow ["utxo", "overflow"],
+                extra_total_net_worth_empty ["utxo"],
+                extra_all_coins_of_simple ["utxo"],
+                extra_all_coins_of_fork ["reorg"],
+                extra_all_coins_spend ["utxo"],
+                extra_all_coin_foreign_address ["utxo"],
+                extra_coin_details_simple ["utxo"],
+                extra_coin_details_fork ["reorg"],
+                extra_coin_details_spend ["utxo"],
+                extra_create_manual_transaction ["manual-tx"],
+                extra_automatic_transaction_simple ["auto-tx", "spec-ambiguous"],
+                extra_automatic_transaction_invalid ["auto-tx"],
+                extra_automatic_transaction_no_owned_address ["auto-tx"],
+                extra_automatic_transaction_zero_coin_value ["auto-tx"],
+                extra_automatic_transaction_overflow ["auto-tx", "overflow", "overkill"],
+            }
+            kwar13_tests {
+                correct_genesis_values ["sync"],
+                foreign_address_error ["utxo"],
+                sync_two_blocks ["sync"],
+                short_reorg ["reorg"],
+                deep_reorg ["reorg"],
+                reorg_to_shorter_chain ["reorg"],
+                tracks_single_utxo ["utxo"],
+                consumes_own_utxo ["utxo"],
+                blockchain_creation ["sync"],
+                empty_wallet_fails_transaction ["auto-tx"],
+                transaction_with_zero_value_fails ["auto-tx"],
+                process_new_block ["auto-tx"],
+                transaction_simple ["auto-tx"],
+                transaction_automatic_insufficient_funds ["auto-tx"],
+                sneak_in_no_inputs ["manual-tx"],
+                sneak_in_non_owned_address ["manual-tx"],
+                transaction_with_no_change_tx ["auto-tx", "spec-ambiguous"],
+                utxo_reog_simple ["reorg"],
+                call_sync_twice ["sync"],
+            }
+            main_tests {
+                correct_genesis_values ["sync"],
+                foreign_address_error ["utxo"],
+                sync_two_blocks ["sync"],
+                short_reorg ["reorg"],
+                deep_reorg ["reorg"],
+                reorg_to_shorter_chain ["reorg"],
+                tracks_single_utxo ["utxo"],
+                consumes_own_utxo ["utxo"],
+            }
+            krayt78_2_tests {
+                correct_genesis_values ["sync"],
+                foreign_address_error ["utxo"],
+                sync_two_blocks ["sync"],
+                short_reorg ["reorg"],
+                deep_reorg ["reorg"],
+                reorg_to_shorter_chain ["reorg"],
+                tracks_single_utxo ["utxo"],
+                consumes_own_utxo ["utxo"],
+                tracks_multiple_utxos ["utxo"],
+                track_utxos_to_multiple_users ["utxo"],
+                check_manual_transaction_with_missing_input ["manual-tx"],
+                check_manual_transaction_with_double_spending ["manual-tx"],
+                check_manual_transaction_with_wrong_input_addresses ["manual-tx"],
+                check_manual_transaction_with_too_much_output ["manual-tx"],
+                check_manual_transaction_with_zero_output_value ["manual-tx"],
+                check_automatic_transaction_with_too_much_output ["auto-tx"],
+                check_automatic_transaction_from_multiple_users ["auto-tx"],
+                check_automatic_transaction_with_zero_change ["auto-tx"],
+                reorg_performance ["perf"],
+                deep_reorg_to_short_chain ["reorg"],
+                dont_save_coins_not_owned_by_our_wallet_addresses ["utxo"],
+                reorg_hard_test_hehe ["reorg"],
+                test_reorgs_with_utxos_in_chain_history ["reorg"],
+            }
+            sinzii_2_tests {
+                reorg_with_utxos_01 ["reorg", "spec-ambiguous"],
+            }
+            tarekkma_tests {
+                total_assets_of_should_not_return_no_owned_address ["utxo"],
+                spend_utxo_in_same_block ["utxo"],
+                perf_sync_100_blocks ["perf"],
+                pref_sync_1000_blocks ["perf"],
+            }
+            differential_tests {
+                oracle_agrees_with_hand_computed_values ["sync"],
+                reorg_with_utxos_in_chain_history ["reorg"],
+                spend_and_reorg_back_and_forth ["reorg"],
+                spend_in_same_block_and_reorg_to_genesis ["reorg"],
+                overflowing_balances ["utxo", "overflow"],
+                wallet_created_transactions_round_trip ["auto-tx"],
+            }
+            chain_builder_tests {
+                coin_ids_follow_where_tx_landed ["utxo"],
+                builder_chains_match_oracle ["sync"],
+            }
+            diagram_tests {
+                same_diagram_builds_the_same_blocks ["reorg"],
+                diagram_reorg_in_the_middle_with_utxos ["reorg"],
+            }
+            random_tests {
+                random_short_chains_with_many_reorgs ["reorg", "random"],
+                random_long_chains ["reorg", "random"],
+                random_busy_blocks_synced_rarely ["reorg", "random"],
+            }
+            shrink_tests {
+                shrinks_a_reorg_failure_to_a_smaller_test ["reorg", "random"],
+            }
+            scenario_file_tests {
+                scenario_files ["sync"],
+            }
+            memory_tests {
+                memory_grows_linearly_with_a_growing_utxo_set ["perf"],
+                memory_grows_linearly_with_a_steady_utxo_set ["perf"],
+            }
+            strict_node_tests {
+                automatic_transactions_are_minable ["auto-tx"],
+                manual_transactions_are_minable ["manual-tx"],
+                transactions_after_a_reorg_are_minable ["auto-tx"],
+                blocks_on_unknown_parents_are_refused ["sync"],
+                spends_of_coins_off_the_chain_are_refused ["utxo"],
+                coins_spent_in_an_earlier_block_are_refused ["utxo"],
+                coins_spent_twice_in_a_block_are_refused ["utxo"],
+                coins_spent_twice_in_a_transaction_are_refused ["utxo"],
+                spends_signed_by_someone_else_are_refused ["utxo"],
+                transactions_creating_value_are_refused ["utxo"],
+            }
+            round_trip_tests {
+                payments_between_three_wallets ["auto-tx", "random"],
+                payments_between_three_wallets_with_reorgs ["auto-tx", "random"],
+            }
+            tree_file_tests {
+                exported_trees_keep_their_block_ids ["reorg", "random"],
+                spends_of_coins_never_created_are_exported ["utxo"],
+                tree_files ["reorg"],
+            }
+            tx_validation_tests {
+                overspending_transactions_are_refused ["auto-tx"],
+                foreign_inputs_are_refused ["auto-tx"],
+                duplicate_inputs_are_refused ["auto-tx"],
+                zero_value_outputs_are_refused ["auto-tx"],
+                transactions_without_outputs_are_refused ["auto-tx"],
+            }
+        }
+    };
+}
+
+/// The tests of each optional extension, handed to `$callback` like the tests of
+/// [`__wallet_tests!`](crate::__wallet_tests). Each extension is one arm.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __extension_tests {
+    (pending; $callback:ident; $factory:ty) => {
+        $crate::$callback! {
+            $factory;
+            pending_tests {
+                consecutive_transactions_do_not_share_inputs ["auto-tx", "pending"],
+                balances_before_and_after_mining ["auto-tx", "pending"],
+                reserved_coins_cannot_be_spent_again ["manual-tx", "pending"],
+                conflicting_spend_drops_the_pending_transaction ["utxo", "pending"],
+                pending_inputs_reorged_away_are_dropped ["reorg", "pending"],
+                mined_then_reorged_out_leaves_coins_spendable ["reorg", "pending"],
+            }
+        }
+    };
+    (persistence; $callback:ident; $factory:ty) => {
+        $crate::$callback! {
+            $factory;
+            persistence_tests {
+                restored_wallet_follows_random_reorgs_like_its_twin ["reorg", "persistence", "random"],
+                restored_wallet_owns_the_saved_addresses ["utxo", "persistence"],
+                restored_wallet_keeps_spending ["auto-tx", "persistence"],
+                incomplete_data_is_rejected ["sync", "persistence"],
+            }
+        }
+    };
+    (history; $callback:ident; $factory:ty) => {
+        $crate::$callback! {
+            $factory;
+            history_tests {
+                history_after_a_reorg_in_the_middle ["reorg", "history"],
+                history_after_reorging_back_to_the_previous_branch ["reorg", "history"],
+                history_follows_random_reorgs ["reorg", "history", "random"],
+            }
+        }
+    };
+    (events; $callback:ident; $factory:ty) => {
+        $crate::$callback! {
+            $factory;
+            events_tests {
+                events_of_a_reorg_with_utxos_in_chain_history ["reorg", "events"],
+                events_of_coins_spent_in_the_block_that_creates_them ["utxo", "events"],
+                events_follow_random_reorgs ["reorg", "events", "random"],
+            }
+        }
+    };
+    (incremental; $callback:ident; $factory:ty) => {
+        $crate::$callback! {
+            $factory;
+            incremental_tests {
+                every_reorg_test_stepwise ["reorg", "incremental", "random"],
+                single_block_steps_follow_the_reorg_path ["reorg", "incremental"],
+                zero_block_step_only_reports_progress ["sync", "incremental"],
+                random_step_sizes_end_like_one_shot_syncs ["reorg", "incremental", "random"],
+            }
+        }
+    };
+    (confirmations; $callback:ident; $factory:ty) => {
+        $crate::$callback! {
+            $factory;
+            confirmations_tests {
+                confirmed_balances_across_reorg_hard_test_hehe ["reorg", "confirmations"],
+                confirmed_balances_across_a_deep_reorg_to_a_short_chain ["reorg", "confirmations"],
+                confirmed_balances_follow_random_reorgs ["reorg", "confirmations", "random"],
+            }
+        }
+    };
+    (finality; $callback:ident; $factory:ty) => {
+        $crate::$callback! {
+            $factory;
+            finality_tests {
+                reorg_to_genesis_below_the_finalized_height ["reorg", "finality"],
+                reorgs_down_to_the_finalized_height ["reorg", "finality"],
+                finality_follows_random_reorgs ["reorg", "finality", "random"],
+                memory_stays_bounded_with_a_finality_depth ["perf", "finality"],
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __instantiate_wallet_tests {
+    ($factory:ty; $($module:ident { $($test:ident [$($tag:literal),*],)* })*) => {
+        $(
+            mod $module {
+                #[allow(unused_imports)]
+                use super::*;
+
+                $(
+                    #[test]
+                    fn $test() {
+                        if $crate::tags::skipped(&[$($tag),*]) {
+                            eprintln!("skipped by {}", $crate::tags::TAGS_VAR);
+                            return;
+                        }
+                        $crate::$module::$test::<$crate::tx_validation::Validated<$factory>>();
+                    }
+                )*
+            }
+        )*
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __collect_wallet_tests {
+    ($factory:ty; $($module:ident { $($test:ident [$($tag:literal),*],)* })*) => {
+        vec![
+            $($(
+                $crate::harness::SuiteTest {
+                    module: stringify!($module),
+                    name: stringify!($test),
+                    tags: &[$($tag),*],
+                    run: $crate::$module::$test::<$crate::tx_validation::Validated<$factory>>,
+                },
+            )*)*
+        ]
+    };
+}
+
+/// One test of the suite, bound to a wallet factory.
+#[derive(Clone, Copy, Debug)]
+pub struct SuiteTest {
+    /// The `*_tests` module the test lives in.
+    pub module: &'static str,
+    pub name: &'static str,
+    /// See [`tags`](crate::tags); the first one is the capability the test exercises.
+    pub tags: &'static [&'static str],
+    pub run: fn(),
+}
+
+/// Every test of the suite running against wallets from `F`, in the order
+/// [`wallet_test_suite!`](crate::wallet_test_suite) declares them.
+pub fn suite_tests<F: WalletFactory>() -> Vec<SuiteTest> {
+    crate::__wallet_tests!(__collect_wallet_tests; F)
+}
+
+/// The extensions [`wallet_test_suite!`](crate::wallet_test_suite) accepts, in the order of the
+/// arms of [`__extension_tests!`](crate::__extension_tests). The tests of each live in
+/// `<extension>_tests`.
+pub const EXTENSIONS: &[&str] = &[
+    "pending",
+    "persistence",
+    "history",
+    "events",
+    "incremental",
+    "confirmations",
+    "finality",
+];
+
+/// Like [`suite_tests`], followed by the tests of the listed extensions, which `$factory`'s
+/// wallet must implement as for [`wallet_test_suite!`](crate::wallet_test_suite):
+///
+/// ```ignore
+/// let tests = bonecoin_testsuite::suite_tests_with!(MyWallet, pending, history);
+/// ```
+#[macro_export]
+macro_rules! suite_tests_with {
+    ($factory:ty $(, $extension:ident)* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut tests = $crate::harness::suite_tests::<$factory>();
+        $(
+            tests.extend($crate::__extension_tests!($extension; __collect_wallet_tests; $factory));
+        )*
+        tests
+    }};
+}
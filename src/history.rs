@@ -0,0 +1,82 @@
+//! Optional wallet extension: what each owned address received and spent.
+//!
+//! `WalletApi` only shows the coins that are unspent right now. A wallet implementing
+//! [`HistoryWalletApi`] also lists, per owned address, every coin it received and every one it
+//! spent on the best chain, with the block and the transaction that did it. The history is that
+//! of the best chain only: a reorg takes the entries of the blocks it disconnects out and puts
+//! those of the new branch in.
+//!
+//! Enable the tests with `wallet_test_suite!(name, factory, history)`.
+
+use crate::recording_node::ChainRecord;
+use crate::tx_validation::ValidatedWallet;
+use bonecoin_core::*;
+use std::collections::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryKind {
+    /// An output of the transaction created the coin.
+    Received,
+    /// An input of the transaction spent the coin.
+    Spent,
+}
+
+/// One coin received or spent by an owned address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub kind: HistoryKind,
+    pub coin_id: CoinId,
+    pub value: u64,
+    pub height: u64,
+    pub block: BlockId,
+    /// Position of the transaction in its block, from 0.
+    pub tx_index: usize,
+}
+
+pub trait HistoryWalletApi: WalletApi {
+    /// Every coin `address` received or spent on the best chain, in chain order: block by block,
+    /// transaction by transaction, the coins a transaction spends before the ones it creates,
+    /// each in input or output order. Spending a coin that is already spent is not an entry.
+    ///
+    /// Fails with `ForeignAddress` for an address the wallet does not own.
+    fn history_of(&self, address: Address) -> Result<Vec<HistoryEntry>, WalletError>;
+}
+
+impl<W: HistoryWalletApi> HistoryWalletApi for ValidatedWallet<W> {
+    fn history_of(&self, address: Address) -> Result<Vec<HistoryEntry>, WalletError> {
+        self.wallet().history_of(address)
+    }
+}
+
+/// The history of `address` on the best chain of `record`, replayed from genesis.
+pub fn expected_history(record: &ChainRecord, address: Address) -> Vec<HistoryEntry> {
+    let mut unspent: HashMap<CoinId, u64> = HashMap::new();
+    let mut history = Vec::new();
+
+    for block_id in record.best_chain() {
+        let block = &record.blocks[&block_id];
+        for (tx_index, tx) in block.transactions.iter().enumerate() {
+            let entry = |kind, coin_id, value| HistoryEntry {
+                kind,
+                coin_id,
+                value,
+                height: block.height,
+                block: block_id,
+                tx_index,
+            };
+            for input in &tx.inputs {
+                if let Some(value) = unspent.remove(&input.coin_id) {
+                    history.push(entry(HistoryKind::Spent, input.coin_id, value));
+                }
+            }
+            for (index, coin) in tx.outputs.iter().enumerate() {
+                if coin.owner == address {
+                    let coin_id = tx.coin_id(block.height, index);
+                    unspent.insert(coin_id, coin.value);
+                    history.push(entry(HistoryKind::Received, coin_id, coin.value));
+                }
+            }
+        }
+    }
+    history
+}
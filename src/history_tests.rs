@@ -0,0 +1,228 @@
+//! Per-address histories rewritten by reorgs
+//!
+//! Only for wallets implementing [`HistoryWalletApi`]. The topologies are those of
+//! `esteblock_tests`, with the history checked after every sync.
+
+use crate::harness::WalletFactory;
+use crate::history::HistoryKind::{self, Received, Spent};
+use crate::history::{expected_history, HistoryEntry, HistoryWalletApi};
+use crate::random_scenario::{for_each_seed, RandomConfig, Scenario, OWNED};
+use crate::recording_node::RecordingNode;
+use crate::test_helpers::pay;
+use bonecoin_core::*;
+
+fn marker_tx(value: u64) -> Transaction {
+    pay(&[], &[(value, Address::Custom(value))])
+}
+
+/// Compare the history of every address of `OWNED` with the one replayed from `node`.
+fn check_history(wallet: &impl HistoryWalletApi, node: &RecordingNode, context: &str) {
+    let record = node.record();
+    for address in OWNED {
+        assert_eq!(
+            wallet.history_of(address),
+            Ok(expected_history(&record.borrow(), address)),
+            "{}: history of {:?}",
+            context,
+            address
+        );
+    }
+    assert_eq!(
+        wallet.history_of(Address::Charlie),
+        Err(WalletError::ForeignAddress)
+    );
+}
+
+fn entry(
+    kind: HistoryKind,
+    (coin_id, value): (CoinId, u64),
+    height: u64,
+    block: BlockId,
+    tx_index: usize,
+) -> HistoryEntry {
+    HistoryEntry {
+        kind,
+        coin_id,
+        value,
+        height,
+        block,
+        tx_index,
+    }
+}
+
+/// The tree shared by both topologies, up to the first sync on the branch that is later
+/// abandoned:
+///
+/// ```text
+///     G - B1 - B2 - B3 - Old_B4 - Old_B5 - Old_B6
+/// ```
+///
+/// B2 mints 100 to Alice, B3 splits it into 4 and 6 for Bob and 90 for Alice, and Old_B6 spends
+/// Bob's 4 into 1 for Alice and 3 for Bob, then Alice's 90 and Bob's 3 into 73 for Alice and 20
+/// for Bob.
+struct Topology {
+    b2: BlockId,
+    b3: BlockId,
+    old_b6: BlockId,
+    /// The coins in the order of the description, with their values.
+    coins: Vec<(CoinId, u64)>,
+}
+
+impl Topology {
+    fn build<W: HistoryWalletApi>(node: &mut RecordingNode, wallet: &mut W) -> Self {
+        let b1 = node.add_block_as_best(Block::genesis().id(), vec![]);
+        let mint = pay(&[], &[(100, Address::Alice)]);
+        let c0 = mint.coin_id(2, 0);
+        let b2 = node.add_block_as_best(b1, vec![mint]);
+        let split = pay(
+            &[c0],
+            &[(4, Address::Bob), (6, Address::Bob), (90, Address::Alice)],
+        );
+        let (c1, c2, c3) = (
+            split.coin_id(3, 0),
+            split.coin_id(3, 1),
+            split.coin_id(3, 2),
+        );
+        let b3 = node.add_block_as_best(b2, vec![split]);
+        wallet.sync(node);
+        check_history(wallet, node, "at B3");
+
+        let old_b4 = node.add_block_as_best(b3, vec![]);
+        let old_b5 = node.add_block_as_best(old_b4, vec![marker_tx(123)]);
+        let first = pay(&[c1], &[(1, Address::Alice), (3, Address::Bob)]);
+        let (c4, c5) = (first.coin_id(6, 0), first.coin_id(6, 1));
+        let second = pay(&[c3, c5], &[(73, Address::Alice), (20, Address::Bob)]);
+        let (c6, c7) = (second.coin_id(6, 0), second.coin_id(6, 1));
+        let old_b6 = node.add_block_as_best(old_b5, vec![first, second]);
+        wallet.sync(node);
+        check_history(wallet, node, "at Old_B6");
+
+        let coins = [c0, c1, c2, c3, c4, c5, c6, c7];
+        let values = [100, 4, 6, 90, 1, 3, 73, 20];
+        Topology {
+            b2,
+            b3,
+            old_b6,
+            coins: coins.into_iter().zip(values).collect(),
+        }
+    }
+
+    /// Alice's history up to Old_B6.
+    fn alice_at_old_b6(&self) -> Vec<HistoryEntry> {
+        let c = &self.coins;
+        vec![
+            entry(Received, c[0], 2, self.b2, 0),
+            entry(Spent, c[0], 3, self.b3, 0),
+            entry(Received, c[3], 3, self.b3, 0),
+            entry(Received, c[4], 6, self.old_b6, 0),
+            entry(Spent, c[3], 6, self.old_b6, 1),
+            entry(Received, c[6], 6, self.old_b6, 1),
+        ]
+    }
+}
+
+//                         Old_B4 - Old_B5 - Old_B6 (discarded)
+//                       /
+//     G - B1 - B2 - B3
+//                       \ B4 - B5 (best)
+pub fn history_after_a_reorg_in_the_middle<F: WalletFactory>()
+where
+    F::Wallet: HistoryWalletApi,
+{
+    let mut node = RecordingNode::new();
+    let mut wallet = F::new_wallet(OWNED.into_iter());
+    let topology = Topology::build(&mut node, &mut wallet);
+    assert_eq!(
+        wallet.history_of(Address::Alice),
+        Ok(topology.alice_at_old_b6())
+    );
+
+    // Bob's 4 and 6 become 7 for Alice and 3 for Bob on the new branch.
+    let c = &topology.coins;
+    let merge = pay(&[c[1].0, c[2].0], &[(7, Address::Alice), (3, Address::Bob)]);
+    let (c8, c9) = ((merge.coin_id(4, 0), 7), (merge.coin_id(4, 1), 3));
+    let b4 = node.add_block_as_best(topology.b3, vec![merge]);
+    node.add_block_as_best(b4, vec![marker_tx(1011)]);
+    wallet.sync(&node);
+    check_history(&wallet, &node, "at B5");
+
+    let (b2, b3) = (topology.b2, topology.b3);
+    assert_eq!(
+        wallet.history_of(Address::Alice),
+        Ok(vec![
+            entry(Received, c[0], 2, b2, 0),
+            entry(Spent, c[0], 3, b3, 0),
+            entry(Received, c[3], 3, b3, 0),
+            entry(Received, c8, 4, b4, 0),
+        ])
+    );
+    assert_eq!(
+        wallet.history_of(Address::Bob),
+        Ok(vec![
+            entry(Received, c[1], 3, b3, 0),
+            entry(Received, c[2], 3, b3, 0),
+            entry(Spent, c[1], 4, b4, 0),
+            entry(Spent, c[2], 4, b4, 0),
+            entry(Received, c9, 4, b4, 0),
+        ])
+    );
+}
+
+//                         Old_B4 - Old_B5 - Old_B6 - B7 (best again)
+//                       /
+//     G - B1 - B2 - B3
+//                       \ B4 - B5
+pub fn history_after_reorging_back_to_the_previous_branch<F: WalletFactory>()
+where
+    F::Wallet: HistoryWalletApi,
+{
+    let mut node = RecordingNode::new();
+    let mut wallet = F::new_wallet(OWNED.into_iter());
+    let topology = Topology::build(&mut node, &mut wallet);
+    let c = &topology.coins;
+
+    let merge = pay(&[c[1].0, c[2].0], &[(7, Address::Alice), (3, Address::Bob)]);
+    let b4 = node.add_block_as_best(topology.b3, vec![merge]);
+    node.add_block_as_best(b4, vec![marker_tx(1011)]);
+    wallet.sync(&node);
+    check_history(&wallet, &node, "at B5");
+
+    // Bob's 20 goes to Charlie on top of the abandoned branch, which is best again.
+    let to_charlie = pay(&[c[7].0], &[(20, Address::Charlie)]);
+    let b7 = node.add_block_as_best(topology.old_b6, vec![to_charlie]);
+    wallet.sync(&node);
+    check_history(&wallet, &node, "at B7");
+
+    assert_eq!(
+        wallet.history_of(Address::Alice),
+        Ok(topology.alice_at_old_b6())
+    );
+    let (b3, old_b6) = (topology.b3, topology.old_b6);
+    assert_eq!(
+        wallet.history_of(Address::Bob),
+        Ok(vec![
+            entry(Received, c[1], 3, b3, 0),
+            entry(Received, c[2], 3, b3, 0),
+            entry(Spent, c[1], 6, old_b6, 0),
+            entry(Received, c[5], 6, old_b6, 0),
+            entry(Spent, c[5], 6, old_b6, 1),
+            entry(Received, c[7], 6, old_b6, 1),
+            entry(Spent, c[7], 7, b7, 0),
+        ])
+    );
+}
+
+pub fn history_follows_random_reorgs<F: WalletFactory>()
+where
+    F::Wallet: HistoryWalletApi,
+{
+    let config = RandomConfig::reorg_heavy();
+    for_each_seed(20, |seed| {
+        let scenario = Scenario::generate(seed, &config);
+        let mut wallet = F::new_wallet(OWNED.into_iter());
+        scenario.replay(&mut RecordingNode::new(), |node| {
+            wallet.sync(node);
+            check_history(&wallet, node, &format!("seed {}", seed));
+        });
+    });
+}
@@ -0,0 +1,201 @@
+//! Optional wallet extension: syncing a few blocks at a time.
+//!
+//! `WalletApi::sync` runs all the way to the node's best block. A wallet implementing
+//! [`IncrementalWalletApi`] can also take bounded steps towards it with `sync_step`: each call
+//! disconnects or connects at most `max_blocks` blocks, going down its own branch to the fork
+//! point first and then up the best chain, and reports how far it got. Between steps the wallet
+//! answers every query as of the block it stopped at, exactly like a wallet that synced to that
+//! block in one go.
+//!
+//! Enable the tests with `wallet_test_suite!(name, factory, incremental)`. Besides their own
+//! checks, they run every `reorg` test of the suite with [`Stepwise`] wallets, whose `sync`
+//! goes step by step and must end where a one-shot sync ends.
+
+use crate::differential::WalletState;
+use crate::harness::WalletFactory;
+use crate::spec_profile::SpecProfile;
+use crate::tx_validation::ValidatedWallet;
+use bonecoin_core::*;
+use std::collections::*;
+use std::marker::PhantomData;
+
+/// Blocks per `sync_step` for [`Stepwise`] wallets.
+pub const STEP_BLOCKS: u64 = 2;
+
+/// Where a wallet stands after a `sync_step`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyncProgress {
+    /// Height of the block the wallet stopped at, its `best_height`.
+    pub height: u64,
+    /// Height of the node's best block.
+    pub target_height: u64,
+    /// The wallet stopped on a block off the node's best chain, so it still has blocks to
+    /// disconnect.
+    pub reorging: bool,
+}
+
+impl SyncProgress {
+    /// The wallet reached the node's best block.
+    pub fn is_done(&self) -> bool {
+        !self.reorging && self.height == self.target_height
+    }
+}
+
+pub trait IncrementalWalletApi: WalletApi {
+    /// Disconnect or connect at most `max_blocks` blocks on the way to the node's best block.
+    /// With `max_blocks` 0 the wallet stays where it is and only reports its progress.
+    fn sync_step(&mut self, node: &MockNode, max_blocks: u64) -> SyncProgress;
+}
+
+impl<W: IncrementalWalletApi> IncrementalWalletApi for ValidatedWallet<W> {
+    fn sync_step(&mut self, node: &MockNode, max_blocks: u64) -> SyncProgress {
+        self.wallet_mut().sync_step(node, max_blocks)
+    }
+}
+
+/// A wallet whose `sync` is a loop of `sync_step`s of [`STEP_BLOCKS`] blocks, next to a twin
+/// that syncs in one go. Each step must leave the wallet consistent and move it at most
+/// `STEP_BLOCKS` blocks, and the last one must leave it in the same state as the twin.
+pub struct StepwiseWallet<W: IncrementalWalletApi> {
+    wallet: W,
+    twin: W,
+    owned: Vec<Address>,
+}
+
+impl<W: IncrementalWalletApi> StepwiseWallet<W> {
+    /// Panic if the answers of the wallet do not add up.
+    fn assert_consistent(&self, progress: &SyncProgress) {
+        assert_eq!(
+            progress.height,
+            self.wallet.best_height(),
+            "sync_step reports another height than best_height"
+        );
+        let mut total: u64 = 0;
+        for address in &self.owned {
+            let balance = self.wallet.total_assets_of(*address).unwrap();
+            let coins = self.wallet.all_coins_of(*address).unwrap();
+            let sum = coins
+                .iter()
+                .fold(0u64, |sum, (_, value)| sum.saturating_add(*value));
+            assert_eq!(
+                balance, sum,
+                "between steps at height {}, the balance of {:?} is not the sum of its coins",
+                progress.height, address
+            );
+            total = total.saturating_add(balance);
+        }
+        assert_eq!(
+            self.wallet.net_worth(),
+            total,
+            "between steps at height {}, net_worth is not the sum of the balances",
+            progress.height
+        );
+    }
+}
+
+impl<W: IncrementalWalletApi> WalletApi for StepwiseWallet<W> {
+    fn sync(&mut self, node: &MockNode) {
+        self.twin.sync(node);
+        let start = self.wallet.best_height();
+        let target = self.twin.best_height();
+        // Down to genesis at worst, then up to the target.
+        let max_steps = (start + target) / STEP_BLOCKS + 2;
+
+        for step in 0.. {
+            assert!(
+                step < max_steps,
+                "sync_step from height {} to {} takes more than {} steps",
+                start,
+                target,
+                max_steps
+            );
+            let height = self.wallet.best_height();
+            let progress = self.wallet.sync_step(node, STEP_BLOCKS);
+            assert_eq!(
+                progress.target_height, target,
+                "sync_step aims at another height"
+            );
+            assert!(
+                progress.height.abs_diff(height) <= STEP_BLOCKS,
+                "sync_step moved from height {} to {} in one step of {} blocks",
+                height,
+                progress.height,
+                STEP_BLOCKS
+            );
+            self.assert_consistent(&progress);
+            if progress.is_done() {
+                break;
+            }
+        }
+
+        assert_eq!(
+            WalletState::of(&self.wallet, &self.owned),
+            WalletState::of(&self.twin, &self.owned),
+            "stepwise sync ended elsewhere than a one-shot sync"
+        );
+    }
+
+    fn best_height(&self) -> u64 {
+        self.wallet.best_height()
+    }
+
+    fn best_hash(&self) -> BlockId {
+        self.wallet.best_hash()
+    }
+
+    fn total_assets_of(&self, address: Address) -> Result<u64, WalletError> {
+        self.wallet.total_assets_of(address)
+    }
+
+    fn net_worth(&self) -> u64 {
+        self.wallet.net_worth()
+    }
+
+    fn all_coins_of(&self, address: Address) -> Result<HashSet<(CoinId, u64)>, WalletError> {
+        self.wallet.all_coins_of(address)
+    }
+
+    fn coin_details(&self, coin_id: &CoinId) -> Result<Coin, WalletError> {
+        self.wallet.coin_details(coin_id)
+    }
+
+    fn create_manual_transaction(
+        &self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError> {
+        self.wallet
+            .create_manual_transaction(input_coin_ids, output_coins)
+    }
+
+    fn create_automatic_transaction(
+        &self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    ) -> Result<Transaction, WalletError> {
+        self.wallet
+            .create_automatic_transaction(recipient, output_value, burn)
+    }
+}
+
+/// The factory of [`StepwiseWallet`]s around wallets from `F`.
+pub struct Stepwise<F>(PhantomData<F>);
+
+impl<F: WalletFactory> WalletFactory for Stepwise<F>
+where
+    F::Wallet: IncrementalWalletApi,
+{
+    type Wallet = StepwiseWallet<F::Wallet>;
+
+    const PROFILE: SpecProfile = F::PROFILE;
+
+    fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet {
+        let owned: Vec<Address> = addresses.collect();
+        StepwiseWallet {
+            wallet: F::new_wallet(owned.clone().into_iter()),
+            twin: F::new_wallet(owned.clone().into_iter()),
+            owned,
+        }
+    }
+}
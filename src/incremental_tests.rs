@@ -0,0 +1,160 @@
+//! Syncing a few blocks at a time ends where syncing in one go does
+//!
+//! Only for wallets implementing [`IncrementalWalletApi`].
+
+use crate::differential::WalletState;
+use crate::harness::{suite_tests, WalletFactory};
+use crate::incremental::{IncrementalWalletApi, Stepwise, SyncProgress};
+use crate::oracle::OracleWallet;
+use crate::random_scenario::{for_each_seed, RandomConfig, Rng, Scenario, OWNED};
+use crate::recording_node::RecordingNode;
+use crate::tags::TagFilter;
+use crate::test_helpers::mint_chain;
+use bonecoin_core::*;
+use std::panic::{self, AssertUnwindSafe};
+
+/// `reorg` tests that count node queries, which stepping and the one-shot twin of a
+/// [`Stepwise`] wallet both add to.
+const COUNTS_QUERIES: &[&str] = &["sinzii_2_tests::reorg_with_utxos_01"];
+
+pub fn every_reorg_test_stepwise<F: WalletFactory>()
+where
+    F::Wallet: IncrementalWalletApi,
+{
+    let filter = TagFilter::from_env();
+    let mut total = 0;
+    let mut failures = Vec::new();
+    for test in suite_tests::<Stepwise<F>>() {
+        let name = format!("{}::{}", test.module, test.name);
+        if test.tags[0] != "reorg"
+            || !filter.selects(test.tags)
+            || COUNTS_QUERIES.contains(&name.as_str())
+        {
+            continue;
+        }
+        total += 1;
+        match panic::catch_unwind(AssertUnwindSafe(test.run)) {
+            Ok(()) => println!("stepwise {} ... ok", name),
+            Err(_) => {
+                println!("stepwise {} ... FAILED", name);
+                failures.push(name);
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} reorg tests failed with stepwise syncs: {}",
+        failures.len(),
+        total,
+        failures.join(", ")
+    );
+}
+
+//     G - B1 - B2 - B3 - B4 - B5 - B6
+//               \
+//                 B3' - B4' - B5' - B6' - B7' (best)
+pub fn single_block_steps_follow_the_reorg_path<F: WalletFactory>()
+where
+    F::Wallet: IncrementalWalletApi,
+{
+    let mut node = RecordingNode::new();
+    let mut wallet = F::new_wallet(OWNED.into_iter());
+    let b2 = mint_chain(&mut node, Block::genesis().id(), 2, 0);
+    let b6 = mint_chain(&mut node, b2, 4, 0);
+    wallet.sync(&node);
+    let b7_prime = mint_chain(&mut node, b2, 5, 100);
+
+    // Down the old branch to B2, then up the new one.
+    let record = node.record();
+    let mut path: Vec<BlockId> = record.borrow().chain_to(b6)[1..5].to_vec();
+    path.reverse();
+    path.extend_from_slice(&record.borrow().chain_to(b7_prime)[2..]);
+
+    let mut oracle = OracleWallet::new(OWNED.into_iter(), node.record());
+    for (step, block_id) in path.iter().enumerate() {
+        let height = record.borrow().height_of(*block_id);
+        let progress = wallet.sync_step(&node, 1);
+        assert_eq!(
+            progress,
+            SyncProgress {
+                height,
+                target_height: 7,
+                reorging: step < 3,
+            },
+            "step {}",
+            step
+        );
+        assert_eq!(progress.is_done(), *block_id == b7_prime);
+
+        oracle.sync_to(*block_id);
+        assert_eq!(
+            WalletState::of(&wallet, &OWNED),
+            WalletState::of(&oracle, &OWNED),
+            "step {}: the wallet is not as synced to height {}",
+            step,
+            height
+        );
+    }
+}
+
+pub fn zero_block_step_only_reports_progress<F: WalletFactory>()
+where
+    F::Wallet: IncrementalWalletApi,
+{
+    let mut node = RecordingNode::new();
+    let mut wallet = F::new_wallet(OWNED.into_iter());
+    let b3 = mint_chain(&mut node, Block::genesis().id(), 3, 0);
+    wallet.sync(&node);
+    mint_chain(&mut node, b3, 2, 0);
+
+    let before = WalletState::of(&wallet, &OWNED);
+    let progress = wallet.sync_step(&node, 0);
+    assert_eq!(
+        progress,
+        SyncProgress {
+            height: 3,
+            target_height: 5,
+            reorging: false,
+        }
+    );
+    assert_eq!(WalletState::of(&wallet, &OWNED), before);
+
+    // A step large enough for everything finishes the sync at once.
+    let progress = wallet.sync_step(&node, u64::MAX);
+    assert!(progress.is_done());
+    assert_eq!(wallet.best_height(), 5);
+    assert_eq!(wallet.net_worth(), 15);
+}
+
+pub fn random_step_sizes_end_like_one_shot_syncs<F: WalletFactory>()
+where
+    F::Wallet: IncrementalWalletApi,
+{
+    let config = RandomConfig::reorg_heavy();
+    for_each_seed(20, |seed| {
+        let scenario = Scenario::generate(seed, &config);
+        let mut rng = Rng::new(seed);
+        let mut node = RecordingNode::new();
+        let mut wallet = F::new_wallet(OWNED.into_iter());
+        let mut oracle = OracleWallet::new(OWNED.into_iter(), node.record());
+
+        scenario.replay(&mut node, |node| {
+            // A reorg back to genesis and up is the longest path there is.
+            let record = node.record();
+            let limit = wallet.best_height() + record.borrow().height_of(record.borrow().best);
+            let mut steps = 0;
+            while !wallet.sync_step(node, 1 + rng.below(4)).is_done() {
+                steps += 1;
+                assert!(steps <= limit, "seed {}: sync_step makes no progress", seed);
+            }
+            oracle.sync(node);
+            assert_eq!(
+                WalletState::of(&wallet, &OWNED),
+                WalletState::of(&oracle, &OWNED),
+                "seed {}: stepwise sync ended elsewhere",
+                seed
+            );
+        });
+    });
+}
@@ -2,19 +2,20 @@
 
 use bonecoin_core::*;
 use std::collections::*;
-use utxo_wallet_assignment::Wallet;
+use crate::chain_builder::{ChainBuilder, MAIN};
+use crate::harness::WalletFactory;
 
 /// Simple helper to initialize a wallet with just one account.
-fn wallet_with_alice() -> Wallet {
-    Wallet::new(vec![Address::Alice].into_iter())
+fn wallet_with_alice<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice].into_iter())
 }
 
-fn wallet_with_alice_and_bob() -> Wallet {
-    Wallet::new(vec![Address::Alice, Address::Bob].into_iter())
+fn wallet_with_alice_and_bob<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice, Address::Bob].into_iter())
 }
 
-fn wallet_with_multiple_users() -> Wallet {
-    Wallet::new(vec![Address::Alice, Address::Bob, Address::Charlie].into_iter())
+fn wallet_with_multiple_users<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice, Address::Bob, Address::Charlie].into_iter())
 }
 
 /// Helper to create a simple and somewhat collision unlikely transaction to mark forks.
@@ -32,9 +33,8 @@ fn marker_tx() -> Transaction {
     }
 }
 
-#[test]
-fn correct_genesis_values() {
-    let wallet = wallet_with_alice();
+pub fn correct_genesis_values<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
 
     assert_eq!(wallet.best_height(), 0);
     assert_eq!(wallet.best_hash(), Block::genesis().id());
@@ -43,9 +43,8 @@ fn correct_genesis_values() {
     assert_eq!(wallet.all_coins_of(Address::Alice).unwrap().len(), 0);
 }
 
-#[test]
-fn foreign_address_error() {
-    let wallet = wallet_with_alice();
+pub fn foreign_address_error<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
 
     assert_eq!(
         wallet.total_assets_of(Address::Bob),
@@ -57,25 +56,23 @@ fn foreign_address_error() {
     );
 }
 
-#[test]
-fn sync_two_blocks() {
+pub fn sync_two_blocks<F: WalletFactory>() {
     // Build a mock node that has a simple two block chain
     let mut node = MockNode::new();
     let b1_id = node.add_block(Block::genesis().id(), vec![]);
     let b2_id = node.add_block_as_best(b1_id, vec![]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     assert_eq!(wallet.best_height(), 2);
     assert_eq!(wallet.best_hash(), b2_id);
 }
 
-#[test]
-fn short_reorg() {
+pub fn short_reorg<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 1
     let _old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -95,11 +92,10 @@ fn short_reorg() {
 //    G
 //        \
 //          C2            -  C3             -       C4          -        C5 (new wallet state)
-#[test]
-fn deep_reorg() {
+pub fn deep_reorg<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -122,11 +118,10 @@ fn deep_reorg() {
 //                  /
 //              G
 //                  \   B2      (should reorg the chain here)
-#[test]
-fn reorg_to_shorter_chain() {
+pub fn reorg_to_shorter_chain<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -143,8 +138,7 @@ fn reorg_to_shorter_chain() {
     assert_eq!(wallet.best_hash(), b2_id);
 }
 
-#[test]
-fn tracks_single_utxo() {
+pub fn tracks_single_utxo<F: WalletFactory>() {
     // We have a single transaction that consumes some made up input
     // and creates a single output to alice.
     const COIN_VALUE: u64 = 100;
@@ -162,7 +156,7 @@ fn tracks_single_utxo() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -175,8 +169,7 @@ fn tracks_single_utxo() {
     assert_eq!(wallet.coin_details(&coin_id), Ok(coin));
 }
 
-#[test]
-fn consumes_own_utxo() {
+pub fn consumes_own_utxo<F: WalletFactory>() {
     // All coins will be valued the same in this test
     const COIN_VALUE: u64 = 100;
 
@@ -209,7 +202,7 @@ fn consumes_own_utxo() {
     let mut node = MockNode::new();
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint]);
     let _b2_id = node.add_block_as_best(b1_id, vec![tx_burn]);
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Make sure the UTXO is cot is reasonable that the wallet could provide details about
@@ -224,8 +217,7 @@ fn consumes_own_utxo() {
 }
 
 // Track UTXOs from two transactions in a single block
-#[test]
-fn tracks_multiple_utxos() {
+pub fn tracks_multiple_utxos<F: WalletFactory>() {
     // We have a single transaction that consumes some made up input
     // and creates a single output to alice.
     const COIN_VALUE: u64 = 100;
@@ -248,7 +240,7 @@ fn tracks_multiple_utxos() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -266,8 +258,7 @@ fn tracks_multiple_utxos() {
 }
 
 // Track UTXOs to multiple users
-#[test]
-fn track_utxos_to_multiple_users() {
+pub fn track_utxos_to_multiple_users<F: WalletFactory>() {
     // We have a single transaction that consumes some made up input
     // and creates a single output to alice.
     const COIN_VALUE: u64 = 100;
@@ -290,7 +281,7 @@ fn track_utxos_to_multiple_users() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice_and_bob();
+    let mut wallet = wallet_with_alice_and_bob::<F>();
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -311,9 +302,8 @@ fn track_utxos_to_multiple_users() {
 
 // Create manual transaction
 // ... with missing input
-#[test]
-fn check_manual_transaction_with_missing_input() {
-    let wallet = wallet_with_alice();
+pub fn check_manual_transaction_with_missing_input<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
     const COIN_VALUE: u64 = 100;
     let coin = Coin {
         value: COIN_VALUE,
@@ -331,9 +321,8 @@ fn check_manual_transaction_with_missing_input() {
 }
 
 // ... with double spending
-#[test]
-fn check_manual_transaction_with_double_spending() {
-    let mut wallet = wallet_with_alice();
+pub fn check_manual_transaction_with_double_spending<F: WalletFactory>() {
+    let mut wallet = wallet_with_alice::<F>();
     const COIN_VALUE: u64 = 100;
     let coin = Coin {
         value: COIN_VALUE,
@@ -359,8 +348,7 @@ fn check_manual_transaction_with_double_spending() {
 }
 
 // ... with owner address to not be in the wallet
-#[test]
-fn check_manual_transaction_with_wrong_input_addresses() {
+pub fn check_manual_transaction_with_wrong_input_addresses<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin = Coin {
         value: COIN_VALUE,
@@ -375,7 +363,7 @@ fn check_manual_transaction_with_wrong_input_addresses() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx.clone()]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     assert_eq!(
@@ -384,9 +372,8 @@ fn check_manual_transaction_with_wrong_input_addresses() {
     );
 }
 // ... with too much output
-#[test]
-fn check_manual_transaction_with_too_much_output() {
-    let wallet = wallet_with_alice();
+pub fn check_manual_transaction_with_too_much_output<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
     let coin = Coin {
         value: 100,
         owner: Address::Alice,
@@ -402,8 +389,7 @@ fn check_manual_transaction_with_too_much_output() {
     );
 }
 // ... with zero output value
-#[test]
-fn check_manual_transaction_with_zero_output_value() {
+pub fn check_manual_transaction_with_zero_output_value<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin = Coin {
         value: COIN_VALUE,
@@ -424,7 +410,7 @@ fn check_manual_transaction_with_zero_output_value() {
         owner: Address::Alice,
     };
 
-    let mut wallet: Wallet = wallet_with_alice();
+    let mut wallet: F::Wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     assert_eq!(
@@ -435,8 +421,7 @@ fn check_manual_transaction_with_zero_output_value() {
 
 // Create automatic transactions
 // ... with too much output
-#[test]
-fn check_automatic_transaction_with_too_much_output() {
+pub fn check_automatic_transaction_with_too_much_output<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin1 = Coin {
         value: COIN_VALUE,
@@ -451,7 +436,7 @@ fn check_automatic_transaction_with_too_much_output() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     let transaction_auto = wallet.create_automatic_transaction(Address::Bob, COIN_VALUE + 1, 0);
@@ -459,8 +444,7 @@ fn check_automatic_transaction_with_too_much_output() {
 }
 
 // ... from multiple addresses from our wallet
-#[test]
-fn check_automatic_transaction_from_multiple_users() {
+pub fn check_automatic_transaction_from_multiple_users<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin1 = Coin {
         value: COIN_VALUE,
@@ -479,7 +463,7 @@ fn check_automatic_transaction_from_multiple_users() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice_and_bob();
+    let mut wallet = wallet_with_alice_and_bob::<F>();
     wallet.sync(&node);
 
     match wallet.create_automatic_transaction(Address::Bob, COIN_VALUE * 2, 0) {
@@ -495,8 +479,7 @@ fn check_automatic_transaction_from_multiple_users() {
 }
 
 // ... with zero change
-#[test]
-fn check_automatic_transaction_with_zero_change() {
+pub fn check_automatic_transaction_with_zero_change<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin1 = Coin {
         value: COIN_VALUE,
@@ -512,7 +495,7 @@ fn check_automatic_transaction_with_zero_change() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     match wallet.create_automatic_transaction(Address::Bob, 50, 50) {
@@ -528,67 +511,48 @@ fn check_automatic_transaction_with_zero_change() {
 }
 
 // Reorg performance tests to make sure they aren't just syncing from genesis each time.
-#[test]
-fn reorg_performance() {
-    // Create node and wallet
-    let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+pub fn reorg_performance<F: WalletFactory>() {
+    // Create chain and wallet
+    let mut chain = ChainBuilder::new();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 10
-    let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
-    let old_b2_id = node.add_block_as_best(old_b1_id, vec![]);
-    let old_b3_id = node.add_block_as_best(old_b2_id, vec![]);
-    let old_b4_id = node.add_block_as_best(old_b3_id, vec![]);
-    let old_b5_id = node.add_block_as_best(old_b4_id, vec![]);
-    let old_b6_id = node.add_block_as_best(old_b5_id, vec![]);
-    let old_b7_id = node.add_block_as_best(old_b6_id, vec![]);
-    let old_b8_id = node.add_block_as_best(old_b7_id, vec![]);
-    let old_b9_id = node.add_block_as_best(old_b8_id, vec![]);
-    let _old_b10_id = node.add_block_as_best(old_b9_id, vec![]);
-    node.add_block_as_best(old_b9_id, vec![]);
-    wallet.sync(&node);
+    chain.extend(MAIN, 10).set_best(MAIN);
+    wallet.sync(chain.node());
 
-    // Reorg to shorter chain of length 8
-    let b7_bis_id = node.add_block_as_best(old_b7_id, vec![marker_tx()]);
-    let b8_bis_id = node.add_block_as_best(b7_bis_id, vec![]);
-    wallet.sync(&node);
+    // Reorg to shorter chain of length 9
+    chain.fork("bis", "main:7");
+    chain.block("bis", vec![marker_tx()]);
+    chain.extend("bis", 1).set_best("bis");
+    wallet.sync(chain.node());
 
     // MODIFIED: change from best_height to best_height(), same for best_hash
     println!("Wallet best_height: {:?}", wallet.best_height());
     println!("Wallet best_hash: {:?}", wallet.best_hash());
 
     assert_eq!(wallet.best_height(), 9);
-    assert_eq!(wallet.best_hash(), b8_bis_id);
+    assert_eq!(wallet.best_hash(), chain.id("bis"));
 }
 
-#[test]
-fn deep_reorg_to_short_chain() {
-    // Create node and wallet
-    let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+pub fn deep_reorg_to_short_chain<F: WalletFactory>() {
+    // Create chain and wallet
+    let mut chain = ChainBuilder::new();
+    let mut wallet = wallet_with_alice::<F>();
 
-    // Sync a chain to height 3
-    let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
-    let old_b2_id = node.add_block_as_best(old_b1_id, vec![]);
-    let old_b3_id = node.add_block_as_best(old_b2_id, vec![]);
-    let old_b4_id = node.add_block_as_best(old_b3_id, vec![]);
-    let old_b5_id = node.add_block_as_best(old_b4_id, vec![]);
-    let old_b6_id = node.add_block_as_best(old_b5_id, vec![]);
-    let _old_b7_id = node.add_block_as_best(old_b6_id, vec![]);
-    wallet.sync(&node);
+    // Sync a chain to height 7
+    chain.extend(MAIN, 7).set_best(MAIN);
+    wallet.sync(chain.node());
 
-    let b1_id = node.add_block(Block::genesis().id(), vec![marker_tx()]);
-    let b2_id = node.add_block_as_best(b1_id, vec![]);
-    let b3_id = node.add_block_as_best(b2_id, vec![]);
-    let b4_id = node.add_block_as_best(b3_id, vec![]);
-    wallet.sync(&node);
+    chain.fork("new", "G");
+    chain.block("new", vec![marker_tx()]);
+    chain.extend("new", 3).set_best("new");
+    wallet.sync(chain.node());
 
     assert_eq!(wallet.best_height(), 4);
-    assert_eq!(wallet.best_hash(), b4_id);
+    assert_eq!(wallet.best_hash(), chain.id("new"));
 }
 
-#[test]
-fn dont_save_coins_not_owned_by_our_wallet_addresses() {
+pub fn dont_save_coins_not_owned_by_our_wallet_addresses<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin1 = Coin {
         value: COIN_VALUE,
@@ -618,7 +582,7 @@ fn dont_save_coins_not_owned_by_our_wallet_addresses() {
 
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx]);
@@ -633,11 +597,10 @@ fn dont_save_coins_not_owned_by_our_wallet_addresses() {
     assert!(wallet.net_worth() == 200);
 }
 
-#[test]
-fn reorg_hard_test_hehe() {
+pub fn reorg_hard_test_hehe<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice_and_bob();
+    let mut wallet = wallet_with_alice_and_bob::<F>();
     // Mint some coins
     let coin1 = Coin {
         value: 100,
@@ -824,7 +787,7 @@ fn reorg_hard_test_hehe() {
     assert_eq!(1045, wallet.net_worth());
 }
 
-// fn initial_setup() -> (impl WalletApi, MockNode) {
+// fn initial_setup::<F>() -> (impl WalletApi, MockNode) {
 //     // All coins will be valued the same in this test
 //     // We start by minting a coin to alice
 //     let tx_mint_1 = Transaction {
@@ -867,7 +830,7 @@ fn reorg_hard_test_hehe() {
 //     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint_1.clone()]);
 //     let _b2_id = node.add_block_as_best(b1_id, vec![tx_mint_2.clone()]);
 
-//     let mut wallet = wallet_with_multiple_users();
+//     let mut wallet = wallet_with_multiple_users::<F>();
 //     wallet.sync(&node);
 
 //     (wallet, node)
@@ -875,7 +838,7 @@ fn reorg_hard_test_hehe() {
 
 // #[test]
 // fn reorg_in_the_middle_with_tx_changes() {
-//     let (mut wallet, mut node) = initial_setup();
+//     let (mut wallet, mut node) = initial_setup::<F>();
 
 //     wallet.sync(&node);
 //     assert_eq!(wallet.total_assets_of(Address::Charlie), Ok(300));
@@ -904,10 +867,9 @@ fn reorg_hard_test_hehe() {
 //     assert_eq!(wallet.total_assets_of(Address::Charlie), Ok(0));
 // }
 
-#[test]
-fn test_reorgs_with_utxos_in_chain_history() {
+pub fn test_reorgs_with_utxos_in_chain_history<F: WalletFactory>() {
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     let coin_1 = Coin {
         value: 50,
@@ -4,15 +4,15 @@
 
 use std::collections::*;
 use bonecoin_core::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
 
 /// Simple helper to initialize a wallet with just one account.
-fn wallet_with_alice() -> Wallet {
-    Wallet::new(vec![Address::Alice].into_iter())
+fn wallet_with_alice<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice].into_iter())
 }
 
-fn wallet_with_alice_and_bob() -> Wallet {
-    Wallet::new(vec![Address::Alice, Address::Bob].into_iter())
+fn wallet_with_alice_and_bob<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice, Address::Bob].into_iter())
 }
 
 /// Helper to create a simple and somewhat collision unlikely transaction to mark forks.
@@ -30,9 +30,8 @@ fn marker_tx() -> Transaction {
     }
 }
 
-#[test]
-fn correct_genesis_values() {
-    let wallet = wallet_with_alice();
+pub fn correct_genesis_values<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
 
     assert_eq!(wallet.best_height(), 0);
     assert_eq!(wallet.best_hash(), Block::genesis().id());
@@ -41,9 +40,8 @@ fn correct_genesis_values() {
     assert_eq!(wallet.all_coins_of(Address::Alice).unwrap().len(), 0);
 }
 
-#[test]
-fn foreign_address_error() {
-    let wallet = wallet_with_alice();
+pub fn foreign_address_error<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
 
     assert_eq!(
         wallet.total_assets_of(Address::Bob),
@@ -55,25 +53,23 @@ fn foreign_address_error() {
     );
 }
 
-#[test]
-fn sync_two_blocks() {
+pub fn sync_two_blocks<F: WalletFactory>() {
     // Build a mock node that has a simple two block chain
     let mut node = MockNode::new();
     let b1_id = node.add_block(Block::genesis().id(), vec![]);
     let b2_id = node.add_block_as_best(b1_id, vec![]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     assert_eq!(wallet.best_height(), 2);
     assert_eq!(wallet.best_hash(), b2_id);
 }
 
-#[test]
-fn short_reorg() {
+pub fn short_reorg<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 1
     let _old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -93,11 +89,10 @@ fn short_reorg() {
 //    G
 //        \
 //          C2            -  C3             -       C4          -        C5 (new wallet state)
-#[test]
-fn deep_reorg() {
+pub fn deep_reorg<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -120,11 +115,10 @@ fn deep_reorg() {
 //                  /
 //              G
 //                  \   B2      (should reorg the chain here)
-#[test]
-fn reorg_to_shorter_chain() {
+pub fn reorg_to_shorter_chain<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -141,8 +135,7 @@ fn reorg_to_shorter_chain() {
     assert_eq!(wallet.best_hash(), b2_id);
 }
 
-#[test]
-fn tracks_single_utxo() {
+pub fn tracks_single_utxo<F: WalletFactory>() {
     // We have a single transaction that consumes some made up input
     // and creates a single output to alice.
     const COIN_VALUE: u64 = 100;
@@ -160,7 +153,7 @@ fn tracks_single_utxo() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -173,8 +166,7 @@ fn tracks_single_utxo() {
     assert_eq!(wallet.coin_details(&coin_id), Ok(coin));
 }
 
-#[test]
-fn consumes_own_utxo() {
+pub fn consumes_own_utxo<F: WalletFactory>() {
     // All coins will be valued the same in this test
     const COIN_VALUE: u64 = 100;
 
@@ -207,7 +199,7 @@ fn consumes_own_utxo() {
     let mut node = MockNode::new();
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint]);
     let _b2_id = node.add_block_as_best(b1_id, vec![tx_burn]);
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Make sure the UTXO is cot is reasonable that the wallet could provide details about
@@ -222,8 +214,7 @@ fn consumes_own_utxo() {
 }
 
 // Track UTXOs from two transactions in a single block
-#[test]
-fn tracks_multiple_utxos() {
+pub fn tracks_multiple_utxos<F: WalletFactory>() {
     // We have a single transaction that consumes some made up input
     // and creates a single output to alice.
     const COIN_VALUE: u64 = 100;
@@ -246,7 +237,7 @@ fn tracks_multiple_utxos() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -261,8 +252,7 @@ fn tracks_multiple_utxos() {
 }   
 
 // Track UTXOs to multiple users
-#[test]
-fn track_utxos_to_multiple_users() {
+pub fn track_utxos_to_multiple_users<F: WalletFactory>() {
     // We have a single transaction that consumes some made up input
     // and creates a single output to alice.
     const COIN_VALUE: u64 = 100;
@@ -285,7 +275,7 @@ fn track_utxos_to_multiple_users() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice_and_bob();
+    let mut wallet = wallet_with_alice_and_bob::<F>();
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -306,9 +296,8 @@ fn track_utxos_to_multiple_users() {
 
 // Create manual transaction
 // ... with missing input
-#[test]
-fn check_manual_transaction_with_missing_input() {
-    let wallet = wallet_with_alice();
+pub fn check_manual_transaction_with_missing_input<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
     const COIN_VALUE: u64 = 100;
     let coin = Coin {
         value: COIN_VALUE,
@@ -326,8 +315,7 @@ fn check_manual_transaction_with_missing_input() {
 } 
 
 // ... with owner address to not be in the wallet
-#[test]
-fn check_manual_transaction_with_wrong_input_addresses() {
+pub fn check_manual_transaction_with_wrong_input_addresses<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin = Coin {
         value: COIN_VALUE,
@@ -342,7 +330,7 @@ fn check_manual_transaction_with_wrong_input_addresses() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx.clone()]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     assert_eq!(
@@ -351,9 +339,8 @@ fn check_manual_transaction_with_wrong_input_addresses() {
     );
 } 
 // ... with too much output
-#[test]
-fn check_manual_transaction_with_too_much_output() {
-    let wallet = wallet_with_alice();
+pub fn check_manual_transaction_with_too_much_output<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
     let coin = Coin {
         value: 100,
         owner: Address::Alice,
@@ -369,8 +356,7 @@ fn check_manual_transaction_with_too_much_output() {
     );
 }
 // ... with zero output value
-#[test]
-fn check_manual_transaction_with_zero_output_value() {
+pub fn check_manual_transaction_with_zero_output_value<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin = Coin {
         value: COIN_VALUE,
@@ -391,7 +377,7 @@ fn check_manual_transaction_with_zero_output_value() {
         owner: Address::Alice,
     };
 
-    let mut wallet: Wallet = wallet_with_alice();
+    let mut wallet: F::Wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     assert_eq!(
@@ -403,8 +389,7 @@ fn check_manual_transaction_with_zero_output_value() {
 
 // Create automatic transactions
 // ... with too much output
-#[test]
-fn check_automatic_transaction_with_too_much_output() {
+pub fn check_automatic_transaction_with_too_much_output<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin1 = Coin {
         value: COIN_VALUE,
@@ -419,15 +404,14 @@ fn check_automatic_transaction_with_too_much_output() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     let transaction_auto = wallet.create_automatic_transaction(Address::Bob, COIN_VALUE + 1, 0);
     assert_eq!(transaction_auto, Err(WalletError::InsufficientFunds));
 }
 // ... with zero change
-#[test]
-fn check_automatic_transaction_with_zero_change() {
+pub fn check_automatic_transaction_with_zero_change<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin1 = Coin {
         value: COIN_VALUE,
@@ -443,7 +427,7 @@ fn check_automatic_transaction_with_zero_change() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     match wallet.create_automatic_transaction(Address::Bob, 50, 50) {
@@ -460,11 +444,10 @@ fn check_automatic_transaction_with_zero_change() {
 }
 
 // Reorg performance tests to make sure they aren't just syncing from genesis each time.
-#[test]
-fn reorg_performance() {
+pub fn reorg_performance<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 10
     let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -493,11 +476,10 @@ fn reorg_performance() {
     assert_eq!(wallet.best_hash(), b8_bis_id);
 }
 
-#[test]
-fn deep_reorg_to_short_chain() {
+pub fn deep_reorg_to_short_chain<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -519,8 +501,7 @@ fn deep_reorg_to_short_chain() {
     assert_eq!(wallet.best_hash(), b4_id);
 }
 
-#[test]
-fn dont_save_coins_not_owned_by_our_wallet_addresses() {
+pub fn dont_save_coins_not_owned_by_our_wallet_addresses<F: WalletFactory>() {
     const COIN_VALUE: u64 = 100;
     let coin1 = Coin {
         value: COIN_VALUE,
@@ -550,7 +531,7 @@ fn dont_save_coins_not_owned_by_our_wallet_addresses() {
 
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx]);
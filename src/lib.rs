@@ -1,11 +1,57 @@
-mod esteblock_tests;
-mod krayt78_tests;
-mod sinzii_tests;
-mod bigtava_tests;
-mod tommy97_tests;
-mod trantorian_tests;
-mod kwar13_tests;
-mod main_tests;
-mod krayt78_2_tests;
-mod sinzii_2_tests;
-mod tarekkma_tests;
\ No newline at end of file
+pub mod bench;
+pub mod chain_builder;
+pub mod confirmations;
+pub mod counting_alloc;
+pub mod diagram;
+pub mod differential;
+pub mod events;
+pub mod finality;
+pub mod harness;
+pub mod history;
+pub mod incremental;
+pub mod oracle;
+pub mod pending;
+pub mod persistence;
+pub mod random_scenario;
+pub mod recording_node;
+pub mod scenario_file;
+pub mod scorecard;
+pub mod shrink;
+pub mod spec_profile;
+pub mod strict_node;
+pub mod tags;
+pub mod test_helpers;
+pub mod tree_file;
+pub mod tx_validation;
+
+pub mod esteblock_tests;
+pub mod krayt78_tests;
+pub mod sinzii_tests;
+pub mod bigtava_tests;
+pub mod tommy97_tests;
+pub mod trantorian_tests;
+pub mod kwar13_tests;
+pub mod main_tests;
+pub mod krayt78_2_tests;
+pub mod sinzii_2_tests;
+pub mod tarekkma_tests;
+pub mod differential_tests;
+pub mod chain_builder_tests;
+pub mod diagram_tests;
+pub mod random_tests;
+pub mod shrink_tests;
+pub mod scenario_file_tests;
+pub mod memory_tests;
+pub mod strict_node_tests;
+pub mod round_trip_tests;
+pub mod pending_tests;
+pub mod persistence_tests;
+pub mod tree_file_tests;
+pub mod history_tests;
+pub mod events_tests;
+pub mod incremental_tests;
+pub mod confirmations_tests;
+pub mod finality_tests;
+pub mod tx_validation_tests;
+
+pub use harness::WalletFactory;
@@ -2,11 +2,12 @@
 
 use std::collections::*;
 use bonecoin_core::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
+use crate::diagram::Diagram;
 
 /// Simple helper to initialize a wallet with just one account.
-fn wallet_with_alice() -> Wallet {
-    Wallet::new(vec![Address::Alice].into_iter())
+fn wallet_with_alice<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice].into_iter())
 }
 
 /// Helper to create a simple and somewhat collision unlikely transaction to mark forks.
@@ -24,9 +25,8 @@ fn marker_tx() -> Transaction {
     }
 }
 
-#[test]
-fn correct_genesis_values() {
-    let wallet = wallet_with_alice();
+pub fn correct_genesis_values<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
 
     assert_eq!(wallet.best_height(), 0);
     assert_eq!(wallet.best_hash(), Block::genesis().id());
@@ -35,9 +35,8 @@ fn correct_genesis_values() {
     assert_eq!(wallet.all_coins_of(Address::Alice).unwrap().len(), 0);
 }
 
-#[test]
-fn foreign_address_error() {
-    let wallet = wallet_with_alice();
+pub fn foreign_address_error<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
 
     assert_eq!(
         wallet.total_assets_of(Address::Bob),
@@ -49,25 +48,23 @@ fn foreign_address_error() {
     );
 }
 
-#[test]
-fn sync_two_blocks() {
+pub fn sync_two_blocks<F: WalletFactory>() {
     // Build a mock node that has a simple two block chain
     let mut node = MockNode::new();
     let b1_id = node.add_block(Block::genesis().id(), vec![]);
     let b2_id = node.add_block_as_best(b1_id, vec![]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     assert_eq!(wallet.best_height(), 2);
     assert_eq!(wallet.best_hash(), b2_id);
 }
 
-#[test]
-fn short_reorg() {
+pub fn short_reorg<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 1
     let _old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -82,61 +79,58 @@ fn short_reorg() {
     assert_eq!(wallet.best_hash(), b2_id);
 }
 
-//          B2 (discard)  -  B3 (discard)
-//        /
-//    G
-//        \
-//          C2            -  C3             -       C4          -        C5 (new wallet state)
-#[test]
-fn deep_reorg() {
+pub fn deep_reorg<F: WalletFactory>() {
+    let diagram = Diagram::parse(
+        r"
+        //          B1 (discard)  -  B2 (discard)  -  B3 (discard)
+        //        /
+        //    G
+        //        \
+        //          C1            -  C2            -  C3  -  C4  -  C5* (new wallet state)
+        ",
+    );
     // Create node and wallet
-    let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut chain = diagram.build(vec![]);
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
-    let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
-    let old_b2_id = node.add_block_as_best(old_b1_id, vec![]);
-    let _old_b3_id = node.add_block_as_best(old_b2_id, vec![]);
-    wallet.sync(&node);
+    chain.set_best("B3");
+    wallet.sync(chain.node());
 
-    let b1_id = node.add_block(Block::genesis().id(), vec![marker_tx()]);
-    let b2_id = node.add_block_as_best(b1_id, vec![]);
-    let b3_id = node.add_block_as_best(b2_id, vec![]);
-    let b4_id = node.add_block_as_best(b3_id, vec![]);
-    let b5_id = node.add_block_as_best(b4_id, vec![]);
-    wallet.sync(&node);
+    // Reorg to the longer chain of length 5
+    chain.set_best(diagram.best());
+    wallet.sync(chain.node());
 
     assert_eq!(wallet.best_height(), 5);
-    assert_eq!(wallet.best_hash(), b5_id);
+    assert_eq!(wallet.best_hash(), chain.id("C5"));
 }
 
-//                      Old_B2 (discard)   -     Old_B3 (discard)
-//                  /
-//              G
-//                  \   B2      (should reorg the chain here)
-#[test]
-fn reorg_to_shorter_chain() {
+pub fn reorg_to_shorter_chain<F: WalletFactory>() {
+    let diagram = Diagram::parse(
+        r"
+        //                      Old_B1 (discard)  -  Old_B2 (discard)  -  Old_B3 (discard)
+        //                  /
+        //              G
+        //                  \   B1  -  B2*     (should reorg the chain here)
+        ",
+    );
     // Create node and wallet
-    let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut chain = diagram.build(vec![]);
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
-    let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
-    let old_b2_id = node.add_block_as_best(old_b1_id, vec![]);
-    let _old_b3_id = node.add_block_as_best(old_b2_id, vec![]);
-    wallet.sync(&node);
+    chain.set_best("Old_B3");
+    wallet.sync(chain.node());
 
     // Reorg to shorter chain of length 2
-    let b1_id = node.add_block(Block::genesis().id(), vec![marker_tx()]);
-    let b2_id = node.add_block_as_best(b1_id, vec![]);
-    wallet.sync(&node);
+    chain.set_best(diagram.best());
+    wallet.sync(chain.node());
 
     assert_eq!(wallet.best_height(), 2);
-    assert_eq!(wallet.best_hash(), b2_id);
+    assert_eq!(wallet.best_hash(), chain.id("B2"));
 }
 
-#[test]
-fn tracks_single_utxo() {
+pub fn tracks_single_utxo<F: WalletFactory>() {
     // We have a single transaction that consumes some made up input
     // and creates a single output to alice.
     const COIN_VALUE: u64 = 100;
@@ -154,7 +148,7 @@ fn tracks_single_utxo() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -167,8 +161,7 @@ fn tracks_single_utxo() {
     assert_eq!(wallet.coin_details(&coin_id), Ok(coin));
 }
 
-#[test]
-fn consumes_own_utxo() {
+pub fn consumes_own_utxo<F: WalletFactory>() {
     // All coins will be valued the same in this test
     const COIN_VALUE: u64 = 100;
 
@@ -201,7 +194,7 @@ fn consumes_own_utxo() {
     let mut node = MockNode::new();
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint]);
     let _b2_id = node.add_block_as_best(b1_id, vec![tx_burn]);
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Make sure the UTXO is consumed
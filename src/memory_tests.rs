@@ -0,0 +1,134 @@
+//! Memory performance tests to make sure wallets aren't just keeping a snapshot of the entire
+//! UTXO set at every height
+//!
+//! Every test syncs the same kind of chain at two lengths. Retained heap must roughly double
+//! with the chain, and stay within a fixed budget per block and per coin. A snapshot per height
+//! grows with height × UTXO set size instead and blows both limits.
+
+use crate::counting_alloc::bytes_retained_by;
+use crate::harness::WalletFactory;
+use bonecoin_core::*;
+
+/// Heap a wallet may keep per block of the best chain.
+const BYTES_PER_BLOCK: isize = 4 * 1024;
+/// Heap a wallet may keep per coin created on the best chain.
+const BYTES_PER_COIN: isize = 1024;
+
+/// Sync a fresh wallet owning Alice and Bob on `node` and return the heap it kept, or `None`
+/// if the counting allocator is not installed.
+fn retained_after_sync<F: WalletFactory>(node: &MockNode) -> Option<isize> {
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
+    let retained = bytes_retained_by(|| wallet.sync(node))?;
+    assert!(retained > 0, "the wallet kept no heap at all");
+    Some(retained)
+}
+
+fn assert_linear(short: (isize, isize, isize), long: (isize, isize, isize)) {
+    let (short_bytes, short_blocks, short_coins) = short;
+    let (long_bytes, long_blocks, long_coins) = long;
+
+    for (bytes, blocks, coins) in [short, long] {
+        let budget = BYTES_PER_BLOCK * blocks + BYTES_PER_COIN * coins;
+        assert!(
+            bytes <= budget,
+            "{} bytes kept for {} blocks and {} coins, the budget is {}",
+            bytes,
+            blocks,
+            coins,
+            budget
+        );
+    }
+
+    // Twice the chain data may cost up to three times the memory.
+    let data_ratio = (long_blocks + long_coins) as f64 / (short_blocks + short_coins) as f64;
+    let memory_ratio = long_bytes as f64 / short_bytes as f64;
+    assert!(
+        memory_ratio <= 1.5 * data_ratio,
+        "{} bytes for {} blocks but {} bytes for {} blocks, memory grows faster than the chain",
+        short_bytes,
+        short_blocks,
+        long_bytes,
+        long_blocks
+    );
+}
+
+/// Every block mints `coins_per_block` coins that are never spent, so the UTXO set grows with
+/// the height.
+fn growing_utxo_set(blocks: u64, coins_per_block: u64) -> MockNode {
+    let mut node = MockNode::new();
+    let mut parent = Block::genesis().id();
+    for height in 1..=blocks {
+        let outputs = (0..coins_per_block)
+            .map(|i| Coin {
+                value: height * 1_000 + i + 1,
+                owner: if i % 2 == 0 {
+                    Address::Alice
+                } else {
+                    Address::Bob
+                },
+            })
+            .collect();
+        let mint = Transaction {
+            inputs: vec![],
+            outputs,
+        };
+        parent = node.add_block_as_best(parent, vec![mint]);
+    }
+    node
+}
+
+/// Block 1 mints `utxos` coins to Alice, every later block spends one of them and creates a
+/// new one, so the UTXO set keeps its size while the chain grows.
+fn steady_utxo_set(blocks: u64, utxos: u64) -> MockNode {
+    let mut node = MockNode::new();
+    let mint = Transaction {
+        inputs: vec![],
+        outputs: (0..utxos)
+            .map(|i| Coin {
+                value: 1_000_000 + i,
+                owner: Address::Alice,
+            })
+            .collect(),
+    };
+    let mut unspent: Vec<CoinId> = (0..utxos as usize).map(|i| mint.coin_id(1, i)).collect();
+    let mut parent = node.add_block_as_best(Block::genesis().id(), vec![mint]);
+
+    for height in 2..=blocks {
+        let spent = unspent.remove(0);
+        let tx = Transaction {
+            inputs: vec![Input {
+                coin_id: spent,
+                signature: Signature::Valid(Address::Alice),
+            }],
+            outputs: vec![Coin {
+                value: height,
+                owner: Address::Alice,
+            }],
+        };
+        unspent.push(tx.coin_id(height, 0));
+        parent = node.add_block_as_best(parent, vec![tx]);
+    }
+    node
+}
+
+pub fn memory_grows_linearly_with_a_growing_utxo_set<F: WalletFactory>() {
+    let (Some(short), Some(long)) = (
+        retained_after_sync::<F>(&growing_utxo_set(150, 20)),
+        retained_after_sync::<F>(&growing_utxo_set(300, 20)),
+    ) else {
+        eprintln!("skipped: the counting allocator is not installed");
+        return;
+    };
+    assert_linear((short, 150, 150 * 20), (long, 300, 300 * 20));
+}
+
+pub fn memory_grows_linearly_with_a_steady_utxo_set<F: WalletFactory>() {
+    let (Some(short), Some(long)) = (
+        retained_after_sync::<F>(&steady_utxo_set(500, 200)),
+        retained_after_sync::<F>(&steady_utxo_set(1000, 200)),
+    ) else {
+        eprintln!("skipped: the counting allocator is not installed");
+        return;
+    };
+    assert_linear((short, 500, 200 + 499), (long, 1000, 200 + 999));
+}
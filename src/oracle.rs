@@ -0,0 +1,235 @@
+//! A deliberately naive reference wallet.
+//!
+//! It throws its state away and replays the whole best chain from genesis on every `sync`, which
+//! makes it slow but easy to trust. The differential checker compares real wallets against it.
+//! The blocks come from the record of a [`RecordingNode`](crate::recording_node::RecordingNode),
+//! so it only syncs on the node that keeps that record.
+//! Its automatic transactions follow
+//! [`SpecProfile::REFERENCE`](crate::spec_profile::SpecProfile::REFERENCE).
+
+use crate::recording_node::ChainRecord;
+use bonecoin_core::*;
+use std::cell::RefCell;
+use std::collections::*;
+use std::rc::Rc;
+
+pub struct OracleWallet {
+    addresses: Vec<Address>,
+    record: Rc<RefCell<ChainRecord>>,
+    best_height: u64,
+    best_hash: BlockId,
+    utxos: HashMap<CoinId, Coin>,
+}
+
+impl OracleWallet {
+    /// Create an oracle owning `addresses` that reads blocks from the given recorded tree,
+    /// usually obtained with [`RecordingNode::record`](crate::recording_node::RecordingNode::record).
+    pub fn new(addresses: impl Iterator<Item = Address>, record: Rc<RefCell<ChainRecord>>) -> Self {
+        OracleWallet {
+            addresses: addresses.collect(),
+            record,
+            best_height: 0,
+            best_hash: Block::genesis().id(),
+            utxos: HashMap::new(),
+        }
+    }
+
+    fn owns(&self, address: Address) -> bool {
+        self.addresses.contains(&address)
+    }
+
+    /// Follow the chain ending at `tip` instead of the best chain, as if it were best.
+    pub fn sync_to(&mut self, tip: BlockId) {
+        let record = self.record.borrow();
+        let mut utxos = HashMap::new();
+
+        for block_id in record.chain_to(tip) {
+            let block = &record.blocks[&block_id];
+            for tx in &block.transactions {
+                for input in &tx.inputs {
+                    utxos.remove(&input.coin_id);
+                }
+                for (index, coin) in tx.outputs.iter().enumerate() {
+                    if self.addresses.contains(&coin.owner) {
+                        utxos.insert(tx.coin_id(block.height, index), coin.clone());
+                    }
+                }
+            }
+        }
+
+        self.best_hash = tip;
+        self.best_height = record.height_of(tip);
+        self.utxos = utxos;
+    }
+
+    /// Panic unless `node` has the best chain of the record, block for block.
+    fn assert_follows(&self, node: &MockNode) {
+        let best_chain = self.record.borrow().best_chain();
+        for (height, block_id) in (1..).zip(&best_chain) {
+            assert_eq!(
+                node.best_block_at_height(height),
+                Some(*block_id),
+                "the oracle syncs on another node than the RecordingNode of its record, their \
+                 best chains differ at height {}",
+                height
+            );
+        }
+        let above = best_chain.len() as u64 + 1;
+        assert_eq!(
+            node.best_block_at_height(above),
+            None,
+            "the oracle syncs on another node than the RecordingNode of its record, which has a \
+             block at height {}",
+            above
+        );
+    }
+
+    /// Owned coins sorted from the largest to the smallest value.
+    fn coins_by_value(&self) -> Vec<(CoinId, Coin)> {
+        let mut coins: Vec<_> = self
+            .utxos
+            .iter()
+            .map(|(coin_id, coin)| (*coin_id, coin.clone()))
+            .collect();
+        coins.sort_by(|a, b| b.1.value.cmp(&a.1.value));
+        coins
+    }
+}
+
+impl WalletApi for OracleWallet {
+    /// `node` must be the [`RecordingNode`](crate::recording_node::RecordingNode) of the
+    /// oracle's record. Blocks are read from the record, the node is only checked against it.
+    fn sync(&mut self, node: &MockNode) {
+        self.assert_follows(node);
+        let best = self.record.borrow().best;
+        self.sync_to(best);
+    }
+
+    fn best_height(&self) -> u64 {
+        self.best_height
+    }
+
+    fn best_hash(&self) -> BlockId {
+        self.best_hash
+    }
+
+    fn total_assets_of(&self, address: Address) -> Result<u64, WalletError> {
+        if !self.owns(address) {
+            return Err(WalletError::ForeignAddress);
+        }
+        Ok(self
+            .utxos
+            .values()
+            .filter(|coin| coin.owner == address)
+            .fold(0u64, |total, coin| total.saturating_add(coin.value)))
+    }
+
+    fn net_worth(&self) -> u64 {
+        self.utxos
+            .values()
+            .fold(0u64, |total, coin| total.saturating_add(coin.value))
+    }
+
+    fn all_coins_of(&self, address: Address) -> Result<HashSet<(CoinId, u64)>, WalletError> {
+        if !self.owns(address) {
+            return Err(WalletError::ForeignAddress);
+        }
+        Ok(self
+            .utxos
+            .iter()
+            .filter(|(_, coin)| coin.owner == address)
+            .map(|(coin_id, coin)| (*coin_id, coin.value))
+            .collect())
+    }
+
+    fn coin_details(&self, coin_id: &CoinId) -> Result<Coin, WalletError> {
+        self.utxos
+            .get(coin_id)
+            .cloned()
+            .ok_or(WalletError::UnknownCoin)
+    }
+
+    fn create_manual_transaction(
+        &self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError> {
+        if input_coin_ids.is_empty() {
+            return Err(WalletError::ZeroInputs);
+        }
+        if output_coins.iter().any(|coin| coin.value == 0) {
+            return Err(WalletError::ZeroCoinValue);
+        }
+
+        let mut seen = HashSet::new();
+        let mut inputs = Vec::new();
+        let mut input_total: u128 = 0;
+        for coin_id in input_coin_ids {
+            let coin = self.utxos.get(&coin_id).ok_or(WalletError::UnknownCoin)?;
+            if !seen.insert(coin_id) {
+                return Err(WalletError::UnknownCoin);
+            }
+            input_total += coin.value as u128;
+            inputs.push(Input {
+                coin_id,
+                signature: Signature::Valid(coin.owner),
+            });
+        }
+
+        let output_total: u128 = output_coins.iter().map(|coin| coin.value as u128).sum();
+        if output_total > input_total {
+            return Err(WalletError::InsufficientFunds);
+        }
+
+        Ok(Transaction {
+            inputs,
+            outputs: output_coins,
+        })
+    }
+
+    fn create_automatic_transaction(
+        &self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    ) -> Result<Transaction, WalletError> {
+        let change_address = *self
+            .addresses
+            .first()
+            .ok_or(WalletError::NoOwnedAddresses)?;
+        if output_value == 0 {
+            return Err(WalletError::ZeroCoinValue);
+        }
+
+        let needed = output_value as u128 + burn as u128;
+        let mut inputs = Vec::new();
+        let mut input_total: u128 = 0;
+        for (coin_id, coin) in self.coins_by_value() {
+            if input_total >= needed {
+                break;
+            }
+            input_total += coin.value as u128;
+            inputs.push(Input {
+                coin_id,
+                signature: Signature::Valid(coin.owner),
+            });
+        }
+        if input_total < needed {
+            return Err(WalletError::InsufficientFunds);
+        }
+
+        let mut outputs = vec![Coin {
+            value: output_value,
+            owner: recipient,
+        }];
+        let change = input_total - needed;
+        if change > 0 {
+            outputs.push(Coin {
+                value: change as u64,
+                owner: change_address,
+            });
+        }
+
+        Ok(Transaction { inputs, outputs })
+    }
+}
@@ -0,0 +1,127 @@
+//! Optional wallet extension: transactions the wallet created but that are not mined yet.
+//!
+//! `WalletApi::create_automatic_transaction` takes `&self`, so two calls in a row can pick the
+//! same coins and build conflicting transactions. Wallets implementing [`PendingWalletApi`]
+//! remember what they created and keep its inputs for it until `sync` shows what happened:
+//!
+//! - Once the transaction is on the best chain it is no longer pending, its outputs are plain
+//!   coins of the best chain.
+//! - Once any of its inputs is gone from the best chain, spent by something else or reorged
+//!   away, it can never be mined and is dropped too, releasing the inputs that are left.
+//!
+//! A mined transaction that a later reorg takes off the best chain does not come back as
+//! pending: its inputs are simply unspent coins again.
+//!
+//! Enable the tests with `wallet_test_suite!(name, factory, pending)`.
+
+use crate::tx_validation::{TxRequest, ValidatedWallet};
+use bonecoin_core::*;
+use std::collections::*;
+
+pub trait PendingWalletApi: WalletApi {
+    /// Like `create_automatic_transaction`, but only coins not reserved by a pending transaction
+    /// are selected, and the new transaction becomes pending.
+    fn create_pending_automatic_transaction(
+        &mut self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    ) -> Result<Transaction, WalletError>;
+
+    /// Like `create_manual_transaction`, but fails if any input is reserved by a pending
+    /// transaction, and the new transaction becomes pending.
+    fn create_pending_manual_transaction(
+        &mut self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError>;
+
+    /// Transactions created and not yet mined or dropped, oldest first.
+    fn pending_transactions(&self) -> Vec<Transaction>;
+
+    /// Value of the owned coins of the best chain, the same as `net_worth`.
+    fn confirmed_balance(&self) -> u64;
+
+    /// What the confirmed balance will be once every pending transaction is mined: reserved
+    /// coins are left out and owned outputs of pending transactions are counted.
+    fn pending_balance(&self) -> u64;
+}
+
+impl<W: PendingWalletApi> ValidatedWallet<W> {
+    /// Panic if `tx` shares an input with another pending transaction.
+    fn assert_reserved(&self, tx: &Transaction) {
+        let inputs: HashSet<CoinId> = tx.inputs.iter().map(|input| input.coin_id).collect();
+        for other in self.wallet().pending_transactions() {
+            if other == *tx {
+                continue;
+            }
+            let shared: Vec<&CoinId> = other
+                .inputs
+                .iter()
+                .map(|input| &input.coin_id)
+                .filter(|coin_id| inputs.contains(coin_id))
+                .collect();
+            assert!(
+                shared.is_empty(),
+                "{:?} spends {:?}, already reserved by pending {:?}",
+                tx,
+                shared,
+                other
+            );
+        }
+    }
+}
+
+impl<W: PendingWalletApi> PendingWalletApi for ValidatedWallet<W> {
+    fn create_pending_automatic_transaction(
+        &mut self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    ) -> Result<Transaction, WalletError> {
+        let result =
+            self.wallet_mut()
+                .create_pending_automatic_transaction(recipient, output_value, burn);
+        let request = TxRequest::Automatic {
+            recipient,
+            output_value,
+            burn,
+        };
+        let result = self.validated(request, result);
+        if let Ok(tx) = &result {
+            self.assert_reserved(tx);
+        }
+        result
+    }
+
+    fn create_pending_manual_transaction(
+        &mut self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError> {
+        let result = self
+            .wallet_mut()
+            .create_pending_manual_transaction(input_coin_ids.clone(), output_coins.clone());
+        let request = TxRequest::Manual {
+            inputs: &input_coin_ids,
+            outputs: &output_coins,
+        };
+        let result = self.validated(request, result);
+        if let Ok(tx) = &result {
+            self.assert_reserved(tx);
+        }
+        result
+    }
+
+    fn pending_transactions(&self) -> Vec<Transaction> {
+        self.wallet().pending_transactions()
+    }
+
+    fn confirmed_balance(&self) -> u64 {
+        self.wallet().confirmed_balance()
+    }
+
+    fn pending_balance(&self) -> u64 {
+        self.wallet().pending_balance()
+    }
+}
@@ -0,0 +1,218 @@
+//! Pending transactions reserve their coins until they are mined or can no longer be mined
+//!
+//! Only for wallets implementing [`PendingWalletApi`].
+
+use crate::harness::WalletFactory;
+use crate::pending::PendingWalletApi;
+use crate::strict_node::StrictNode;
+use bonecoin_core::*;
+use std::collections::*;
+
+/// Add a block on top of `parent` minting one coin of each value to Alice.
+fn mint_to_alice(node: &mut StrictNode, parent: BlockId, values: &[u64]) -> BlockId {
+    let mint = Transaction {
+        inputs: vec![],
+        outputs: values
+            .iter()
+            .map(|value| Coin {
+                value: *value,
+                owner: Address::Alice,
+            })
+            .collect(),
+    };
+    node.add_block_as_best(parent, vec![mint]).unwrap()
+}
+
+fn inputs_of(tx: &Transaction) -> HashSet<CoinId> {
+    tx.inputs.iter().map(|input| input.coin_id).collect()
+}
+
+pub fn consecutive_transactions_do_not_share_inputs<F: WalletFactory>()
+where
+    F::Wallet: PendingWalletApi,
+{
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    let b1 = mint_to_alice(&mut node, Block::genesis().id(), &[20, 12, 53, 40]);
+    wallet.sync(&node);
+
+    let first = wallet
+        .create_pending_automatic_transaction(Address::Bob, 30, 0)
+        .unwrap();
+    let second = wallet
+        .create_pending_automatic_transaction(Address::Bob, 30, 0)
+        .unwrap();
+    assert!(inputs_of(&first).is_disjoint(&inputs_of(&second)));
+    assert_eq!(
+        wallet.pending_transactions(),
+        vec![first.clone(), second.clone()]
+    );
+
+    // Both fit in the same block.
+    node.add_block_as_best(b1, vec![first, second]).unwrap();
+    wallet.sync(&node);
+    assert!(wallet.pending_transactions().is_empty());
+    assert_eq!(wallet.confirmed_balance(), 125 - 60);
+}
+
+pub fn balances_before_and_after_mining<F: WalletFactory>()
+where
+    F::Wallet: PendingWalletApi,
+{
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    let b1 = mint_to_alice(&mut node, Block::genesis().id(), &[100]);
+    wallet.sync(&node);
+
+    let tx = wallet
+        .create_pending_automatic_transaction(Address::Charlie, 30, 5)
+        .unwrap();
+    assert_eq!(wallet.confirmed_balance(), 100);
+    assert_eq!(wallet.net_worth(), 100);
+    assert_eq!(wallet.pending_balance(), 65);
+
+    // Syncing without the transaction on chain keeps it pending.
+    wallet.sync(&node);
+    assert_eq!(wallet.pending_transactions(), vec![tx.clone()]);
+    assert_eq!(wallet.pending_balance(), 65);
+
+    node.add_block_as_best(b1, vec![tx]).unwrap();
+    wallet.sync(&node);
+    assert!(wallet.pending_transactions().is_empty());
+    assert_eq!(wallet.confirmed_balance(), 65);
+    assert_eq!(wallet.pending_balance(), 65);
+}
+
+pub fn reserved_coins_cannot_be_spent_again<F: WalletFactory>()
+where
+    F::Wallet: PendingWalletApi,
+{
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    mint_to_alice(&mut node, Block::genesis().id(), &[100]);
+    wallet.sync(&node);
+    let coin = wallet.all_coins_of(Address::Alice).unwrap();
+    let coin = coin.iter().next().unwrap().0;
+
+    let tx = wallet
+        .create_pending_manual_transaction(
+            vec![coin],
+            vec![Coin {
+                value: 90,
+                owner: Address::Bob,
+            }],
+        )
+        .unwrap();
+    assert_eq!(inputs_of(&tx), HashSet::from([coin]));
+
+    let again = wallet.create_pending_manual_transaction(
+        vec![coin],
+        vec![Coin {
+            value: 80,
+            owner: Address::Charlie,
+        }],
+    );
+    assert!(again.is_err());
+    assert_eq!(
+        wallet.create_pending_automatic_transaction(Address::Charlie, 10, 0),
+        Err(WalletError::InsufficientFunds)
+    );
+    assert_eq!(wallet.pending_transactions(), vec![tx]);
+    assert_eq!(wallet.pending_balance(), 0);
+}
+
+pub fn conflicting_spend_drops_the_pending_transaction<F: WalletFactory>()
+where
+    F::Wallet: PendingWalletApi,
+{
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    let b1 = mint_to_alice(&mut node, Block::genesis().id(), &[50, 50]);
+    wallet.sync(&node);
+
+    // Needs both coins.
+    let tx = wallet
+        .create_pending_automatic_transaction(Address::Bob, 80, 0)
+        .unwrap();
+    assert_eq!(tx.inputs.len(), 2);
+
+    // Alice spends one of them from somewhere else.
+    let spent = tx.inputs[0].coin_id;
+    let elsewhere = Transaction {
+        inputs: vec![Input {
+            coin_id: spent,
+            signature: Signature::Valid(Address::Alice),
+        }],
+        outputs: vec![Coin {
+            value: 50,
+            owner: Address::Dave,
+        }],
+    };
+    node.add_block_as_best(b1, vec![elsewhere]).unwrap();
+    wallet.sync(&node);
+
+    // The pending transaction can never be mined, so the other coin is free again.
+    assert!(wallet.pending_transactions().is_empty());
+    assert_eq!(wallet.confirmed_balance(), 50);
+    assert_eq!(wallet.pending_balance(), 50);
+    let retry = wallet
+        .create_pending_automatic_transaction(Address::Bob, 40, 0)
+        .unwrap();
+    assert_eq!(inputs_of(&retry), HashSet::from([tx.inputs[1].coin_id]));
+}
+
+pub fn pending_inputs_reorged_away_are_dropped<F: WalletFactory>()
+where
+    F::Wallet: PendingWalletApi,
+{
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    let b1 = mint_to_alice(&mut node, Block::genesis().id(), &[10]);
+    mint_to_alice(&mut node, b1, &[100]);
+    wallet.sync(&node);
+
+    let tx = wallet
+        .create_pending_automatic_transaction(Address::Bob, 90, 0)
+        .unwrap();
+    assert_eq!(wallet.pending_transactions(), vec![tx]);
+
+    // A longer fork from block 1 where the coin of 100 never existed.
+    let b2 = node.add_block_as_best(b1, vec![]).unwrap();
+    node.add_block_as_best(b2, vec![]).unwrap();
+    wallet.sync(&node);
+
+    assert!(wallet.pending_transactions().is_empty());
+    assert_eq!(wallet.confirmed_balance(), 10);
+    assert_eq!(wallet.pending_balance(), 10);
+}
+
+pub fn mined_then_reorged_out_leaves_coins_spendable<F: WalletFactory>()
+where
+    F::Wallet: PendingWalletApi,
+{
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    let b1 = mint_to_alice(&mut node, Block::genesis().id(), &[100]);
+    wallet.sync(&node);
+
+    let tx = wallet
+        .create_pending_automatic_transaction(Address::Bob, 100, 0)
+        .unwrap();
+    node.add_block_as_best(b1, vec![tx.clone()]).unwrap();
+    wallet.sync(&node);
+    assert!(wallet.pending_transactions().is_empty());
+    assert_eq!(wallet.confirmed_balance(), 0);
+
+    // A longer fork from block 1 without the transaction gives the coin back, not pending.
+    let b2 = node.add_block_as_best(b1, vec![]).unwrap();
+    node.add_block_as_best(b2, vec![]).unwrap();
+    wallet.sync(&node);
+    assert!(wallet.pending_transactions().is_empty());
+    assert_eq!(wallet.confirmed_balance(), 100);
+    assert_eq!(wallet.pending_balance(), 100);
+
+    let again = wallet
+        .create_pending_automatic_transaction(Address::Bob, 100, 0)
+        .unwrap();
+    assert_eq!(inputs_of(&again), inputs_of(&tx));
+}
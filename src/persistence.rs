@@ -0,0 +1,45 @@
+//! Optional wallet extension: saving the whole wallet state and loading it back.
+//!
+//! A wallet implementing [`PersistentWalletApi`] writes everything it knows to a byte stream:
+//! owned addresses, best block, UTXO set and whatever it keeps to undo blocks on a reorg. Loaded
+//! back, it must behave exactly like a wallet that was never saved, without resyncing from
+//! genesis.
+//!
+//! Enable the tests with `wallet_test_suite!(name, factory, persistence)`.
+
+use crate::random_scenario::RECIPIENTS;
+use crate::tx_validation::ValidatedWallet;
+use bonecoin_core::*;
+use std::io::{self, Read, Write};
+
+pub trait PersistentWalletApi: WalletApi {
+    /// Write the whole state of the wallet to `writer`.
+    fn save(&self, writer: &mut dyn Write) -> io::Result<()>;
+
+    /// Replace the whole state of the wallet, owned addresses included, with one written by
+    /// [`save`](Self::save). Data that is not a complete saved state is an error.
+    fn restore(&mut self, reader: &mut dyn Read) -> io::Result<()>;
+}
+
+/// A restored wallet owns the addresses it saved, so the addresses its transactions are validated
+/// against are read back from it afterwards: those it does not answer `ForeignAddress` for,
+/// among the ones of the random scenarios and the ones it owned before.
+impl<W: PersistentWalletApi> PersistentWalletApi for ValidatedWallet<W> {
+    fn save(&self, writer: &mut dyn Write) -> io::Result<()> {
+        self.wallet().save(writer)
+    }
+
+    fn restore(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.wallet_mut().restore(reader)?;
+        let mut owned = Vec::new();
+        for address in RECIPIENTS.iter().chain(self.owned()) {
+            let foreign =
+                self.wallet().total_assets_of(*address) == Err(WalletError::ForeignAddress);
+            if !foreign && !owned.contains(address) {
+                owned.push(*address);
+            }
+        }
+        self.set_owned(owned);
+        Ok(())
+    }
+}
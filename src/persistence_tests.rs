@@ -0,0 +1,159 @@
+//! Saved and restored wallets behave like wallets that were never saved
+//!
+//! Only for wallets implementing [`PersistentWalletApi`].
+
+use crate::differential::WalletState;
+use crate::harness::WalletFactory;
+use crate::persistence::PersistentWalletApi;
+use crate::random_scenario::{for_each_seed, RandomConfig, Scenario, OWNED, RECIPIENTS};
+use crate::recording_node::RecordingNode;
+use bonecoin_core::*;
+use std::io;
+
+fn save(wallet: &impl PersistentWalletApi) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    wallet
+        .save(&mut bytes)
+        .expect("saving to memory cannot fail");
+    bytes
+}
+
+/// A fresh wallet from `F` owning `addresses`, loaded with `bytes`.
+fn restore<F: WalletFactory>(addresses: &[Address], bytes: &[u8]) -> F::Wallet
+where
+    F::Wallet: PersistentWalletApi,
+{
+    let mut wallet = F::new_wallet(addresses.iter().copied());
+    wallet
+        .restore(&mut &bytes[..])
+        .expect("restoring a saved wallet");
+    wallet
+}
+
+/// A chain of `blocks` blocks, each paying Alice and Charlie, and a wallet synced on it.
+fn synced_wallet<F: WalletFactory>(blocks: u64) -> (RecordingNode, F::Wallet) {
+    let mut node = RecordingNode::new();
+    let mut parent = Block::genesis().id();
+    for height in 1..=blocks {
+        let mint = Transaction {
+            inputs: vec![],
+            outputs: vec![
+                Coin {
+                    value: height,
+                    owner: Address::Alice,
+                },
+                Coin {
+                    value: 1_000 + height,
+                    owner: Address::Charlie,
+                },
+            ],
+        };
+        parent = node.add_block_as_best(parent, vec![mint]);
+    }
+    let mut wallet = F::new_wallet(OWNED.into_iter());
+    wallet.sync(&node);
+    (node, wallet)
+}
+
+pub fn restored_wallet_follows_random_reorgs_like_its_twin<F: WalletFactory>()
+where
+    F::Wallet: PersistentWalletApi,
+{
+    let config = RandomConfig::reorg_heavy();
+    for_each_seed(20, |seed| {
+        let scenario = Scenario::generate(seed, &config);
+        let mut twin = F::new_wallet(OWNED.into_iter());
+        let mut wallet = F::new_wallet(OWNED.into_iter());
+
+        scenario.replay(&mut RecordingNode::new(), |node| {
+            // Round trip the wallet before every sync, so reorgs start from a restored state.
+            // Both must take the same path, without falling back to genesis.
+            wallet = restore::<F>(&OWNED, &save(&wallet));
+            assert_eq!(
+                WalletState::of(&wallet, &RECIPIENTS),
+                WalletState::of(&twin, &RECIPIENTS),
+                "seed {}: restored wallet differs before syncing",
+                seed
+            );
+
+            let queries = node.how_many_queries();
+            twin.sync(node);
+            let twin_queries = node.how_many_queries() - queries;
+            wallet.sync(node);
+            let wallet_queries = node.how_many_queries() - queries - twin_queries;
+
+            assert_eq!(
+                WalletState::of(&wallet, &RECIPIENTS),
+                WalletState::of(&twin, &RECIPIENTS),
+                "seed {}: restored wallet differs after syncing",
+                seed
+            );
+            assert_eq!(
+                wallet_queries, twin_queries,
+                "seed {}: restored wallet spent other queries than its twin",
+                seed
+            );
+        });
+    });
+}
+
+pub fn restored_wallet_owns_the_saved_addresses<F: WalletFactory>()
+where
+    F::Wallet: PersistentWalletApi,
+{
+    let (node, wallet) = synced_wallet::<F>(5);
+    let bytes = save(&wallet);
+
+    // Loaded into a wallet owning nothing, and into one owning someone else.
+    for addresses in [vec![], vec![Address::Charlie]] {
+        let restored = restore::<F>(&addresses, &bytes);
+        assert_eq!(restored.best_hash(), node.best_block_at_height(5).unwrap());
+        assert_eq!(restored.total_assets_of(Address::Alice), Ok(15));
+        assert_eq!(restored.total_assets_of(Address::Bob), Ok(0));
+        assert_eq!(
+            restored.total_assets_of(Address::Charlie),
+            Err(WalletError::ForeignAddress)
+        );
+        assert_eq!(
+            WalletState::of(&restored, &RECIPIENTS),
+            WalletState::of(&wallet, &RECIPIENTS)
+        );
+    }
+}
+
+pub fn restored_wallet_keeps_spending<F: WalletFactory>()
+where
+    F::Wallet: PersistentWalletApi,
+{
+    let (mut node, wallet) = synced_wallet::<F>(5);
+    let mut restored = restore::<F>(&OWNED, &save(&wallet));
+
+    let tx = restored
+        .create_automatic_transaction(Address::Dave, 14, 1)
+        .unwrap();
+    let tip = node.best_block_at_height(5).unwrap();
+    node.add_block_as_best(tip, vec![tx]);
+    restored.sync(&node);
+    assert_eq!(restored.net_worth(), 0);
+    assert_eq!(restored.best_height(), 6);
+}
+
+pub fn incomplete_data_is_rejected<F: WalletFactory>()
+where
+    F::Wallet: PersistentWalletApi,
+{
+    let (_, wallet) = synced_wallet::<F>(20);
+    let bytes = save(&wallet);
+
+    let mut empty = F::new_wallet(OWNED.into_iter());
+    assert!(empty.restore(&mut io::empty()).is_err());
+    for len in [1, bytes.len() / 2, bytes.len() - 1] {
+        let mut truncated = F::new_wallet(OWNED.into_iter());
+        assert!(
+            truncated.restore(&mut &bytes[..len]).is_err(),
+            "restored from {} of {} bytes",
+            len,
+            bytes.len()
+        );
+    }
+}
@@ -0,0 +1,470 @@
+//! Seeded random block trees with mints, spends, reorgs and syncs.
+//!
+//! A [`Scenario`] is a symbolic list of [`Step`]s: blocks refer to their parent by position and
+//! inputs refer to the coin they spend by block, transaction and output position. Coin ids are
+//! only computed when the scenario is run, from the height each block actually ends up at. This
+//! keeps every scenario valid however it is edited.
+//!
+//! Wallet state is checked against the [`OracleWallet`](crate::oracle::OracleWallet) after every
+//! sync. Every run prints nothing unless it fails, in which case the seed is printed and
+//! `BONECOIN_SEED=<seed> cargo t <test name>` replays exactly that scenario. The failing scenario
+//! is also [shrunk](crate::shrink) and printed as a test.
+
+use crate::differential::DifferentialWallet;
+use crate::harness::WalletFactory;
+use crate::recording_node::RecordingNode;
+use bonecoin_core::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable that pins the seed of randomized tests.
+pub const SEED_VAR: &str = "BONECOIN_SEED";
+
+/// Small, dependency free SplitMix64 generator. Same seed, same scenario, on every platform.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// True with probability `percent` / 100.
+    pub fn percent(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+/// A coin created by output `output` of transaction `tx` in block `block` of a scenario.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CoinRef {
+    pub block: usize,
+    pub tx: usize,
+    pub output: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputSpec {
+    /// `Input::dummy()`, as used by most hand-written mints.
+    Dummy,
+    /// Spend a coin created earlier in the scenario.
+    Coin(CoinRef),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TxSpec {
+    pub inputs: Vec<InputSpec>,
+    pub outputs: Vec<Coin>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Add the next block. Blocks are numbered by the order of these steps, `None` is genesis.
+    AddBlock {
+        parent: Option<usize>,
+        transactions: Vec<TxSpec>,
+        as_best: bool,
+    },
+    SetBest(Option<usize>),
+    Sync,
+}
+
+/// Knobs of the random generator.
+#[derive(Clone, Debug)]
+pub struct RandomConfig {
+    pub blocks: usize,
+    pub max_txs_per_block: u64,
+    /// Chance, in percent, that a new block does not build on the current best block.
+    pub fork_percent: u64,
+    /// Chance, in percent, of switching the best block to a random one after a block.
+    pub set_best_percent: u64,
+    /// Chance, in percent, of syncing the wallet after a block.
+    pub sync_percent: u64,
+}
+
+impl Default for RandomConfig {
+    fn default() -> Self {
+        RandomConfig {
+            blocks: 40,
+            max_txs_per_block: 3,
+            fork_percent: 25,
+            set_best_percent: 10,
+            sync_percent: 30,
+        }
+    }
+}
+
+impl RandomConfig {
+    /// More forks and best block switches than the default, for the extensions that have to
+    /// follow reorgs.
+    pub fn reorg_heavy() -> Self {
+        RandomConfig {
+            fork_percent: 40,
+            set_best_percent: 20,
+            ..RandomConfig::default()
+        }
+    }
+}
+
+/// Addresses owned by the wallet in random scenarios.
+pub const OWNED: [Address; 2] = [Address::Alice, Address::Bob];
+
+/// Every address random transactions pay to, owned or not.
+pub const RECIPIENTS: [Address; 6] = [
+    Address::Alice,
+    Address::Bob,
+    Address::Charlie,
+    Address::Custom(0),
+    Address::Custom(1),
+    Address::Custom(2),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+    pub seed: u64,
+    pub addresses: Vec<Address>,
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    /// Generate the scenario for `seed`.
+    pub fn generate(seed: u64, config: &RandomConfig) -> Scenario {
+        let mut rng = Rng::new(seed);
+        let mut steps = Vec::new();
+        let mut parents: Vec<Option<usize>> = Vec::new();
+        let mut best: Option<usize> = None;
+
+        for block in 0..config.blocks {
+            let parent = if block == 0 || !rng.percent(config.fork_percent) {
+                best
+            } else {
+                random_block(&mut rng, block)
+            };
+
+            let mut unspent = unspent_after(&steps, &parents, parent);
+            let mut transactions = Vec::new();
+            for _ in 0..rng.below(config.max_txs_per_block + 1) {
+                let spec = random_tx(&mut rng, &mut unspent);
+                // Two identical mints in one block would create the same coin ids twice.
+                if transactions.contains(&spec) {
+                    continue;
+                }
+                let tx = transactions.len();
+                for (output, coin) in spec.outputs.iter().enumerate() {
+                    unspent.push((CoinRef { block, tx, output }, coin.clone()));
+                }
+                transactions.push(spec);
+            }
+
+            let as_best = rng.percent(70);
+            if as_best {
+                best = Some(block);
+            }
+            parents.push(parent);
+            steps.push(Step::AddBlock {
+                parent,
+                transactions,
+                as_best,
+            });
+
+            if rng.percent(config.set_best_percent) {
+                best = random_block(&mut rng, block + 1);
+                steps.push(Step::SetBest(best));
+            }
+            if rng.percent(config.sync_percent) {
+                steps.push(Step::Sync);
+            }
+        }
+        steps.push(Step::Sync);
+
+        Scenario {
+            seed,
+            addresses: OWNED.to_vec(),
+            steps,
+        }
+    }
+
+    /// Number of blocks the scenario adds.
+    pub fn block_count(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| matches!(step, Step::AddBlock { .. }))
+            .count()
+    }
+
+    /// Play the scenario on a fresh node, comparing `wallet` with the oracle after every sync.
+    pub fn run<W: WalletApi>(&self, wallet: W) {
+        let mut node = RecordingNode::new();
+        let mut wallet = DifferentialWallet::new(wallet, self.addresses.clone(), &node);
+        self.replay(&mut node, |node| wallet.sync(node));
+    }
+
+    /// Add the blocks of the scenario to `node` and switch its best block, calling `on_sync` at
+    /// every [`Step::Sync`]. Returns the blocks as they were added.
+    pub fn replay(
+        &self,
+        node: &mut RecordingNode,
+        mut on_sync: impl FnMut(&RecordingNode),
+    ) -> MaterializedChain {
+        let mut chain = MaterializedChain::default();
+        for step in &self.steps {
+            match step {
+                Step::AddBlock {
+                    parent,
+                    transactions,
+                    as_best,
+                } => {
+                    let (parent_id, transactions) = chain.materialize(*parent, transactions);
+                    let block_id = if *as_best {
+                        node.add_block_as_best(parent_id, transactions.clone())
+                    } else {
+                        node.add_block(parent_id, transactions.clone())
+                    };
+                    chain.push(block_id, *parent, transactions);
+                }
+                Step::SetBest(block) => node.set_best(chain.id(*block)),
+                Step::Sync => on_sync(node),
+            }
+        }
+        chain
+    }
+}
+
+/// The concrete blocks and transactions of a scenario being run.
+#[derive(Default)]
+pub struct MaterializedChain {
+    pub ids: Vec<BlockId>,
+    pub heights: Vec<u64>,
+    pub transactions: Vec<Vec<Transaction>>,
+}
+
+impl MaterializedChain {
+    pub fn id(&self, block: Option<usize>) -> BlockId {
+        block.map_or_else(|| Block::genesis().id(), |block| self.ids[block])
+    }
+
+    pub fn height(&self, block: Option<usize>) -> u64 {
+        block.map_or(0, |block| self.heights[block])
+    }
+
+    /// Turn the specs of the next block into real transactions. Inputs whose coin does not
+    /// exist (anymore) in the scenario are left out.
+    pub fn materialize(
+        &self,
+        parent: Option<usize>,
+        specs: &[TxSpec],
+    ) -> (BlockId, Vec<Transaction>) {
+        let block = self.ids.len();
+        let height = self.height(parent) + 1;
+        let mut transactions: Vec<Transaction> = Vec::new();
+
+        for spec in specs {
+            let mut inputs = Vec::new();
+            for input in &spec.inputs {
+                match input {
+                    InputSpec::Dummy => inputs.push(Input::dummy()),
+                    InputSpec::Coin(coin) => {
+                        let (source, source_height) = if coin.block == block {
+                            (transactions.get(coin.tx), height)
+                        } else {
+                            (
+                                self.transactions
+                                    .get(coin.block)
+                                    .and_then(|txs| txs.get(coin.tx)),
+                                self.heights.get(coin.block).copied().unwrap_or(0),
+                            )
+                        };
+                        let Some(source) = source else { continue };
+                        if let Some(output) = source.outputs.get(coin.output) {
+                            inputs.push(Input {
+                                coin_id: source.coin_id(source_height, coin.output),
+                                signature: Signature::Valid(output.owner),
+                            });
+                        }
+                    }
+                }
+            }
+            transactions.push(Transaction {
+                inputs,
+                outputs: spec.outputs.clone(),
+            });
+        }
+
+        (self.id(parent), transactions)
+    }
+
+    pub fn push(&mut self, id: BlockId, parent: Option<usize>, transactions: Vec<Transaction>) {
+        self.heights.push(self.height(parent) + 1);
+        self.ids.push(id);
+        self.transactions.push(transactions);
+    }
+}
+
+/// Genesis or one of the first `blocks` blocks, uniformly.
+fn random_block(rng: &mut Rng, blocks: usize) -> Option<usize> {
+    let pick = rng.below(blocks as u64 + 1) as usize;
+    pick.checked_sub(1)
+}
+
+/// Coins created and not spent on the way from genesis to `tip`.
+fn unspent_after(
+    steps: &[Step],
+    parents: &[Option<usize>],
+    tip: Option<usize>,
+) -> Vec<(CoinRef, Coin)> {
+    let mut path = Vec::new();
+    let mut current = tip;
+    while let Some(block) = current {
+        path.push(block);
+        current = parents[block];
+    }
+    path.reverse();
+
+    let blocks: Vec<&Vec<TxSpec>> = steps
+        .iter()
+        .filter_map(|step| match step {
+            Step::AddBlock { transactions, .. } => Some(transactions),
+            _ => None,
+        })
+        .collect();
+
+    let mut unspent: Vec<(CoinRef, Coin)> = Vec::new();
+    for block in path {
+        for (tx, spec) in blocks[block].iter().enumerate() {
+            for input in &spec.inputs {
+                if let InputSpec::Coin(spent) = input {
+                    unspent.retain(|(coin, _)| coin != spent);
+                }
+            }
+            for (output, coin) in spec.outputs.iter().enumerate() {
+                unspent.push((CoinRef { block, tx, output }, coin.clone()));
+            }
+        }
+    }
+    unspent
+}
+
+fn random_coin(rng: &mut Rng, value: u64) -> Coin {
+    Coin {
+        value,
+        owner: *rng.pick(&RECIPIENTS),
+    }
+}
+
+/// A mint, or a spend of up to two coins from `unspent`, which are then removed from it.
+fn random_tx(rng: &mut Rng, unspent: &mut Vec<(CoinRef, Coin)>) -> TxSpec {
+    if unspent.is_empty() || rng.percent(40) {
+        let inputs = if rng.percent(50) {
+            vec![InputSpec::Dummy]
+        } else {
+            vec![]
+        };
+        let outputs = (0..1 + rng.below(3))
+            .map(|_| {
+                let value = 1 + rng.below(1000);
+                random_coin(rng, value)
+            })
+            .collect();
+        return TxSpec { inputs, outputs };
+    }
+
+    let mut inputs = Vec::new();
+    let mut total = 0;
+    for _ in 0..1 + rng.below(2) {
+        if unspent.is_empty() {
+            break;
+        }
+        let (coin, spent) = unspent.remove(rng.below(unspent.len() as u64) as usize);
+        total += spent.value;
+        inputs.push(InputSpec::Coin(coin));
+    }
+
+    // Burn a little sometimes, and split what is left in up to two outputs.
+    let burn = if rng.percent(30) { rng.below(total) } else { 0 };
+    let mut left = total - burn;
+    let mut outputs = Vec::new();
+    if left > 1 && rng.percent(50) {
+        let first = 1 + rng.below(left - 1);
+        outputs.push(random_coin(rng, first));
+        left -= first;
+    }
+    if left > 0 {
+        outputs.push(random_coin(rng, left));
+    }
+    TxSpec { inputs, outputs }
+}
+
+/// The seed pinned with [`SEED_VAR`], if any.
+pub fn pinned_seed() -> Option<u64> {
+    std::env::var(SEED_VAR).ok().map(|seed| {
+        seed.parse()
+            .unwrap_or_else(|_| panic!("{} must be an unsigned integer, got {}", SEED_VAR, seed))
+    })
+}
+
+/// Seeds for `runs` random runs, different every time, or only the pinned seed if there is one.
+pub fn seeds(runs: u64) -> Vec<u64> {
+    match pinned_seed() {
+        Some(seed) => vec![seed],
+        None => {
+            let base = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("system clock is before 1970")
+                .as_nanos() as u64;
+            (0..runs).map(|run| base.wrapping_add(run)).collect()
+        }
+    }
+}
+
+/// Run `check` with each of [`seeds`]`(runs)`. The seed of a failing run is printed before the
+/// failure is propagated.
+pub fn for_each_seed(runs: u64, mut check: impl FnMut(u64)) {
+    for seed in seeds(runs) {
+        if let Err(failure) = panic::catch_unwind(AssertUnwindSafe(|| check(seed))) {
+            eprintln!("random run failed, reproduce with {}={}", SEED_VAR, seed);
+            panic::resume_unwind(failure);
+        }
+    }
+}
+
+/// Run `runs` random scenarios against wallets from `F`, or only the pinned seed if there is
+/// one. The seed of a failing scenario is printed, along with a shrunk version of it as a
+/// suite test, before the failure is propagated.
+pub fn check_random_scenarios<F: WalletFactory>(config: &RandomConfig, runs: u64) {
+    for seed in seeds(runs) {
+        let scenario = Scenario::generate(seed, config);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            scenario.run(F::new_wallet(scenario.addresses.iter().copied()))
+        }));
+        if let Err(failure) = result {
+            eprintln!(
+                "random scenario failed, reproduce with {}={}",
+                SEED_VAR, seed
+            );
+            let shrunk = crate::shrink::shrink::<F>(&scenario);
+            eprintln!(
+                "minimal failing scenario ({} blocks instead of {}):\n\n{}",
+                shrunk.block_count(),
+                scenario.block_count(),
+                shrunk.to_rust_test()
+            );
+            panic::resume_unwind(failure);
+        }
+    }
+}
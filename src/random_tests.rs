@@ -0,0 +1,35 @@
+//! Randomized reorg scenarios checked against the oracle wallet
+//!
+//! Set `BONECOIN_SEED` to replay the seed printed by a failing run.
+
+use crate::harness::WalletFactory;
+use crate::random_scenario::{check_random_scenarios, RandomConfig};
+
+pub fn random_short_chains_with_many_reorgs<F: WalletFactory>() {
+    let config = RandomConfig {
+        blocks: 15,
+        fork_percent: 50,
+        set_best_percent: 25,
+        sync_percent: 50,
+        ..RandomConfig::default()
+    };
+    check_random_scenarios::<F>(&config, 50);
+}
+
+pub fn random_long_chains<F: WalletFactory>() {
+    let config = RandomConfig {
+        blocks: 150,
+        ..RandomConfig::default()
+    };
+    check_random_scenarios::<F>(&config, 10);
+}
+
+pub fn random_busy_blocks_synced_rarely<F: WalletFactory>() {
+    let config = RandomConfig {
+        blocks: 60,
+        max_txs_per_block: 8,
+        sync_percent: 5,
+        ..RandomConfig::default()
+    };
+    check_random_scenarios::<F>(&config, 10);
+}
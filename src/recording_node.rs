@@ -0,0 +1,147 @@
+//! A `MockNode` that remembers every block put into it.
+//!
+//! The wallet under test still talks to the plain `MockNode` (the wrapper derefs to it), but the
+//! suite keeps its own copy of the block tree so reference implementations and checkers can
+//! replay it without spending any node queries.
+
+use bonecoin_core::*;
+use std::cell::RefCell;
+use std::collections::*;
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// Everything the suite knows about a block it added to the node.
+#[derive(Clone, Debug)]
+pub struct BlockRecord {
+    pub parent: BlockId,
+    pub height: u64,
+    pub transactions: Vec<Transaction>,
+}
+
+/// The block tree as seen by the suite, shared between a [`RecordingNode`] and its readers.
+#[derive(Debug)]
+pub struct ChainRecord {
+    pub blocks: HashMap<BlockId, BlockRecord>,
+    pub best: BlockId,
+}
+
+impl ChainRecord {
+    fn new() -> Self {
+        ChainRecord {
+            blocks: HashMap::new(),
+            best: Block::genesis().id(),
+        }
+    }
+
+    /// Height of a recorded block. Genesis is height 0.
+    pub fn height_of(&self, block_id: BlockId) -> u64 {
+        if block_id == Block::genesis().id() {
+            return 0;
+        }
+        self.blocks
+            .get(&block_id)
+            .expect("block was not added through the RecordingNode")
+            .height
+    }
+
+    /// Ids of the best chain from height 1 up to the best block.
+    pub fn best_chain(&self) -> Vec<BlockId> {
+        self.chain_to(self.best)
+    }
+
+    /// Ids of the chain from height 1 up to `tip`.
+    pub fn chain_to(&self, tip: BlockId) -> Vec<BlockId> {
+        let mut chain = Vec::new();
+        let mut current = tip;
+        while current != Block::genesis().id() {
+            chain.push(current);
+            current = self.blocks[&current].parent;
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Every coin created anywhere in the tree, on the best chain or not.
+    pub fn all_created_coins(&self) -> Vec<(CoinId, Coin)> {
+        let mut coins = Vec::new();
+        for record in self.blocks.values() {
+            for tx in &record.transactions {
+                for (index, coin) in tx.outputs.iter().enumerate() {
+                    coins.push((tx.coin_id(record.height, index), coin.clone()));
+                }
+            }
+        }
+        coins
+    }
+}
+
+/// Drop-in replacement for `MockNode` in tests that want the block tree recorded.
+///
+/// Pass `&node` to `wallet.sync` exactly as with a `MockNode`.
+pub struct RecordingNode {
+    node: MockNode,
+    record: Rc<RefCell<ChainRecord>>,
+}
+
+impl RecordingNode {
+    pub fn new() -> Self {
+        RecordingNode {
+            node: MockNode::new(),
+            record: Rc::new(RefCell::new(ChainRecord::new())),
+        }
+    }
+
+    pub fn add_block(&mut self, parent: BlockId, transactions: Vec<Transaction>) -> BlockId {
+        let block_id = self.node.add_block(parent, transactions.clone());
+        self.remember(block_id, parent, transactions);
+        block_id
+    }
+
+    pub fn add_block_as_best(
+        &mut self,
+        parent: BlockId,
+        transactions: Vec<Transaction>,
+    ) -> BlockId {
+        let block_id = self.node.add_block_as_best(parent, transactions.clone());
+        self.remember(block_id, parent, transactions);
+        self.record.borrow_mut().best = block_id;
+        block_id
+    }
+
+    pub fn set_best(&mut self, block_id: BlockId) {
+        self.node.set_best(block_id);
+        self.record.borrow_mut().best = block_id;
+    }
+
+    /// A handle on the recorded tree that stays up to date as blocks are added.
+    pub fn record(&self) -> Rc<RefCell<ChainRecord>> {
+        self.record.clone()
+    }
+
+    fn remember(&mut self, block_id: BlockId, parent: BlockId, transactions: Vec<Transaction>) {
+        let mut record = self.record.borrow_mut();
+        let height = record.height_of(parent) + 1;
+        record.blocks.insert(
+            block_id,
+            BlockRecord {
+                parent,
+                height,
+                transactions,
+            },
+        );
+    }
+}
+
+impl Default for RecordingNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for RecordingNode {
+    type Target = MockNode;
+
+    fn deref(&self) -> &MockNode {
+        &self.node
+    }
+}
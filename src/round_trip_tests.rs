@@ -0,0 +1,236 @@
+//! Several wallets paying each other for hundreds of rounds of create, mine and sync
+//!
+//! Every transaction is mined into a strict node, so it has to be valid, and after every round
+//! the value held by all wallets together must match an independent ledger of what was minted,
+//! burned and paid out of the wallets. Every assertion names the seed and round it failed
+//! at; set `BONECOIN_SEED` to replay that seed.
+
+use crate::harness::WalletFactory;
+use crate::random_scenario::{for_each_seed, Rng};
+use crate::strict_node::StrictNode;
+use bonecoin_core::*;
+use std::collections::*;
+
+/// Receives payments but belongs to no wallet.
+const OUTSIDER: Address = Address::Custom(7);
+
+struct Participant<W> {
+    wallet: W,
+    addresses: Vec<Address>,
+}
+
+fn participants<F: WalletFactory>() -> Vec<Participant<F::Wallet>> {
+    [
+        vec![Address::Alice, Address::Bob],
+        vec![Address::Charlie],
+        vec![Address::Dave, Address::Eve],
+    ]
+    .into_iter()
+    .map(|addresses| Participant {
+        wallet: F::new_wallet(addresses.clone().into_iter()),
+        addresses,
+    })
+    .collect()
+}
+
+/// A payment from `sender` to any address, or `None` if it has nothing to spend. Returns the
+/// transaction and how much value it takes out of the wallets: the burn, plus the payment if it
+/// goes to the outsider. `context` names the seed and round in failures.
+fn random_payment<W: WalletApi>(
+    rng: &mut Rng,
+    sender: &Participant<W>,
+    recipients: &[Address],
+    context: &str,
+) -> Option<(Transaction, u64)> {
+    let balance = sender.wallet.net_worth();
+    if balance == 0 {
+        return None;
+    }
+    let recipient = *rng.pick(recipients);
+
+    let (tx, value, burn) = if rng.percent(60) {
+        let value = 1 + rng.below(balance);
+        let burn = rng.below(3).min(balance - value);
+        let tx = sender
+            .wallet
+            .create_automatic_transaction(recipient, value, burn)
+            .unwrap_or_else(|e| panic!("{}: automatic transaction failed: {:?}", context, e));
+        (tx, value, burn)
+    } else {
+        let mut coins: Vec<(CoinId, u64)> = sender
+            .addresses
+            .iter()
+            .flat_map(|address| {
+                sender
+                    .wallet
+                    .all_coins_of(*address)
+                    .unwrap_or_else(|e| panic!("{}: coins of {:?}: {:?}", context, address, e))
+            })
+            .collect();
+        // Equal values are ordered by id, so a seed picks the same coins on every run.
+        coins.sort_by_cached_key(|(coin_id, value)| (*value, format!("{:?}", coin_id)));
+        let mut spent: Vec<(CoinId, u64)> = Vec::new();
+        for coin in coins {
+            if spent.is_empty() || rng.percent(50) {
+                spent.push(coin);
+            }
+        }
+
+        let total: u64 = spent.iter().map(|(_, value)| value).sum();
+        let value = 1 + rng.below(total);
+        let burn = rng.below(3).min(total - value);
+        let mut outputs = vec![Coin {
+            value,
+            owner: recipient,
+        }];
+        if total - value - burn > 0 {
+            outputs.push(Coin {
+                value: total - value - burn,
+                owner: sender.addresses[0],
+            });
+        }
+        let tx = sender
+            .wallet
+            .create_manual_transaction(spent.iter().map(|(coin_id, _)| *coin_id).collect(), outputs)
+            .unwrap_or_else(|e| panic!("{}: manual transaction failed: {:?}", context, e));
+        (tx, value, burn)
+    };
+
+    let paid_out = if recipient == OUTSIDER { value } else { 0 };
+    Some((tx, burn + paid_out))
+}
+
+/// Check every wallet against the node, and all of them together against `ledger`, which holds
+/// how much value each block brought into the wallets. `context` names the seed and round in
+/// failures.
+fn check_conservation<W: WalletApi>(
+    node: &StrictNode,
+    participants: &[Participant<W>],
+    ledger: &HashMap<BlockId, i128>,
+    context: &str,
+) {
+    let utxos = node.utxos();
+    for participant in participants {
+        let held: u64 = utxos
+            .values()
+            .filter(|coin| participant.addresses.contains(&coin.owner))
+            .map(|coin| coin.value)
+            .sum();
+        assert_eq!(
+            participant.wallet.net_worth(),
+            held,
+            "{}: net worth of the wallet owning {:?}",
+            context,
+            participant.addresses
+        );
+    }
+
+    let expected: i128 = node
+        .record()
+        .borrow()
+        .best_chain()
+        .iter()
+        .map(|block_id| ledger[block_id])
+        .sum();
+    let total: i128 = participants
+        .iter()
+        .map(|participant| participant.wallet.net_worth() as i128)
+        .sum();
+    assert_eq!(
+        total, expected,
+        "{}: value held by all wallets together",
+        context
+    );
+}
+
+fn run_rounds<F: WalletFactory>(seed: u64, rounds: u64, reorg_percent: u64) {
+    let mut rng = Rng::new(seed);
+    let mut node = StrictNode::new();
+    let mut participants = participants::<F>();
+    let mut recipients: Vec<Address> = participants
+        .iter()
+        .flat_map(|participant| participant.addresses.clone())
+        .collect();
+    recipients.push(OUTSIDER);
+
+    let mut ledger = HashMap::new();
+    let funding = Transaction {
+        inputs: vec![],
+        outputs: recipients[..recipients.len() - 1]
+            .iter()
+            .map(|address| Coin {
+                value: 1_000,
+                owner: *address,
+            })
+            .collect(),
+    };
+    let funded = node
+        .add_block_as_best(Block::genesis().id(), vec![funding])
+        .unwrap();
+    ledger.insert(funded, 1_000 * (recipients.len() as i128 - 1));
+
+    for round in 0..rounds {
+        let context = format!("seed {}, round {}", seed, round);
+        let chain = node.record().borrow().best_chain();
+        if chain.len() > 2 && rng.percent(reorg_percent) {
+            // Fork off between the funding block and the tip, one block longer than what it drops.
+            let depth = 1 + rng.below(3.min(chain.len() as u64 - 1));
+            let mut parent = chain[chain.len() - 1 - depth as usize];
+            let marker = Transaction {
+                inputs: vec![],
+                outputs: vec![Coin {
+                    value: round + 1,
+                    owner: OUTSIDER,
+                }],
+            };
+            for i in 0..=depth {
+                let transactions = if i == 0 { vec![marker.clone()] } else { vec![] };
+                parent = node.add_block_as_best(parent, transactions).unwrap();
+                ledger.insert(parent, 0);
+            }
+        } else {
+            let mut transactions = Vec::new();
+            let mut delta: i128 = 0;
+            for participant in &participants {
+                if !rng.percent(60) {
+                    continue;
+                }
+                if let Some((tx, taken_out)) =
+                    random_payment(&mut rng, participant, &recipients, &context)
+                {
+                    transactions.push(tx);
+                    delta -= taken_out as i128;
+                }
+            }
+            if rng.percent(10) {
+                transactions.push(Transaction {
+                    inputs: vec![],
+                    outputs: vec![Coin {
+                        value: 100 + round,
+                        owner: *rng.pick(&recipients[..recipients.len() - 1]),
+                    }],
+                });
+                delta += 100 + round as i128;
+            }
+
+            let best = node.record().borrow().best;
+            let block = node
+                .add_block_as_best(best, transactions)
+                .unwrap_or_else(|invalid| panic!("{}: block refused: {}", context, invalid));
+            ledger.insert(block, delta);
+        }
+
+        for participant in &mut participants {
+            participant.wallet.sync(&node);
+        }
+        check_conservation(&node, &participants, &ledger, &context);
+    }
+}
+
+pub fn payments_between_three_wallets<F: WalletFactory>() {
+    for_each_seed(3, |seed| run_rounds::<F>(seed, 300, 0));
+}
+
+pub fn payments_between_three_wallets_with_reorgs<F: WalletFactory>() {
+    for_each_seed(3, |seed| run_rounds::<F>(seed, 300, 5));
+}
@@ -0,0 +1,486 @@
+//! Wallet scenarios written as TOML files instead of Rust.
+//!
+//! Every `*.toml` file under [`SCENARIO_DIR`] is one scenario. Transactions are declared once
+//! by name, blocks list the transactions they contain, and coins are referred to as
+//! `"<transaction>:<output>"`. A reference always means the coin as created by the last block its
+//! transaction was put in, so a transaction mined again on another branch keeps its name.
+//!
+//! ```toml
+//! addresses = ["Alice", "Bob"]
+//!
+//! [transactions.mint]
+//! inputs = ["dummy"]
+//! outputs = [{ value = 100, owner = "Alice" }]
+//!
+//! [transactions.pay_bob]
+//! inputs = ["mint:0"]
+//! outputs = [{ value = 60, owner = "Bob" }, { value = 40, owner = "Alice" }]
+//!
+//! [[steps]]
+//! action = "block"
+//! name = "b1"
+//! parent = "G"
+//! transactions = ["mint", "pay_bob"]
+//! best = true
+//!
+//! [[steps]]
+//! action = "sync"
+//!
+//! [[steps]]
+//! action = "expect"
+//! best_height = 1
+//! best_hash = "b1"
+//! net_worth = 100
+//! total_assets_of = { Alice = 40, Bob = 60, Charlie = "ForeignAddress" }
+//! all_coins_of = { Bob = ["pay_bob:0"] }
+//! coin_details = { "mint:0" = "UnknownCoin", "pay_bob:1" = { value = 40, owner = "Alice" } }
+//! manual_transactions = [{ inputs = ["pay_bob:1"], outputs = [{ value = 50, owner = "Bob" }], error = "InsufficientFunds" }]
+//! automatic_transactions = [{ recipient = "Charlie", value = 0, error = "ZeroCoinValue" }]
+//! ```
+//!
+//! The actions are `block` (`name`, `parent`, optional `transactions` and `best`), `set_best`
+//! (`block`), `sync` and `expect`. Every field of `expect` is optional. Expected values may be
+//! replaced by the name of a `WalletError` variant. Inputs are `"dummy"` or a coin reference.
+//! Addresses are `Alice`, `Bob`, `Charlie`, `Dave`, `Eve` or `Custom(<n>)`.
+
+use crate::harness::WalletFactory;
+use bonecoin_core::*;
+use serde::Deserialize;
+use std::collections::*;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// Directory the scenario runner picks its files from.
+pub const SCENARIO_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/scenarios");
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioFile {
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub transactions: BTreeMap<String, TxFile>,
+    pub steps: Vec<StepFile>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TxFile {
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    pub outputs: Vec<CoinFile>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CoinFile {
+    pub value: u64,
+    pub owner: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum StepFile {
+    Block {
+        name: String,
+        parent: String,
+        #[serde(default)]
+        transactions: Vec<String>,
+        #[serde(default)]
+        best: bool,
+    },
+    SetBest {
+        block: String,
+    },
+    Sync,
+    Expect(Expectations),
+}
+
+/// Either the expected value or the name of the expected `WalletError`.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Expected<T> {
+    Value(T),
+    Error(String),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Expectations {
+    pub best_height: Option<u64>,
+    pub best_hash: Option<String>,
+    pub net_worth: Option<u64>,
+    #[serde(default)]
+    pub total_assets_of: BTreeMap<String, Expected<u64>>,
+    #[serde(default)]
+    pub all_coins_of: BTreeMap<String, Expected<Vec<String>>>,
+    #[serde(default)]
+    pub coin_details: BTreeMap<String, Expected<CoinFile>>,
+    #[serde(default)]
+    pub manual_transactions: Vec<ManualTxFile>,
+    #[serde(default)]
+    pub automatic_transactions: Vec<AutomaticTxFile>,
+}
+
+/// A `create_manual_transaction` call, expected to succeed unless `error` is given.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ManualTxFile {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<CoinFile>,
+    pub error: Option<String>,
+}
+
+/// A `create_automatic_transaction` call, expected to succeed unless `error` is given.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AutomaticTxFile {
+    pub recipient: String,
+    pub value: u64,
+    #[serde(default)]
+    pub burn: u64,
+    pub error: Option<String>,
+}
+
+/// Parse an address as written in scenario files.
+pub fn parse_address(name: &str) -> Address {
+    try_parse_address(name).unwrap_or_else(|| panic!("unknown address {}", name))
+}
+
+/// Parse an address written as its `Debug` form, like `Alice` or `Custom(3)`.
+pub fn try_parse_address(name: &str) -> Option<Address> {
+    match name {
+        "Alice" => Some(Address::Alice),
+        "Bob" => Some(Address::Bob),
+        "Charlie" => Some(Address::Charlie),
+        "Dave" => Some(Address::Dave),
+        "Eve" => Some(Address::Eve),
+        _ => name
+            .strip_prefix("Custom(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|number| number.trim().parse().ok())
+            .map(Address::Custom),
+    }
+}
+
+/// Parse the name of a `WalletError` variant.
+pub fn parse_error(name: &str) -> WalletError {
+    match name {
+        "ForeignAddress" => WalletError::ForeignAddress,
+        "UnknownCoin" => WalletError::UnknownCoin,
+        "InsufficientFunds" => WalletError::InsufficientFunds,
+        "NoOwnedAddresses" => WalletError::NoOwnedAddresses,
+        "ZeroCoinValue" => WalletError::ZeroCoinValue,
+        "ZeroInputs" => WalletError::ZeroInputs,
+        _ => panic!("unknown wallet error {}", name),
+    }
+}
+
+impl CoinFile {
+    fn coin(&self) -> Coin {
+        Coin {
+            value: self.value,
+            owner: parse_address(&self.owner),
+        }
+    }
+}
+
+impl<T> Expected<T> {
+    fn map<U>(&self, f: impl FnOnce(&T) -> U) -> Result<U, WalletError> {
+        match self {
+            Expected::Value(value) => Ok(f(value)),
+            Expected::Error(error) => Err(parse_error(error)),
+        }
+    }
+}
+
+/// The node side of a scenario file being run.
+struct FileChain<'a> {
+    file: &'a ScenarioFile,
+    node: MockNode,
+    blocks: HashMap<String, (BlockId, u64)>,
+    /// Each transaction as last put in a block, with the height of that block.
+    landed: HashMap<String, (Transaction, u64)>,
+}
+
+impl<'a> FileChain<'a> {
+    fn block(&self, name: &str) -> (BlockId, u64) {
+        if name == "G" {
+            return (Block::genesis().id(), 0);
+        }
+        *self
+            .blocks
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown block {}", name))
+    }
+
+    fn tx_file(&self, name: &str) -> &'a TxFile {
+        self.file
+            .transactions
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown transaction {}", name))
+    }
+
+    /// The coin id and the coin a `"<transaction>:<output>"` reference stands for.
+    fn coin(&self, reference: &str) -> (CoinId, Coin) {
+        let (tx, output) = reference
+            .split_once(':')
+            .and_then(|(tx, output)| Some((tx, output.parse::<usize>().ok()?)))
+            .unwrap_or_else(|| {
+                panic!("coin reference {} is not <transaction>:<output>", reference)
+            });
+        let (transaction, height) = self
+            .landed
+            .get(tx)
+            .unwrap_or_else(|| panic!("coin {} refers to a transaction never mined", reference));
+        let coin = transaction
+            .outputs
+            .get(output)
+            .unwrap_or_else(|| panic!("transaction {} has no output {}", tx, output))
+            .clone();
+        (transaction.coin_id(*height, output), coin)
+    }
+
+    fn coin_id(&self, reference: &str) -> CoinId {
+        self.coin(reference).0
+    }
+
+    fn transaction(&self, name: &str) -> Transaction {
+        let tx_file = self.tx_file(name);
+        let inputs = tx_file
+            .inputs
+            .iter()
+            .map(|input| {
+                if input == "dummy" {
+                    return Input::dummy();
+                }
+                let (coin_id, coin) = self.coin(input);
+                Input {
+                    coin_id,
+                    signature: Signature::Valid(coin.owner),
+                }
+            })
+            .collect();
+        Transaction {
+            inputs,
+            outputs: tx_file.outputs.iter().map(CoinFile::coin).collect(),
+        }
+    }
+
+    fn add_block(&mut self, name: &str, parent: &str, transactions: &[String], best: bool) {
+        assert!(
+            name != "G" && !self.blocks.contains_key(name),
+            "block {} is defined twice",
+            name
+        );
+        let (parent_id, parent_height) = self.block(parent);
+        let height = parent_height + 1;
+
+        // Transactions land one by one so they can spend coins created earlier in the block.
+        let mut body = Vec::new();
+        for tx in transactions {
+            let transaction = self.transaction(tx);
+            self.landed
+                .insert(tx.clone(), (transaction.clone(), height));
+            body.push(transaction);
+        }
+
+        let block_id = if best {
+            self.node.add_block_as_best(parent_id, body)
+        } else {
+            self.node.add_block(parent_id, body)
+        };
+        self.blocks.insert(name.to_string(), (block_id, height));
+    }
+}
+
+impl ScenarioFile {
+    /// Read and parse a scenario file, panicking with the file name if it is malformed.
+    pub fn load(path: &Path) -> ScenarioFile {
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+        toml::from_str(&text).unwrap_or_else(|e| panic!("cannot parse {}: {}", path.display(), e))
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.addresses.iter().map(|a| parse_address(a)).collect()
+    }
+
+    /// Play the scenario against `wallet`, panicking on the first unmet expectation.
+    pub fn run<W: WalletApi>(&self, mut wallet: W) {
+        let mut chain = FileChain {
+            file: self,
+            node: MockNode::new(),
+            blocks: HashMap::new(),
+            landed: HashMap::new(),
+        };
+
+        for (index, step) in self.steps.iter().enumerate() {
+            match step {
+                StepFile::Block {
+                    name,
+                    parent,
+                    transactions,
+                    best,
+                } => chain.add_block(name, parent, transactions, *best),
+                StepFile::SetBest { block } => {
+                    let (block_id, _) = chain.block(block);
+                    chain.node.set_best(block_id);
+                }
+                StepFile::Sync => wallet.sync(&chain.node),
+                StepFile::Expect(expectations) => check(&wallet, &chain, expectations, index + 1),
+            }
+        }
+    }
+}
+
+fn check<W: WalletApi>(wallet: &W, chain: &FileChain, expect: &Expectations, step: usize) {
+    if let Some(height) = expect.best_height {
+        assert_eq!(wallet.best_height(), height, "best_height, step {}", step);
+    }
+    if let Some(block) = &expect.best_hash {
+        assert_eq!(
+            wallet.best_hash(),
+            chain.block(block).0,
+            "best_hash, step {}",
+            step
+        );
+    }
+    if let Some(net_worth) = expect.net_worth {
+        assert_eq!(wallet.net_worth(), net_worth, "net_worth, step {}", step);
+    }
+    for (address, expected) in &expect.total_assets_of {
+        assert_eq!(
+            wallet.total_assets_of(parse_address(address)),
+            expected.map(|value| *value),
+            "total_assets_of({}), step {}",
+            address,
+            step
+        );
+    }
+    for (address, expected) in &expect.all_coins_of {
+        let expected = expected.map(|coins| {
+            coins
+                .iter()
+                .map(|reference| {
+                    let (coin_id, coin) = chain.coin(reference);
+                    (coin_id, coin.value)
+                })
+                .collect::<HashSet<_>>()
+        });
+        assert_eq!(
+            wallet.all_coins_of(parse_address(address)),
+            expected,
+            "all_coins_of({}), step {}",
+            address,
+            step
+        );
+    }
+    for (reference, expected) in &expect.coin_details {
+        assert_eq!(
+            wallet.coin_details(&chain.coin_id(reference)),
+            expected.map(CoinFile::coin),
+            "coin_details({}), step {}",
+            reference,
+            step
+        );
+    }
+    for manual in &expect.manual_transactions {
+        let inputs = manual.inputs.iter().map(|c| chain.coin_id(c)).collect();
+        let outputs = manual.outputs.iter().map(CoinFile::coin).collect();
+        let result = wallet.create_manual_transaction(inputs, outputs);
+        match &manual.error {
+            Some(error) => assert_eq!(
+                result.err(),
+                Some(parse_error(error)),
+                "create_manual_transaction({:?}), step {}",
+                manual.inputs,
+                step
+            ),
+            None => assert!(
+                result.is_ok(),
+                "create_manual_transaction({:?}) failed with {:?}, step {}",
+                manual.inputs,
+                result,
+                step
+            ),
+        }
+    }
+    for automatic in &expect.automatic_transactions {
+        let result = wallet.create_automatic_transaction(
+            parse_address(&automatic.recipient),
+            automatic.value,
+            automatic.burn,
+        );
+        match &automatic.error {
+            Some(error) => assert_eq!(
+                result.err(),
+                Some(parse_error(error)),
+                "create_automatic_transaction({}, {}), step {}",
+                automatic.recipient,
+                automatic.value,
+                step
+            ),
+            None => assert!(
+                result.is_ok(),
+                "create_automatic_transaction({}, {}) failed with {:?}, step {}",
+                automatic.recipient,
+                automatic.value,
+                result,
+                step
+            ),
+        }
+    }
+}
+
+/// Every scenario file in `dir`, sorted by name.
+pub fn scenario_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.expect("unreadable directory entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic".to_string()
+    }
+}
+
+/// Run every scenario file in `dir` against a fresh wallet from `F`, print one line per file
+/// and fail if any of them failed.
+pub fn check_scenario_files<F: WalletFactory>(dir: &Path) {
+    let files = scenario_files(dir);
+    let mut failures = Vec::new();
+
+    for path in &files {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let scenario = ScenarioFile::load(path);
+            scenario.run(F::new_wallet(scenario.addresses().into_iter()));
+        }));
+        let name = path.file_name().unwrap().to_string_lossy();
+        match result {
+            Ok(()) => println!("scenario {} ... ok", name),
+            Err(payload) => {
+                let message = panic_message(payload.as_ref());
+                println!("scenario {} ... FAILED: {}", name, message);
+                failures.push(name.into_owned());
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} scenario files failed: {}",
+        failures.len(),
+        files.len(),
+        failures.join(", ")
+    );
+}
@@ -0,0 +1,9 @@
+//! Scenarios written as TOML files under `scenarios/`, see `scenario_file.rs` for the format
+
+use crate::harness::WalletFactory;
+use crate::scenario_file::{check_scenario_files, SCENARIO_DIR};
+use std::path::Path;
+
+pub fn scenario_files<F: WalletFactory>() {
+    check_scenario_files::<F>(Path::new(SCENARIO_DIR));
+}
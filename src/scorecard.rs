@@ -0,0 +1,262 @@
+//! Conformance scorecard: the whole battery run once, tallied per contributor module and per
+//! capability.
+//!
+//! The `bonecoin-score` binary prints it for the configured wallet. Tests are run one after the
+//! other on the calling thread; a failing test is caught and recorded, never fatal. Extension
+//! tests are only scored for the extensions listed in
+//! [`suite_tests_with!`](crate::suite_tests_with), and the scorecard names the ones left out.
+
+use crate::harness::{SuiteTest, EXTENSIONS};
+use crate::tags::TagFilter;
+use serde::Serialize;
+use std::collections::*;
+use std::fmt::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// What a test exercises, for the per capability tally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Sync,
+    Reorg,
+    UtxoTracking,
+    ManualTx,
+    AutomaticTx,
+    Performance,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Capability::Sync => "sync",
+            Capability::Reorg => "reorg",
+            Capability::UtxoTracking => "UTXO tracking",
+            Capability::ManualTx => "manual tx",
+            Capability::AutomaticTx => "automatic tx",
+            Capability::Performance => "performance",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The capability a test of the suite is counted under, from its first [tag](crate::tags).
+pub fn capability_of(tags: &[&str]) -> Capability {
+    match tags.first().copied() {
+        Some("reorg") => Capability::Reorg,
+        Some("utxo") => Capability::UtxoTracking,
+        Some("manual-tx") => Capability::ManualTx,
+        Some("auto-tx") => Capability::AutomaticTx,
+        Some("perf") => Capability::Performance,
+        _ => Capability::Sync,
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TestOutcome {
+    pub module: &'static str,
+    pub name: &'static str,
+    pub tags: &'static [&'static str],
+    pub capability: Capability,
+    pub passed: bool,
+    /// The panic message of a failed test.
+    pub failure: Option<String>,
+    pub seconds: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Tally {
+    pub group: String,
+    pub passed: usize,
+    pub total: usize,
+}
+
+impl Tally {
+    pub fn percent(&self) -> f64 {
+        100.0 * self.passed as f64 / self.total.max(1) as f64
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Scorecard {
+    pub wallet: String,
+    pub passed: usize,
+    pub total: usize,
+    /// Extensions whose tests were not part of the run.
+    pub extensions_left_out: Vec<&'static str>,
+    pub by_module: Vec<Tally>,
+    pub by_capability: Vec<Tally>,
+    pub tests: Vec<TestOutcome>,
+}
+
+/// Run `test`, catching its failure and the message it panicked with.
+fn run_one(test: &SuiteTest, last_panic: &Mutex<Option<String>>) -> TestOutcome {
+    last_panic.lock().unwrap().take();
+    let start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(test.run));
+    let seconds = start.elapsed().as_secs_f64();
+
+    let failure = result.err().map(|_| {
+        last_panic
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(|| "panicked".to_string())
+    });
+    TestOutcome {
+        module: test.module,
+        name: test.name,
+        tags: test.tags,
+        capability: capability_of(test.tags),
+        passed: failure.is_none(),
+        failure,
+        seconds,
+    }
+}
+
+fn tally<K: Ord + ToString>(
+    outcomes: &[TestOutcome],
+    key: impl Fn(&TestOutcome) -> K,
+) -> Vec<Tally> {
+    let mut groups: BTreeMap<K, (usize, usize)> = BTreeMap::new();
+    for outcome in outcomes {
+        let (passed, total) = groups.entry(key(outcome)).or_default();
+        *passed += outcome.passed as usize;
+        *total += 1;
+    }
+    groups
+        .into_iter()
+        .map(|(group, (passed, total))| Tally {
+            group: group.to_string(),
+            passed,
+            total,
+        })
+        .collect()
+}
+
+/// Run every test of `tests` selected by `BONECOIN_TAGS`, as listed by
+/// [`suite_tests_with!`](crate::suite_tests_with). `progress` is called after each test.
+pub fn score(
+    wallet: &str,
+    tests: Vec<SuiteTest>,
+    mut progress: impl FnMut(&TestOutcome),
+) -> Scorecard {
+    let extensions_left_out = EXTENSIONS
+        .iter()
+        .copied()
+        .filter(|extension| {
+            let module = format!("{}_tests", extension);
+            !tests.iter().any(|test| test.module == module)
+        })
+        .collect();
+
+    // Keep panic messages for the report instead of printing them.
+    let last_panic = Arc::new(Mutex::new(None));
+    let previous_hook = panic::take_hook();
+    let hook_panic = last_panic.clone();
+    panic::set_hook(Box::new(move |info| {
+        *hook_panic.lock().unwrap() = Some(info.to_string());
+    }));
+
+    let mut outcomes = Vec::new();
+    let filter = TagFilter::from_env();
+    for test in tests {
+        if !filter.selects(test.tags) {
+            continue;
+        }
+        let outcome = run_one(&test, &last_panic);
+        progress(&outcome);
+        outcomes.push(outcome);
+    }
+    panic::set_hook(previous_hook);
+
+    // Modules keep the order of the suite, capabilities the order of `Capability`.
+    let mut by_module: Vec<Tally> = Vec::new();
+    for outcome in &outcomes {
+        if by_module.last().map(|t| t.group.as_str()) != Some(outcome.module) {
+            by_module.push(Tally {
+                group: outcome.module.to_string(),
+                passed: 0,
+                total: 0,
+            });
+        }
+        let module = by_module.last_mut().unwrap();
+        module.passed += outcome.passed as usize;
+        module.total += 1;
+    }
+
+    Scorecard {
+        wallet: wallet.to_string(),
+        passed: outcomes.iter().filter(|outcome| outcome.passed).count(),
+        total: outcomes.len(),
+        extensions_left_out,
+        by_module,
+        by_capability: tally(&outcomes, |outcome| outcome.capability),
+        tests: outcomes,
+    }
+}
+
+impl Scorecard {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scorecard is always serializable")
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "# Scorecard for {}", self.wallet).unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "**{} / {} tests pass ({:.1}%)**",
+            self.passed,
+            self.total,
+            100.0 * self.passed as f64 / self.total.max(1) as f64
+        )
+        .unwrap();
+        if !self.extensions_left_out.is_empty() {
+            writeln!(out).unwrap();
+            writeln!(
+                out,
+                "Not scored: the tests of the {} extensions.",
+                self.extensions_left_out.join(", ")
+            )
+            .unwrap();
+        }
+
+        for (title, column, tallies) in [
+            ("By contributor module", "Module", &self.by_module),
+            ("By capability", "Capability", &self.by_capability),
+        ] {
+            writeln!(out).unwrap();
+            writeln!(out, "## {}", title).unwrap();
+            writeln!(out).unwrap();
+            writeln!(out, "| {} | Passed | Total | % |", column).unwrap();
+            writeln!(out, "|---|---:|---:|---:|").unwrap();
+            for tally in tallies {
+                writeln!(
+                    out,
+                    "| {} | {} | {} | {:.1} |",
+                    tally.group,
+                    tally.passed,
+                    tally.total,
+                    tally.percent()
+                )
+                .unwrap();
+            }
+        }
+
+        let failures: Vec<&TestOutcome> = self.tests.iter().filter(|t| !t.passed).collect();
+        if !failures.is_empty() {
+            writeln!(out).unwrap();
+            writeln!(out, "## Failures").unwrap();
+            writeln!(out).unwrap();
+            for test in failures {
+                let reason = test.failure.as_deref().unwrap_or_default();
+                let reason = reason.lines().collect::<Vec<_>>().join(" ");
+                writeln!(out, "- `{}::{}`: {}", test.module, test.name, reason).unwrap();
+            }
+        }
+        out
+    }
+}
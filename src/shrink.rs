@@ -0,0 +1,395 @@
+//! Minimization of failing random scenarios.
+//!
+//! The shrinker greedily removes blocks, transactions, inputs, best block switches and syncs
+//! from a [`Scenario`] for as long as the wallet keeps failing on it. The result is printed as a
+//! suite test that can be pasted into any `*_tests.rs` module.
+
+use crate::differential::MISMATCH;
+use crate::harness::WalletFactory;
+use crate::oracle::OracleWallet;
+use crate::random_scenario::{InputSpec, MaterializedChain, Scenario, Step};
+use crate::recording_node::RecordingNode;
+use bonecoin_core::*;
+use std::cell::Cell;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+
+thread_local! {
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` and catch its panic, without printing the panic of this thread. The panic hook in
+/// place before is put back afterwards.
+pub fn catch_quietly(f: impl FnOnce()) -> thread::Result<()> {
+    let previous = Arc::new(panic::take_hook());
+    let forward = Arc::clone(&previous);
+    // Other threads may panic meanwhile, only this one is silenced.
+    panic::set_hook(Box::new(move |info| {
+        if !QUIET.with(|quiet| quiet.get()) {
+            (*forward)(info);
+        }
+    }));
+
+    QUIET.with(|quiet| quiet.set(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    QUIET.with(|quiet| quiet.set(false));
+
+    drop(panic::take_hook());
+    match Arc::try_unwrap(previous) {
+        Ok(previous) => panic::set_hook(previous),
+        // Another thread took our hook meanwhile and still holds on to it.
+        Err(previous) => panic::set_hook(Box::new(move |info| (*previous)(info))),
+    }
+    result
+}
+
+/// Run `f`, returning whether it panicked, without printing the panic of this thread.
+pub fn panics_quietly(f: impl FnOnce()) -> bool {
+    catch_quietly(f).is_err()
+}
+
+/// Whether the wallets of `F` disagree with the oracle on `scenario`, without printing their
+/// panics. A candidate on which anything else panics, like `MockNode` on a chain the shrinker
+/// broke, does not fail: it is discarded.
+pub fn fails<F: WalletFactory>(scenario: &Scenario) -> bool {
+    let Err(payload) =
+        catch_quietly(|| scenario.run(F::new_wallet(scenario.addresses.iter().copied())))
+    else {
+        return false;
+    };
+    let message = match payload.downcast_ref::<String>() {
+        Some(message) => message.as_str(),
+        None => payload.downcast_ref::<&str>().copied().unwrap_or_default(),
+    };
+    message.contains(MISMATCH)
+}
+
+/// Whether a block of `scenario` has the same transaction twice, whose coins would then share
+/// their ids. Removing inputs can turn a spend into a copy of a mint next to it.
+fn repeats_a_transaction(scenario: &Scenario) -> bool {
+    scenario.steps.iter().any(|step| match step {
+        Step::AddBlock { transactions, .. } => transactions
+            .iter()
+            .enumerate()
+            .any(|(index, spec)| transactions[..index].contains(spec)),
+        _ => false,
+    })
+}
+
+/// Block positions of the scenario, in step order, with their parents.
+fn parents(scenario: &Scenario) -> Vec<Option<usize>> {
+    scenario
+        .steps
+        .iter()
+        .filter_map(|step| match step {
+            Step::AddBlock { parent, .. } => Some(*parent),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The scenario without block `removed`. Its children move to its parent and its coins are
+/// no longer spent by anyone.
+fn without_block(scenario: &Scenario, removed: usize) -> Scenario {
+    let grandparent = parents(scenario)[removed];
+    let renumber = |block: Option<usize>| match block {
+        Some(block) if block == removed => grandparent,
+        Some(block) if block > removed => Some(block - 1),
+        other => other,
+    };
+
+    let mut block = 0;
+    let mut steps = Vec::new();
+    for step in &scenario.steps {
+        match step {
+            Step::AddBlock {
+                parent,
+                transactions,
+                as_best,
+            } => {
+                if block != removed {
+                    let mut transactions = transactions.clone();
+                    for tx in &mut transactions {
+                        tx.inputs.retain(|input| {
+                            !matches!(input, InputSpec::Coin(coin) if coin.block == removed)
+                        });
+                        for input in &mut tx.inputs {
+                            if let InputSpec::Coin(coin) = input {
+                                if coin.block > removed {
+                                    coin.block -= 1;
+                                }
+                            }
+                        }
+                    }
+                    steps.push(Step::AddBlock {
+                        parent: renumber(*parent),
+                        transactions,
+                        as_best: *as_best,
+                    });
+                }
+                block += 1;
+            }
+            Step::SetBest(best) => steps.push(Step::SetBest(renumber(*best))),
+            Step::Sync => steps.push(Step::Sync),
+        }
+    }
+
+    Scenario {
+        steps,
+        ..scenario.clone()
+    }
+}
+
+/// The scenario without transaction `tx` of block `block`.
+fn without_tx(scenario: &Scenario, block: usize, tx: usize) -> Scenario {
+    let mut scenario = scenario.clone();
+    let mut current = 0;
+    for step in &mut scenario.steps {
+        if let Step::AddBlock { transactions, .. } = step {
+            if current == block {
+                transactions.remove(tx);
+            }
+            for spec in transactions.iter_mut() {
+                spec.inputs.retain(|input| match input {
+                    InputSpec::Coin(coin) => coin.block != block || coin.tx != tx,
+                    InputSpec::Dummy => true,
+                });
+                for input in &mut spec.inputs {
+                    if let InputSpec::Coin(coin) = input {
+                        if coin.block == block && coin.tx > tx {
+                            coin.tx -= 1;
+                        }
+                    }
+                }
+            }
+            current += 1;
+        }
+    }
+    scenario
+}
+
+/// Every smaller scenario one edit away, the ones removing the most first.
+fn candidates(scenario: &Scenario) -> Vec<Scenario> {
+    let mut candidates = Vec::new();
+    let blocks = scenario.block_count();
+
+    for block in (0..blocks).rev() {
+        candidates.push(without_block(scenario, block));
+    }
+
+    let mut block = 0;
+    for (index, step) in scenario.steps.iter().enumerate() {
+        match step {
+            Step::AddBlock { transactions, .. } => {
+                for tx in (0..transactions.len()).rev() {
+                    candidates.push(without_tx(scenario, block, tx));
+                }
+                for (tx, spec) in transactions.iter().enumerate() {
+                    for input in 0..spec.inputs.len() {
+                        let mut smaller = scenario.clone();
+                        if let Step::AddBlock { transactions, .. } = &mut smaller.steps[index] {
+                            transactions[tx].inputs.remove(input);
+                        }
+                        candidates.push(smaller);
+                    }
+                }
+                block += 1;
+            }
+            Step::SetBest(_) | Step::Sync => {
+                let mut smaller = scenario.clone();
+                smaller.steps.remove(index);
+                candidates.push(smaller);
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Shrink a scenario the wallets of `F` fail on to one where no single edit keeps it failing.
+pub fn shrink<F: WalletFactory>(scenario: &Scenario) -> Scenario {
+    let mut smallest = scenario.clone();
+    'pass: loop {
+        for candidate in candidates(&smallest) {
+            if !repeats_a_transaction(&candidate) && fails::<F>(&candidate) {
+                smallest = candidate;
+                continue 'pass;
+            }
+        }
+        return smallest;
+    }
+}
+
+fn address(address: Address) -> String {
+    format!("Address::{:?}", address)
+}
+
+impl Scenario {
+    /// The scenario as a suite test, with the oracle's view asserted after every sync.
+    pub fn to_rust_test(&self) -> String {
+        let mut node = RecordingNode::new();
+        let mut oracle = OracleWallet::new(self.addresses.iter().copied(), node.record());
+        let mut chain = MaterializedChain::default();
+        let mut code = String::new();
+
+        let addresses: Vec<String> = self.addresses.iter().map(|a| address(*a)).collect();
+        writeln!(
+            code,
+            "pub fn shrunk_seed_{}<F: WalletFactory>() {{",
+            self.seed
+        )
+        .unwrap();
+        writeln!(code, "    let mut node = MockNode::new();").unwrap();
+        writeln!(
+            code,
+            "    let mut wallet = F::new_wallet(vec![{}].into_iter());",
+            addresses.join(", ")
+        )
+        .unwrap();
+
+        for step in &self.steps {
+            match step {
+                Step::AddBlock {
+                    parent,
+                    transactions: specs,
+                    as_best,
+                } => {
+                    let block = chain.ids.len();
+                    let (parent_id, transactions) = chain.materialize(*parent, specs);
+                    let height = chain.height(*parent) + 1;
+                    writeln!(code).unwrap();
+
+                    let mut names = Vec::new();
+                    for (index, tx) in transactions.iter().enumerate() {
+                        let name = format!("tx_{}_{}", block, index);
+                        writeln!(code, "    let {} = Transaction {{", name).unwrap();
+                        writeln!(code, "        inputs: vec![").unwrap();
+                        for input in &specs[index].inputs {
+                            if let Some(input) =
+                                input_code(&chain, input, block, height, &transactions[..index])
+                            {
+                                writeln!(code, "            {},", input).unwrap();
+                            }
+                        }
+                        writeln!(code, "        ],").unwrap();
+                        writeln!(code, "        outputs: vec![").unwrap();
+                        for coin in &tx.outputs {
+                            writeln!(
+                                code,
+                                "            Coin {{ value: {}, owner: {} }},",
+                                coin.value,
+                                address(coin.owner)
+                            )
+                            .unwrap();
+                        }
+                        writeln!(code, "        ],").unwrap();
+                        writeln!(code, "    }};").unwrap();
+                        names.push(format!("{}.clone()", name));
+                    }
+
+                    let parent_name = match parent {
+                        Some(parent) => format!("b{}", parent),
+                        None => "Block::genesis().id()".to_string(),
+                    };
+                    let method = if *as_best {
+                        "add_block_as_best"
+                    } else {
+                        "add_block"
+                    };
+                    writeln!(
+                        code,
+                        "    let b{} = node.{}({}, vec![{}]);",
+                        block,
+                        method,
+                        parent_name,
+                        names.join(", ")
+                    )
+                    .unwrap();
+
+                    let block_id = if *as_best {
+                        node.add_block_as_best(parent_id, transactions.clone())
+                    } else {
+                        node.add_block(parent_id, transactions.clone())
+                    };
+                    chain.push(block_id, *parent, transactions);
+                }
+                Step::SetBest(best) => {
+                    let name = match best {
+                        Some(best) => format!("b{}", best),
+                        None => "Block::genesis().id()".to_string(),
+                    };
+                    writeln!(code, "    node.set_best({});", name).unwrap();
+                    node.set_best(chain.id(*best));
+                }
+                Step::Sync => {
+                    oracle.sync(&node);
+                    writeln!(code, "    wallet.sync(&node);").unwrap();
+                    writeln!(
+                        code,
+                        "    assert_eq!(wallet.best_height(), {});",
+                        oracle.best_height()
+                    )
+                    .unwrap();
+                    let best = chain.ids.iter().position(|id| *id == oracle.best_hash());
+                    let best = match best {
+                        Some(best) => format!("b{}", best),
+                        None => "Block::genesis().id()".to_string(),
+                    };
+                    writeln!(code, "    assert_eq!(wallet.best_hash(), {});", best).unwrap();
+                    for owned in &self.addresses {
+                        writeln!(
+                            code,
+                            "    assert_eq!(wallet.total_assets_of({}), Ok({}));",
+                            address(*owned),
+                            oracle.total_assets_of(*owned).unwrap()
+                        )
+                        .unwrap();
+                    }
+                    writeln!(
+                        code,
+                        "    assert_eq!(wallet.net_worth(), {});",
+                        oracle.net_worth()
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        writeln!(code, "}}").unwrap();
+        code
+    }
+}
+
+/// Source code of `input` of a transaction in block `block`, or `None` if the coin it spends
+/// is not part of the scenario anymore, in which case running the scenario leaves it out too.
+fn input_code(
+    chain: &MaterializedChain,
+    input: &InputSpec,
+    block: usize,
+    height: u64,
+    block_txs: &[Transaction],
+) -> Option<String> {
+    let coin = match input {
+        InputSpec::Dummy => return Some("Input::dummy()".to_string()),
+        InputSpec::Coin(coin) => coin,
+    };
+    let (source, source_height) = if coin.block == block {
+        (block_txs.get(coin.tx)?, height)
+    } else {
+        (
+            chain.transactions.get(coin.block)?.get(coin.tx)?,
+            chain.heights[coin.block],
+        )
+    };
+    let owner = source.outputs.get(coin.output)?.owner;
+    Some(format!(
+        "Input {{ coin_id: tx_{}_{}.coin_id({}, {}), signature: Signature::Valid({}) }}",
+        coin.block,
+        coin.tx,
+        source_height,
+        coin.output,
+        address(owner)
+    ))
+}
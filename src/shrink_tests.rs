@@ -0,0 +1,135 @@
+//! The shrinker, run on a wallet that is broken on purpose
+//!
+//! [`ReorgBlind`] wallets stop following the node as soon as the block they synced last leaves
+//! the best chain, so random scenarios with forks fail on them whatever wallet they wrap.
+
+use crate::harness::WalletFactory;
+use crate::random_scenario::{RandomConfig, Scenario, Step};
+use crate::shrink::{fails, shrink};
+use crate::spec_profile::SpecProfile;
+use bonecoin_core::*;
+use std::collections::*;
+use std::marker::PhantomData;
+
+/// A wallet that never reorgs: it only syncs while its best block is still on the best chain.
+pub struct ReorgBlindWallet<W: WalletApi>(W);
+
+impl<W: WalletApi> WalletApi for ReorgBlindWallet<W> {
+    fn sync(&mut self, node: &MockNode) {
+        if node.best_block_at_height(self.0.best_height()) == Some(self.0.best_hash()) {
+            self.0.sync(node);
+        }
+    }
+
+    fn best_height(&self) -> u64 {
+        self.0.best_height()
+    }
+
+    fn best_hash(&self) -> BlockId {
+        self.0.best_hash()
+    }
+
+    fn total_assets_of(&self, address: Address) -> Result<u64, WalletError> {
+        self.0.total_assets_of(address)
+    }
+
+    fn net_worth(&self) -> u64 {
+        self.0.net_worth()
+    }
+
+    fn all_coins_of(&self, address: Address) -> Result<HashSet<(CoinId, u64)>, WalletError> {
+        self.0.all_coins_of(address)
+    }
+
+    fn coin_details(&self, coin_id: &CoinId) -> Result<Coin, WalletError> {
+        self.0.coin_details(coin_id)
+    }
+
+    fn create_manual_transaction(
+        &self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError> {
+        self.0
+            .create_manual_transaction(input_coin_ids, output_coins)
+    }
+
+    fn create_automatic_transaction(
+        &self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    ) -> Result<Transaction, WalletError> {
+        self.0
+            .create_automatic_transaction(recipient, output_value, burn)
+    }
+}
+
+/// The factory of [`ReorgBlindWallet`]s around wallets from `F`.
+pub struct ReorgBlind<F>(PhantomData<F>);
+
+impl<F: WalletFactory> WalletFactory for ReorgBlind<F> {
+    type Wallet = ReorgBlindWallet<F::Wallet>;
+
+    const PROFILE: SpecProfile = F::PROFILE;
+
+    fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet {
+        ReorgBlindWallet(F::new_wallet(addresses))
+    }
+}
+
+pub fn shrinks_a_reorg_failure_to_a_smaller_test<F: WalletFactory>() {
+    let config = RandomConfig {
+        blocks: 15,
+        fork_percent: 50,
+        set_best_percent: 25,
+        sync_percent: 50,
+        ..RandomConfig::default()
+    };
+    let scenario = (0..100)
+        .map(|seed| Scenario::generate(seed, &config))
+        .find(|scenario| fails::<ReorgBlind<F>>(scenario))
+        .expect("no scenario of the first 100 seeds needs a reorg");
+
+    let shrunk = shrink::<ReorgBlind<F>>(&scenario);
+    assert!(
+        fails::<ReorgBlind<F>>(&shrunk),
+        "seed {}: the shrunk scenario no longer fails",
+        scenario.seed
+    );
+    assert!(
+        shrunk.block_count() < scenario.block_count() && shrunk.steps.len() < scenario.steps.len(),
+        "seed {}: shrinking kept {} of {} blocks and {} of {} steps",
+        scenario.seed,
+        shrunk.block_count(),
+        scenario.block_count(),
+        shrunk.steps.len(),
+        scenario.steps.len()
+    );
+
+    // A test for the suite, with one block per `add_block` and assertions after every sync.
+    let code = shrunk.to_rust_test();
+    let syncs = shrunk
+        .steps
+        .iter()
+        .filter(|step| matches!(step, Step::Sync))
+        .count();
+    assert!(
+        code.starts_with(&format!(
+            "pub fn shrunk_seed_{}<F: WalletFactory>() {{\n    let mut node = MockNode::new();\n",
+            scenario.seed
+        )),
+        "unexpected start of the test:\n{}",
+        code
+    );
+    assert!(
+        code.ends_with("\n}\n"),
+        "unexpected end of the test:\n{}",
+        code
+    );
+    let count = |pattern: &str| code.lines().filter(|line| line.contains(pattern)).count();
+    assert_eq!(count(" = node.add_block"), shrunk.block_count(), "{}", code);
+    assert_eq!(count("    wallet.sync(&node);"), syncs, "{}", code);
+    assert_eq!(count("assert_eq!(wallet.best_hash(), "), syncs, "{}", code);
+    assert_eq!(count("assert_eq!(wallet.net_worth(), "), syncs, "{}", code);
+}
@@ -2,15 +2,15 @@
 
 use bonecoin_core::*;
 use std::collections::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
 
 /// Simple helper to initialize a wallet with just one account.
-fn wallet_with_alice() -> Wallet {
-    Wallet::new(vec![Address::Alice].into_iter())
+fn wallet_with_alice<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice].into_iter())
 }
 
-fn wallet_with_multiple_users() -> Wallet {
-    Wallet::new(vec![Address::Alice, Address::Bob, Address::Charlie].into_iter())
+fn wallet_with_multiple_users<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice, Address::Bob, Address::Charlie].into_iter())
 }
 
 /// Helper to create a simple and somewhat collision unlikely transaction to mark forks.
@@ -28,7 +28,7 @@ fn marker_tx() -> Transaction {
     }
 }
 
-fn initial_setup() -> (impl WalletApi, MockNode) {
+fn initial_setup<F: WalletFactory>() -> (impl WalletApi, MockNode) {
     // All coins will be valued the same in this test
     // We start by minting a coin to alice
     let tx_mint_1 = Transaction {
@@ -71,18 +71,27 @@ fn initial_setup() -> (impl WalletApi, MockNode) {
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint_1.clone()]);
     let _b2_id = node.add_block_as_best(b1_id, vec![tx_mint_2.clone()]);
 
-    let mut wallet = wallet_with_multiple_users();
+    let mut wallet = wallet_with_multiple_users::<F>();
     wallet.sync(&node);
 
     (wallet, node)
 }
 
 // MODIFIED: your UTXO selection strategy can result on this test failing, so I've changed the transaction
-#[test]
-fn reorg_with_utxos_01() {
-    let (mut wallet, mut node) = initial_setup();
+// to spend every coin, and the strategy declared in the wallet's profile is checked on its own
+pub fn reorg_with_utxos_01<F: WalletFactory>() {
+    let (mut wallet, mut node) = initial_setup::<F>();
     let coins = Vec::from_iter(wallet.all_coins_of(Address::Alice).unwrap());
 
+    // Each address owns a coin of 100 and one of 200, so this spends either 2 or 3 of them.
+    let partial = wallet.create_automatic_transaction(Address::Dave, 250, 50);
+    F::PROFILE.assert_selection(
+        &wallet,
+        &[Address::Alice, Address::Bob, Address::Charlie],
+        &partial.unwrap(),
+        300,
+    );
+
     let tx: Result<Transaction, WalletError> =
         wallet.create_automatic_transaction(Address::Dave, 800, 100);
 
@@ -2,15 +2,15 @@
 
 use std::collections::*;
 use bonecoin_core::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
 
 /// Simple helper to initialize a wallet with just one account.
-fn wallet_with_alice() -> Wallet {
-    Wallet::new(vec![Address::Alice].into_iter())
+fn wallet_with_alice<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice].into_iter())
 }
 
-fn wallet_with_multiple_users() -> Wallet {
-    Wallet::new(vec![Address::Alice, Address::Bob, Address::Charlie].into_iter())
+fn wallet_with_multiple_users<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice, Address::Bob, Address::Charlie].into_iter())
 }
 
 /// Helper to create a simple and somewhat collision unlikely transaction to mark forks.
@@ -28,7 +28,7 @@ fn marker_tx() -> Transaction {
     }
 }
 
-fn initial_setup() -> (impl WalletApi, MockNode) {
+fn initial_setup<F: WalletFactory>() -> (impl WalletApi, MockNode) {
     // All coins will be valued the same in this test
     // We start by minting a coin to alice
     let tx_mint_1 = Transaction {
@@ -71,15 +71,14 @@ fn initial_setup() -> (impl WalletApi, MockNode) {
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint_1.clone()]);
     let _b2_id = node.add_block_as_best(b1_id, vec![tx_mint_2.clone()]);
 
-    let mut wallet = wallet_with_multiple_users();
+    let mut wallet = wallet_with_multiple_users::<F>();
     wallet.sync(&node);
 
     (wallet, node)
 }
 
-#[test]
-fn reorg_in_the_middle_with_tx_changes() {
-    let (mut wallet, mut node) = initial_setup();
+pub fn reorg_in_the_middle_with_tx_changes<F: WalletFactory>() {
+    let (mut wallet, mut node) = initial_setup::<F>();
 
     wallet.sync(&node);
     assert_eq!(wallet.total_assets_of(Address::Charlie), Ok(300));
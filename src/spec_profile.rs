@@ -0,0 +1,133 @@
+//! Readings of the parts of the spec that implementations legitimately disagree on.
+//!
+//! Automatic transactions leave three things open: whether burned value shows up as an output,
+//! which owned address gets the change, and which coins are spent. A [`SpecProfile`] pins down
+//! one answer to each. Wallets declare theirs as [`WalletFactory::PROFILE`] and the tests tagged
+//! `spec-ambiguous` then hold them to exactly that reading. Wallets that declare nothing get
+//! [`SpecProfile::DEFAULT`], which accepts every reading the suite accepted before profiles.
+//!
+//! [`WalletFactory::PROFILE`]: crate::harness::WalletFactory::PROFILE
+
+use bonecoin_core::*;
+
+/// Where the burned part of an automatic transaction goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Burn {
+    /// Burned value is simply left out of the outputs.
+    Implicit,
+    /// Burned value is paid to an output owned by this address.
+    Output(Address),
+}
+
+/// Which owned address receives the change of an automatic transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeAddress {
+    /// The first address the wallet was created with.
+    First,
+    /// Any address the wallet owns.
+    AnyOwned,
+}
+
+/// Which coins an automatic transaction spends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoinSelection {
+    /// The most valuable coins, and no more of them than needed.
+    LargestFirst,
+    /// The least valuable coins, and no more of them than needed.
+    SmallestFirst,
+    /// Any coins that cover the amount.
+    Any,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpecProfile {
+    pub burn: Burn,
+    pub change: ChangeAddress,
+    pub selection: CoinSelection,
+}
+
+impl SpecProfile {
+    /// What the suite asserts for wallets that do not declare a profile.
+    pub const DEFAULT: SpecProfile = SpecProfile {
+        burn: Burn::Implicit,
+        change: ChangeAddress::AnyOwned,
+        selection: CoinSelection::Any,
+    };
+
+    /// The reading of the [oracle](crate::oracle::OracleWallet).
+    pub const REFERENCE: SpecProfile = SpecProfile {
+        burn: Burn::Implicit,
+        change: ChangeAddress::First,
+        selection: CoinSelection::LargestFirst,
+    };
+
+    /// Outputs of an automatic transaction that needs no change.
+    pub fn outputs_without_change(&self, burn: u64) -> usize {
+        match self.burn {
+            Burn::Output(_) if burn > 0 => 2,
+            _ => 1,
+        }
+    }
+
+    /// The addresses allowed to receive change from a wallet created with `owned`, in order.
+    pub fn change_owners(&self, owned: &[Address]) -> Vec<Address> {
+        match self.change {
+            ChangeAddress::First => owned.iter().copied().take(1).collect(),
+            ChangeAddress::AnyOwned => owned.to_vec(),
+        }
+    }
+
+    /// Check that `tx`, built by `wallet` to spend `needed` (output value plus burn), picked its
+    /// inputs following the profile. `owned` are the addresses of the wallet.
+    pub fn assert_selection(
+        &self,
+        wallet: &impl WalletApi,
+        owned: &[Address],
+        tx: &Transaction,
+        needed: u64,
+    ) {
+        let mut selected = Vec::new();
+        let mut unselected = Vec::new();
+        for address in owned {
+            for (coin_id, value) in wallet.all_coins_of(*address).unwrap() {
+                if tx.inputs.iter().any(|input| input.coin_id == coin_id) {
+                    selected.push(value);
+                } else {
+                    unselected.push(value);
+                }
+            }
+        }
+        let total: u128 = selected.iter().map(|value| *value as u128).sum();
+
+        // The coin the strategy would have picked last, and its bound on the coins left out.
+        let (last, fits) = match self.selection {
+            CoinSelection::Any => return,
+            CoinSelection::LargestFirst => {
+                let last = selected.iter().copied().min().unwrap_or_default();
+                (last, unselected.iter().all(|value| *value <= last))
+            }
+            CoinSelection::SmallestFirst => {
+                let last = selected.iter().copied().max().unwrap_or_default();
+                (last, unselected.iter().all(|value| *value >= last))
+            }
+        };
+        assert!(
+            fits,
+            "{:?} spent {:?} but left {:?}",
+            self.selection, selected, unselected
+        );
+        assert!(
+            total - (last as u128) < needed as u128,
+            "{:?} spent {:?} for {}, more coins than needed",
+            self.selection,
+            selected,
+            needed
+        );
+    }
+}
+
+impl Default for SpecProfile {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
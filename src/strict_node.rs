@@ -0,0 +1,213 @@
+//! A node that only accepts valid blocks.
+//!
+//! `MockNode` takes any transactions, which is what most of the suite wants: wallets are not
+//! supposed to validate blocks, so tests freely mint with `Input::dummy()`, double spend or sign
+//! with `Signature::Invalid`. A [`StrictNode`] checks every block against the UTXO set of the
+//! chain it extends and refuses it with an [`InvalidBlock`] instead. Mining wallet-created
+//! transactions into one proves they are actually valid.
+//!
+//! Transactions without inputs are mints and always accepted, since coins have to come from
+//! somewhere.
+
+use crate::recording_node::{ChainRecord, RecordingNode};
+use bonecoin_core::*;
+use std::collections::*;
+use std::fmt;
+use std::ops::Deref;
+
+/// Why a block was refused. `tx` is the index of the offending transaction in the block.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidBlock {
+    UnknownParent(BlockId),
+    /// The input spends a coin that does not exist on the chain, or not yet.
+    MissingInput {
+        tx: usize,
+        coin_id: CoinId,
+    },
+    /// The input spends a coin already spent on the chain, in the block or in the transaction.
+    DoubleSpend {
+        tx: usize,
+        coin_id: CoinId,
+    },
+    /// The input is not signed as `Signature::Valid(owner)` of the coin it spends.
+    WrongSignature {
+        tx: usize,
+        coin_id: CoinId,
+        owner: Address,
+    },
+    /// The outputs are worth more than the inputs.
+    ValueCreated {
+        tx: usize,
+        inputs: u128,
+        outputs: u128,
+    },
+}
+
+impl fmt::Display for InvalidBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidBlock::UnknownParent(parent) => write!(f, "unknown parent block {:?}", parent),
+            InvalidBlock::MissingInput { tx, coin_id } => {
+                write!(f, "transaction {} spends unknown coin {:?}", tx, coin_id)
+            }
+            InvalidBlock::DoubleSpend { tx, coin_id } => {
+                write!(f, "transaction {} spends {:?} again", tx, coin_id)
+            }
+            InvalidBlock::WrongSignature { tx, coin_id, owner } => write!(
+                f,
+                "transaction {} spends {:?} without a valid signature of {:?}",
+                tx, coin_id, owner
+            ),
+            InvalidBlock::ValueCreated {
+                tx,
+                inputs,
+                outputs,
+            } => write!(
+                f,
+                "transaction {} spends {} but creates {}",
+                tx, inputs, outputs
+            ),
+        }
+    }
+}
+
+/// Coins of the chain ending at `tip`, and every coin ever spent on it.
+fn utxos_at(record: &ChainRecord, tip: BlockId) -> (HashMap<CoinId, Coin>, HashSet<CoinId>) {
+    let mut utxos = HashMap::new();
+    let mut spent = HashSet::new();
+    for block_id in record.chain_to(tip) {
+        let block = &record.blocks[&block_id];
+        for tx in &block.transactions {
+            for input in &tx.inputs {
+                utxos.remove(&input.coin_id);
+                spent.insert(input.coin_id);
+            }
+            for (index, coin) in tx.outputs.iter().enumerate() {
+                utxos.insert(tx.coin_id(block.height, index), coin.clone());
+            }
+        }
+    }
+    (utxos, spent)
+}
+
+/// Check `transactions` as the block at `height` on top of the given UTXO set.
+fn validate_block(
+    mut utxos: HashMap<CoinId, Coin>,
+    mut spent: HashSet<CoinId>,
+    height: u64,
+    transactions: &[Transaction],
+) -> Result<(), InvalidBlock> {
+    for (tx_index, tx) in transactions.iter().enumerate() {
+        let mut inputs: u128 = 0;
+        for input in &tx.inputs {
+            let coin_id = input.coin_id;
+            let coin = match utxos.remove(&coin_id) {
+                Some(coin) => coin,
+                None if spent.contains(&coin_id) => {
+                    return Err(InvalidBlock::DoubleSpend {
+                        tx: tx_index,
+                        coin_id,
+                    })
+                }
+                None => {
+                    return Err(InvalidBlock::MissingInput {
+                        tx: tx_index,
+                        coin_id,
+                    })
+                }
+            };
+            spent.insert(coin_id);
+            if input.signature != Signature::Valid(coin.owner) {
+                return Err(InvalidBlock::WrongSignature {
+                    tx: tx_index,
+                    coin_id,
+                    owner: coin.owner,
+                });
+            }
+            inputs += coin.value as u128;
+        }
+
+        let outputs: u128 = tx.outputs.iter().map(|coin| coin.value as u128).sum();
+        if !tx.inputs.is_empty() && outputs > inputs {
+            return Err(InvalidBlock::ValueCreated {
+                tx: tx_index,
+                inputs,
+                outputs,
+            });
+        }
+        for (index, coin) in tx.outputs.iter().enumerate() {
+            utxos.insert(tx.coin_id(height, index), coin.clone());
+        }
+    }
+    Ok(())
+}
+
+/// A [`RecordingNode`] that refuses invalid blocks.
+///
+/// Wallets sync with `wallet.sync(&node)` as usual. Blocks can only be added through the
+/// checked methods below; the node derefs to a `RecordingNode` for reading only.
+pub struct StrictNode {
+    node: RecordingNode,
+}
+
+impl StrictNode {
+    pub fn new() -> Self {
+        StrictNode {
+            node: RecordingNode::new(),
+        }
+    }
+
+    fn check(&self, parent: BlockId, transactions: &[Transaction]) -> Result<(), InvalidBlock> {
+        let record = self.node.record();
+        let record = record.borrow();
+        if parent != Block::genesis().id() && !record.blocks.contains_key(&parent) {
+            return Err(InvalidBlock::UnknownParent(parent));
+        }
+        let (utxos, spent) = utxos_at(&record, parent);
+        validate_block(utxos, spent, record.height_of(parent) + 1, transactions)
+    }
+
+    pub fn add_block(
+        &mut self,
+        parent: BlockId,
+        transactions: Vec<Transaction>,
+    ) -> Result<BlockId, InvalidBlock> {
+        self.check(parent, &transactions)?;
+        Ok(self.node.add_block(parent, transactions))
+    }
+
+    pub fn add_block_as_best(
+        &mut self,
+        parent: BlockId,
+        transactions: Vec<Transaction>,
+    ) -> Result<BlockId, InvalidBlock> {
+        self.check(parent, &transactions)?;
+        Ok(self.node.add_block_as_best(parent, transactions))
+    }
+
+    pub fn set_best(&mut self, block_id: BlockId) {
+        self.node.set_best(block_id);
+    }
+
+    /// Unspent coins of the best chain, whoever owns them.
+    pub fn utxos(&self) -> HashMap<CoinId, Coin> {
+        let record = self.node.record();
+        let best = record.borrow().best;
+        let (utxos, _) = utxos_at(&record.borrow(), best);
+        utxos
+    }
+}
+
+impl Default for StrictNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for StrictNode {
+    type Target = RecordingNode;
+
+    fn deref(&self) -> &RecordingNode {
+        &self.node
+    }
+}
@@ -0,0 +1,290 @@
+//! Transactions created by the wallet must be accepted by a node that validates blocks
+//!
+//! The last tests check that the node refuses each kind of invalid block, so that accepting a
+//! wallet's transactions means something.
+
+use crate::harness::WalletFactory;
+use crate::strict_node::{InvalidBlock, StrictNode};
+use bonecoin_core::*;
+
+/// Mine `transactions` on top of the best block of `node`, failing the test if it refuses them.
+fn mine(node: &mut StrictNode, transactions: Vec<Transaction>) -> BlockId {
+    let best = node.record().borrow().best;
+    node.add_block_as_best(best, transactions)
+        .unwrap_or_else(|invalid| panic!("the node refused the block: {}", invalid))
+}
+
+/// A block minting coins of `values` to Alice and Bob in turn.
+fn mint(node: &mut StrictNode, values: &[u64]) -> BlockId {
+    let outputs = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| Coin {
+            value: *value,
+            owner: if i % 2 == 0 {
+                Address::Alice
+            } else {
+                Address::Bob
+            },
+        })
+        .collect();
+    mine(
+        node,
+        vec![Transaction {
+            inputs: vec![],
+            outputs,
+        }],
+    )
+}
+
+pub fn automatic_transactions_are_minable<F: WalletFactory>() {
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
+    mint(&mut node, &[20, 12, 53, 7, 100, 1]);
+    wallet.sync(&node);
+
+    for (recipient, value, burn) in [
+        (Address::Charlie, 30, 3),
+        (Address::Alice, 11, 0),
+        (Address::Bob, 40, 1),
+        (Address::Dave, 50, 0),
+    ] {
+        let tx = wallet
+            .create_automatic_transaction(recipient, value, burn)
+            .unwrap();
+        mine(&mut node, vec![tx]);
+        wallet.sync(&node);
+    }
+    assert_eq!(wallet.net_worth(), 193 - 30 - 3 - 1 - 50);
+}
+
+pub fn manual_transactions_are_minable<F: WalletFactory>() {
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
+    mint(&mut node, &[20, 12, 53, 7]);
+    wallet.sync(&node);
+
+    // Spend every coin of Alice into one coin for Bob, then all of Bob's into Charlie and Alice.
+    let alice_coins = wallet.all_coins_of(Address::Alice).unwrap();
+    let tx = wallet
+        .create_manual_transaction(
+            alice_coins.iter().map(|(coin_id, _)| *coin_id).collect(),
+            vec![Coin {
+                value: 70,
+                owner: Address::Bob,
+            }],
+        )
+        .unwrap();
+    mine(&mut node, vec![tx]);
+    wallet.sync(&node);
+
+    let bob_coins = wallet.all_coins_of(Address::Bob).unwrap();
+    let tx = wallet
+        .create_manual_transaction(
+            bob_coins.iter().map(|(coin_id, _)| *coin_id).collect(),
+            vec![
+                Coin {
+                    value: 50,
+                    owner: Address::Charlie,
+                },
+                Coin {
+                    value: 30,
+                    owner: Address::Alice,
+                },
+            ],
+        )
+        .unwrap();
+    mine(&mut node, vec![tx]);
+    wallet.sync(&node);
+
+    assert_eq!(wallet.total_assets_of(Address::Alice), Ok(30));
+    assert_eq!(wallet.total_assets_of(Address::Bob), Ok(0));
+}
+
+pub fn transactions_after_a_reorg_are_minable<F: WalletFactory>() {
+    let mut node = StrictNode::new();
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    let b1 = mint(&mut node, &[40]);
+    mint(&mut node, &[60]);
+    wallet.sync(&node);
+
+    // The coin of 60 is reorged away by a longer fork with another coin of 25, so anything
+    // the wallet spends now has to come from the new chain.
+    node.add_block_as_best(
+        b1,
+        vec![Transaction {
+            inputs: vec![],
+            outputs: vec![Coin {
+                value: 25,
+                owner: Address::Alice,
+            }],
+        }],
+    )
+    .unwrap();
+    mine(&mut node, vec![]);
+    wallet.sync(&node);
+    assert_eq!(wallet.net_worth(), 65);
+
+    let tx = wallet
+        .create_automatic_transaction(Address::Bob, 60, 5)
+        .unwrap();
+    mine(&mut node, vec![tx]);
+    wallet.sync(&node);
+    assert_eq!(wallet.net_worth(), 0);
+}
+
+/// A node whose best block mints a coin of 20 to Alice, and the id of that coin.
+fn alices_coin() -> (StrictNode, CoinId) {
+    let mut node = StrictNode::new();
+    mint(&mut node, &[20]);
+    let coin_id = *node.utxos().keys().next().unwrap();
+    (node, coin_id)
+}
+
+/// A transaction spending `coin_ids`, signed by `signer`, into a coin of `value` for Charlie.
+fn spend(coin_ids: &[CoinId], signer: Address, value: u64) -> Transaction {
+    Transaction {
+        inputs: coin_ids
+            .iter()
+            .map(|coin_id| Input {
+                coin_id: *coin_id,
+                signature: Signature::Valid(signer),
+            })
+            .collect(),
+        outputs: vec![Coin {
+            value,
+            owner: Address::Charlie,
+        }],
+    }
+}
+
+/// Check that `node` refuses `transactions` on top of `parent` with `invalid`, and that its best
+/// block stays where it was.
+fn assert_refused(
+    node: &mut StrictNode,
+    parent: BlockId,
+    transactions: Vec<Transaction>,
+    invalid: InvalidBlock,
+) {
+    let best = node.record().borrow().best;
+    let height = node.record().borrow().height_of(best);
+
+    assert_eq!(node.add_block_as_best(parent, transactions), Err(invalid));
+    assert_eq!(
+        node.record().borrow().best,
+        best,
+        "the refused block became best"
+    );
+    assert_eq!(node.best_block_at_height(height), Some(best));
+    assert_eq!(node.best_block_at_height(height + 1), None);
+}
+
+pub fn blocks_on_unknown_parents_are_refused<F: WalletFactory>() {
+    let mut node = StrictNode::new();
+    mint(&mut node, &[20]);
+    let mut other = StrictNode::new();
+    let elsewhere = mint(&mut other, &[10]);
+
+    assert_refused(
+        &mut node,
+        elsewhere,
+        vec![],
+        InvalidBlock::UnknownParent(elsewhere),
+    );
+}
+
+pub fn spends_of_coins_off_the_chain_are_refused<F: WalletFactory>() {
+    let (mut node, _) = alices_coin();
+    let tip = node.record().borrow().best;
+    // A coin of 30 in a child of the best block that does not become best.
+    let fork_mint = Transaction {
+        inputs: vec![],
+        outputs: vec![Coin {
+            value: 30,
+            owner: Address::Alice,
+        }],
+    };
+    node.add_block(tip, vec![fork_mint.clone()]).unwrap();
+    let forked_coin = fork_mint.coin_id(2, 0);
+
+    assert_refused(
+        &mut node,
+        tip,
+        vec![spend(&[forked_coin], Address::Alice, 30)],
+        InvalidBlock::MissingInput {
+            tx: 0,
+            coin_id: forked_coin,
+        },
+    );
+}
+
+pub fn coins_spent_in_an_earlier_block_are_refused<F: WalletFactory>() {
+    let (mut node, coin_id) = alices_coin();
+    let tip = mine(&mut node, vec![spend(&[coin_id], Address::Alice, 20)]);
+
+    assert_refused(
+        &mut node,
+        tip,
+        vec![spend(&[coin_id], Address::Alice, 19)],
+        InvalidBlock::DoubleSpend { tx: 0, coin_id },
+    );
+}
+
+pub fn coins_spent_twice_in_a_block_are_refused<F: WalletFactory>() {
+    let (mut node, coin_id) = alices_coin();
+    let tip = node.record().borrow().best;
+
+    assert_refused(
+        &mut node,
+        tip,
+        vec![
+            spend(&[coin_id], Address::Alice, 20),
+            spend(&[coin_id], Address::Alice, 19),
+        ],
+        InvalidBlock::DoubleSpend { tx: 1, coin_id },
+    );
+}
+
+pub fn coins_spent_twice_in_a_transaction_are_refused<F: WalletFactory>() {
+    let (mut node, coin_id) = alices_coin();
+    let tip = node.record().borrow().best;
+
+    assert_refused(
+        &mut node,
+        tip,
+        vec![spend(&[coin_id, coin_id], Address::Alice, 20)],
+        InvalidBlock::DoubleSpend { tx: 0, coin_id },
+    );
+}
+
+pub fn spends_signed_by_someone_else_are_refused<F: WalletFactory>() {
+    let (mut node, coin_id) = alices_coin();
+    let tip = node.record().borrow().best;
+
+    assert_refused(
+        &mut node,
+        tip,
+        vec![spend(&[coin_id], Address::Bob, 20)],
+        InvalidBlock::WrongSignature {
+            tx: 0,
+            coin_id,
+            owner: Address::Alice,
+        },
+    );
+}
+
+pub fn transactions_creating_value_are_refused<F: WalletFactory>() {
+    let (mut node, coin_id) = alices_coin();
+    let tip = node.record().borrow().best;
+
+    assert_refused(
+        &mut node,
+        tip,
+        vec![spend(&[coin_id], Address::Alice, 21)],
+        InvalidBlock::ValueCreated {
+            tx: 0,
+            inputs: 20,
+            outputs: 21,
+        },
+    );
+}
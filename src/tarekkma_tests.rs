@@ -1,11 +1,10 @@
 use bonecoin_core::*;
 use std::collections::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
 
-#[test]
-fn total_assets_of_should_not_return_no_owned_address() {
+pub fn total_assets_of_should_not_return_no_owned_address<F: WalletFactory>() {
     // https://discord.com/channels/1219966585582653471/1246066143907811368/1246112529189568555
-    let wallet = Wallet::new(vec![].into_iter());
+    let wallet = F::new_wallet(vec![].into_iter());
 
     assert_eq!(
         wallet.total_assets_of(Address::Bob),
@@ -33,10 +32,9 @@ fn total_assets_of_should_not_return_no_owned_address() {
     );
 }
 
-#[test]
-fn spend_utxo_in_same_block() {
+pub fn spend_utxo_in_same_block<F: WalletFactory>() {
     let mut node = MockNode::new();
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
 
     let coin1 = Coin {
         value: 100,
@@ -99,10 +97,9 @@ fn spend_utxo_in_same_block() {
 }
 
 /// test sync performance with 1000 blocks
-#[test]
-fn perf_sync_100_blocks() {
+pub fn perf_sync_100_blocks<F: WalletFactory>() {
     let mut node = MockNode::new();
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
 
     let mut last_block = Block::genesis().id();
     let mut block75 = last_block;
@@ -162,10 +159,9 @@ fn perf_sync_100_blocks() {
 }
 
 /// test sync performance with 100 blocks
-#[test]
-fn pref_sync_1000_blocks() {
+pub fn pref_sync_1000_blocks<F: WalletFactory>() {
     let mut node = MockNode::new();
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
 
     let mut last_block = Block::genesis().id();
     let mut block850 = last_block;
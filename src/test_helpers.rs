@@ -0,0 +1,42 @@
+//! Small transaction and chain builders shared by several `*_tests` modules.
+
+use crate::recording_node::RecordingNode;
+use bonecoin_core::*;
+
+/// A transaction spending `inputs` into `outputs`. Signatures are left `Invalid` since
+/// `MockNode` never checks them.
+pub fn pay(inputs: &[CoinId], outputs: &[(u64, Address)]) -> Transaction {
+    Transaction {
+        inputs: inputs
+            .iter()
+            .map(|coin_id| Input {
+                coin_id: *coin_id,
+                signature: Signature::Invalid,
+            })
+            .collect(),
+        outputs: outputs
+            .iter()
+            .map(|(value, owner)| Coin {
+                value: *value,
+                owner: *owner,
+            })
+            .collect(),
+    }
+}
+
+/// Add `blocks` blocks on top of `parent`, each minting its height plus `offset` to Alice.
+pub fn mint_chain(node: &mut RecordingNode, parent: BlockId, blocks: u64, offset: u64) -> BlockId {
+    let mut parent = parent;
+    for _ in 0..blocks {
+        let height = node.record().borrow().height_of(parent) + 1;
+        let mint = Transaction {
+            inputs: vec![Input::dummy()],
+            outputs: vec![Coin {
+                value: height + offset,
+                owner: Address::Alice,
+            }],
+        };
+        parent = node.add_block_as_best(parent, vec![mint]);
+    }
+    parent
+}
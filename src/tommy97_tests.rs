@@ -1,9 +1,9 @@
 use bonecoin_core::*;
 use std::collections::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
 
-fn wallet_with_alice_and_bob() -> Wallet {
-    Wallet::new(vec![Address::Alice, Address::Bob].into_iter())
+fn wallet_with_alice_and_bob<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice, Address::Bob].into_iter())
 }
 
 fn marker_tx() -> Transaction {
@@ -16,11 +16,10 @@ fn marker_tx() -> Transaction {
     }
 }
 
-#[test]
-fn reorg_hard_test_hehe() {
+pub fn reorg_hard_test_hehe<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice_and_bob();
+    let mut wallet = wallet_with_alice_and_bob::<F>();
     // Mint some coins
     let coin1 = Coin {
         value: 100,
@@ -2,11 +2,11 @@
 
 use bonecoin_core::*;
 use std::collections::*;
-use utxo_wallet_assignment::Wallet;
+use crate::harness::WalletFactory;
 
 /// Simple helper to initialize a wallet with just one account.
-fn wallet_with_alice() -> Wallet {
-    Wallet::new(vec![Address::Alice].into_iter())
+fn wallet_with_alice<F: WalletFactory>() -> F::Wallet {
+    F::new_wallet(vec![Address::Alice].into_iter())
 }
 
 /// Helper to create a simple and somewhat collision unlikely transaction to mark forks.
@@ -24,9 +24,8 @@ fn marker_tx() -> Transaction {
     }
 }
 
-#[test]
-fn correct_genesis_values() {
-    let wallet = wallet_with_alice();
+pub fn correct_genesis_values<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
 
     assert_eq!(wallet.best_height(), 0);
     assert_eq!(wallet.best_hash(), Block::genesis().id());
@@ -35,9 +34,8 @@ fn correct_genesis_values() {
     assert_eq!(wallet.all_coins_of(Address::Alice).unwrap().len(), 0);
 }
 
-#[test]
-fn foreign_address_error() {
-    let wallet = wallet_with_alice();
+pub fn foreign_address_error<F: WalletFactory>() {
+    let wallet = wallet_with_alice::<F>();
 
     assert_eq!(
         wallet.total_assets_of(Address::Bob),
@@ -49,25 +47,23 @@ fn foreign_address_error() {
     );
 }
 
-#[test]
-fn sync_two_blocks() {
+pub fn sync_two_blocks<F: WalletFactory>() {
     // Build a mock node that has a simple two block chain
     let mut node = MockNode::new();
     let b1_id = node.add_block(Block::genesis().id(), vec![]);
     let b2_id = node.add_block_as_best(b1_id, vec![]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     assert_eq!(wallet.best_height(), 2);
     assert_eq!(wallet.best_hash(), b2_id);
 }
 
-#[test]
-fn short_reorg() {
+pub fn short_reorg<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 1
     let _old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -87,11 +83,10 @@ fn short_reorg() {
 //    G
 //        \
 //          C2            -  C3             -       C4          -        C5 (new wallet state)
-#[test]
-fn deep_reorg() {
+pub fn deep_reorg<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -114,11 +109,10 @@ fn deep_reorg() {
 //                  /
 //              G
 //                  \   B2      (should reorg the chain here)
-#[test]
-fn reorg_to_shorter_chain() {
+pub fn reorg_to_shorter_chain<F: WalletFactory>() {
     // Create node and wallet
     let mut node = MockNode::new();
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
 
     // Sync a chain to height 3
     let old_b1_id = node.add_block_as_best(Block::genesis().id(), vec![]);
@@ -135,8 +129,7 @@ fn reorg_to_shorter_chain() {
     assert_eq!(wallet.best_hash(), b2_id);
 }
 
-#[test]
-fn tracks_single_utxo() {
+pub fn tracks_single_utxo<F: WalletFactory>() {
     // We have a single transaction that consumes some made up input
     // and creates a single output to alice.
     const COIN_VALUE: u64 = 100;
@@ -154,7 +147,7 @@ fn tracks_single_utxo() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx]);
 
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -167,8 +160,7 @@ fn tracks_single_utxo() {
     assert_eq!(wallet.coin_details(&coin_id), Ok(coin));
 }
 
-#[test]
-fn consumes_own_utxo() {
+pub fn consumes_own_utxo<F: WalletFactory>() {
     // All coins will be valued the same in this test
     const COIN_VALUE: u64 = 100;
 
@@ -201,7 +193,7 @@ fn consumes_own_utxo() {
     let mut node = MockNode::new();
     let b1_id = node.add_block_as_best(Block::genesis().id(), vec![tx_mint]);
     let _b2_id = node.add_block_as_best(b1_id, vec![tx_burn]);
-    let mut wallet = wallet_with_alice();
+    let mut wallet = wallet_with_alice::<F>();
     wallet.sync(&node);
 
     // Make sure the UTXO is consumed
@@ -233,8 +225,7 @@ fn consumes_own_utxo() {
 
 // Memory performance test to make sure they aren't just keeping a snapshot of the entire UTXO set at every height.
 // Track UTXOs from two transactions in a single block
-#[test]
-fn extra_track_two_utxo() {
+pub fn extra_track_two_utxo<F: WalletFactory>() {
     // TODO: might be the easiest scenario
     const COIN_0_VALUE: u64 = 100;
     const COIN_1_VALUE: u64 = 200;
@@ -271,7 +262,7 @@ fn extra_track_two_utxo() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx, tx_1, tx_2]);
 
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -296,8 +287,7 @@ fn extra_track_two_utxo() {
 }
 
 // Track UTXOs to multiple users
-#[test]
-fn extra_utxo_to_multiple_users() {
+pub fn extra_utxo_to_multiple_users<F: WalletFactory>() {
     // TODO: might be the easiest scenario
     const COIN_0_VALUE: u64 = 100;
     const COIN_1_VALUE: u64 = 200;
@@ -324,7 +314,7 @@ fn extra_utxo_to_multiple_users() {
     let mut node = MockNode::new();
     node.add_block_as_best(Block::genesis().id(), vec![tx, tx_1]);
 
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     wallet.sync(&node);
 
     // Check that the accounting is right
@@ -343,9 +333,8 @@ fn extra_utxo_to_multiple_users() {
     assert_eq!(wallet.coin_details(&coin_id_1), Ok(coin_1));
 }
 
-#[test]
-fn extra_best_height_and_hash() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_best_height_and_hash<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     wallet.sync(&node);
@@ -377,9 +366,8 @@ fn extra_best_height_and_hash() {
     assert_eq!(wallet.best_hash(), block_3);
 }
 
-#[test]
-fn extra_best_height_and_hash_fork1() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_best_height_and_hash_fork1<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     // Chain 1
@@ -436,9 +424,8 @@ fn extra_best_height_and_hash_fork1() {
     assert_eq!(wallet.best_hash(), block_4);
 }
 
-#[test]
-fn extra_best_height_and_hash_fork2() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_best_height_and_hash_fork2<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     // Chain 1
@@ -477,9 +464,8 @@ fn extra_best_height_and_hash_fork2() {
     assert_eq!(wallet.best_hash(), block_2);
 }
 
-#[test]
-fn extra_total_assets_of_simple() {
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+pub fn extra_total_assets_of_simple<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     let mut node = MockNode::new();
 
     let tx_1a = Transaction {
@@ -563,9 +549,8 @@ fn extra_total_assets_of_simple() {
     assert_eq!(wallet.total_assets_of(Address::Bob), Ok(15 + 7 + 27 + 63));
 }
 
-#[test]
-fn extra_total_assets_of_fork() {
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+pub fn extra_total_assets_of_fork<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     let mut node = MockNode::new();
 
     // First chain
@@ -676,9 +661,8 @@ fn extra_total_assets_of_fork() {
     assert_eq!(wallet.total_assets_of(Address::Bob), Ok(15 + 7 + 27 + 111));
 }
 
-#[test]
-fn extra_total_assets_of_overflow() {
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+pub fn extra_total_assets_of_overflow<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     let mut node = MockNode::new();
 
     // First chain
@@ -704,23 +688,20 @@ fn extra_total_assets_of_overflow() {
     assert_eq!(wallet.net_worth(), u64::MAX);
 }
 
-#[test]
-fn extra_total_assets_of_empty() {
-    let wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_total_assets_of_empty<F: WalletFactory>() {
+    let wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let total_assets = wallet.total_assets_of(Address::Alice);
     assert_eq!(total_assets, Ok(0));
 }
 
-#[test]
-fn extra_total_assets_of_foreign_address() {
-    let wallet = Wallet::new(vec![].into_iter());
+pub fn extra_total_assets_of_foreign_address<F: WalletFactory>() {
+    let wallet = F::new_wallet(vec![].into_iter());
     let total_assets = wallet.total_assets_of(Address::Alice);
     assert_eq!(total_assets, Err(WalletError::ForeignAddress));
 }
 
-#[test]
-fn extra_net_worth_simple() {
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+pub fn extra_net_worth_simple<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     let mut node = MockNode::new();
 
     let tx_1a = Transaction {
@@ -803,9 +784,8 @@ fn extra_net_worth_simple() {
     );
 }
 
-#[test]
-fn extra_net_worth_fork() {
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+pub fn extra_net_worth_fork<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     let mut node = MockNode::new();
 
     // First chain
@@ -914,9 +894,8 @@ fn extra_net_worth_fork() {
     );
 }
 
-#[test]
-fn extra_net_worth_overflow() {
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+pub fn extra_net_worth_overflow<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     let mut node = MockNode::new();
 
     // First chain
@@ -942,15 +921,13 @@ fn extra_net_worth_overflow() {
     assert_eq!(wallet.net_worth(), u64::MAX);
 }
 
-#[test]
-fn extra_total_net_worth_empty() {
-    let wallet = Wallet::new(vec![].into_iter());
+pub fn extra_total_net_worth_empty<F: WalletFactory>() {
+    let wallet = F::new_wallet(vec![].into_iter());
     assert_eq!(wallet.net_worth(), 0);
 }
 
-#[test]
-fn extra_all_coins_of_simple() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_all_coins_of_simple<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
     let mut all_coins_alice = HashSet::new();
 
@@ -1030,9 +1007,8 @@ fn extra_all_coins_of_simple() {
     assert_eq!(actual, expected);
 }
 
-#[test]
-fn extra_all_coins_of_fork() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_all_coins_of_fork<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
     let mut all_coins_alice = HashSet::new();
     let mut all_coins_alice_fork = HashSet::new();
@@ -1167,9 +1143,8 @@ fn extra_all_coins_of_fork() {
     assert_eq!(actual, expected);
 }
 
-#[test]
-fn extra_all_coins_spend() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_all_coins_spend<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
     let mut all_coins_alice = HashSet::new();
 
@@ -1213,18 +1188,16 @@ fn extra_all_coins_spend() {
     assert_eq!(actual, expected);
 }
 
-#[test]
-fn extra_all_coin_foreign_address() {
-    let wallet = Wallet::new(vec![].into_iter());
+pub fn extra_all_coin_foreign_address<F: WalletFactory>() {
+    let wallet = F::new_wallet(vec![].into_iter());
     assert_eq!(
         wallet.all_coins_of(Address::Alice),
         Err(WalletError::ForeignAddress)
     );
 }
 
-#[test]
-fn extra_coin_details_simple() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_coin_details_simple<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     let coin_1 = Coin {
@@ -1299,9 +1272,8 @@ fn extra_coin_details_simple() {
     assert_eq!(wallet.coin_details(&coin_id_6), Ok(coin_6));
 }
 
-#[test]
-fn extra_coin_details_fork() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_coin_details_fork<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     // Chain 1
@@ -1422,9 +1394,8 @@ fn extra_coin_details_fork() {
     assert_eq!(wallet.coin_details(&coin_id_8), Ok(coin_8));
 }
 
-#[test]
-fn extra_coin_details_spend() {
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+pub fn extra_coin_details_spend<F: WalletFactory>() {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     let coin_1 = Coin {
@@ -1463,8 +1434,7 @@ fn extra_coin_details_spend() {
 }
 
 // Create manual transaction
-#[test]
-fn extra_create_manual_transaction() {
+pub fn extra_create_manual_transaction<F: WalletFactory>() {
     const COIN_0_VALUE: u64 = 200;
     const COIN_1_VALUE: u64 = 100;
 
@@ -1485,7 +1455,7 @@ fn extra_create_manual_transaction() {
 
     let coin_id = tx.coin_id(1, 0);
 
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     // Create a minimal chain to contain this transaction and sync it
     let mut node = MockNode::new();
     let node_1 = node.add_block_as_best(Block::genesis().id(), vec![tx]);
@@ -1507,10 +1477,9 @@ fn extra_create_manual_transaction() {
     assert_eq!(wallet.net_worth(), COIN_1_VALUE);
 }
 
-#[test]
-fn extra_automatic_transaction_simple() {
+pub fn extra_automatic_transaction_simple<F: WalletFactory>() {
     // Alice is the sending address in this case and Bob receives the automatic tx.
-    let mut wallet = Wallet::new(vec![Address::Alice, Address::Bob].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice, Address::Bob].into_iter());
     let mut node = MockNode::new();
 
     // Alice starts with 85 bones.
@@ -1548,19 +1517,23 @@ fn extra_automatic_transaction_simple() {
     // Because the specs only specify to "sends the remaining amount back to an address" in the
     // wallet, this means either Alice or Bob could receive the tip. This is especially
     // pertinent if you are using a HashMap or HashSet to store your address, as these have
-    // non-deterministic ordering over multiple tests.
+    // non-deterministic ordering over multiple tests. The wallet's profile says which.
     let assets_alice = wallet.total_assets_of(Address::Alice);
     let assets_bob = wallet.total_assets_of(Address::Bob);
+    let change_owners = F::PROFILE.change_owners(&[Address::Alice, Address::Bob]);
     assert!(
-        (assets_bob == Ok(80) && assets_alice == Ok(2))
-            || (assets_bob == Ok(82) && assets_alice == Ok(0))
+        (change_owners.contains(&Address::Alice)
+            && assets_bob == Ok(80)
+            && assets_alice == Ok(2))
+            || (change_owners.contains(&Address::Bob)
+                && assets_bob == Ok(82)
+                && assets_alice == Ok(0))
     );
 }
 
-#[test]
-fn extra_automatic_transaction_invalid() {
+pub fn extra_automatic_transaction_invalid<F: WalletFactory>() {
     // Alice is the sending address.
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     // Alice starts with 85 bones.
@@ -1592,20 +1565,18 @@ fn extra_automatic_transaction_invalid() {
     assert_eq!(tx_auto, Err(WalletError::InsufficientFunds));
 }
 
-#[test]
-fn extra_automatic_transaction_no_owned_address() {
+pub fn extra_automatic_transaction_no_owned_address<F: WalletFactory>() {
     // Alice is the sending address.
-    let wallet = Wallet::new(vec![].into_iter());
+    let wallet = F::new_wallet(vec![].into_iter());
 
     // This should fail because our wallet was not given any address to own on initialisation.
     let tx_auto = wallet.create_automatic_transaction(Address::Bob, 100, 3);
     assert_eq!(tx_auto, Err(WalletError::NoOwnedAddresses));
 }
 
-#[test]
-fn extra_automatic_transaction_zero_coin_value() {
+pub fn extra_automatic_transaction_zero_coin_value<F: WalletFactory>() {
     // Alice is the sending address.
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     // Alice starts with 85 bones.
@@ -1638,13 +1609,12 @@ fn extra_automatic_transaction_zero_coin_value() {
     assert_eq!(tx_auto, Err(WalletError::ZeroCoinValue));
 }
 
-#[test]
 // WARN: this test is by all regards overkill and should most likely not be part of the actual
 // testing battery. Still if you feel paranoid (like me) and do not want to assume anything, this
 // can be a fun thing to puzzle out :)
-fn extra_automatic_transaction_overflow() {
+pub fn extra_automatic_transaction_overflow<F: WalletFactory>() {
     // Alice is the sending address.
-    let mut wallet = Wallet::new(vec![Address::Alice].into_iter());
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
     let mut node = MockNode::new();
 
     let tx_0 = Transaction {
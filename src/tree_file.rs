@@ -0,0 +1,361 @@
+//! Block trees saved to text files, every fork included.
+//!
+//! `MockNode` does not hand out its blocks, so trees are exported from the record of a
+//! [`RecordingNode`]. Importing one adds the same blocks with the same parents and transactions
+//! to a fresh `RecordingNode`, so every block gets the id `Block::id()` gave it originally.
+//!
+//! ```text
+//! bonecoin-tree 1
+//! b 0
+//! t d > 100:Alice 20:Custom(7)
+//! b 1
+//! t 1.0.0 > 60:Bob 30:Alice
+//! t 2.0.1! > 30:Charlie
+//! b 0
+//! best 2
+//! ```
+//!
+//! The first line names the format and its version. Each `b <parent>` line adds the next block,
+//! numbered from 1 in file order, on top of block `<parent>`; 0 is genesis and parents always
+//! come first. The `t` lines below it are its transactions: inputs, `>`, then outputs as
+//! `<value>:<owner>`. An input is `d` for `Input::dummy()` or `<block>.<tx>.<output>` for the coin
+//! created by that output, with `tx` counted from 0 in its block. It is signed
+//! `Signature::Valid` by the coin's owner unless it ends in `!` for `Signature::Invalid` or
+//! `@<address>` for a valid signature by someone else. Coins that no block above creates, like
+//! the ones of another fork or ones never created at all, are written `#<coin id>` with the id in
+//! its `Debug` form instead. The file does not know their owner, so their signature is always
+//! written. The last line is the best block.
+
+use crate::recording_node::{ChainRecord, RecordingNode};
+use crate::scenario_file::try_parse_address;
+use bonecoin_core::*;
+use std::collections::*;
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The first line of every tree file this version writes.
+pub const TREE_HEADER: &str = "bonecoin-tree 1";
+
+/// Directory of the tree files checked in as regression fixtures.
+pub const TREE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/trees");
+
+#[derive(Debug)]
+pub enum TreeFileError {
+    /// Line `line`, counted from 1, is not valid.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for TreeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TreeFileError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+/// Write `record` in the tree file format.
+pub fn export_tree(record: &ChainRecord) -> String {
+    let blocks = write_order(record);
+
+    // Coins are only known once written, so a file never refers to a line below.
+    let mut numbers: HashMap<BlockId, usize> = HashMap::from([(Block::genesis().id(), 0)]);
+    let mut coins: HashMap<CoinId, (String, Address)> = HashMap::new();
+    let dummy = Input::dummy();
+    let mut out = String::new();
+    writeln!(out, "{}", TREE_HEADER).unwrap();
+    for (index, block_id) in blocks.iter().enumerate() {
+        let block = &record.blocks[block_id];
+        numbers.insert(*block_id, index + 1);
+        writeln!(out, "b {}", numbers[&block.parent]).unwrap();
+        for (tx_index, tx) in block.transactions.iter().enumerate() {
+            out.push('t');
+            for input in &tx.inputs {
+                let (coin, default) = if input.coin_id == dummy.coin_id {
+                    ("d".to_string(), Some(dummy.signature.clone()))
+                } else if let Some((coin, owner)) = coins.get(&input.coin_id) {
+                    (coin.clone(), Some(Signature::Valid(*owner)))
+                } else {
+                    (format!("#{:?}", input.coin_id), None)
+                };
+                write!(out, " {}", coin).unwrap();
+                if Some(&input.signature) != default.as_ref() {
+                    match &input.signature {
+                        Signature::Valid(signer) => write!(out, "@{:?}", signer).unwrap(),
+                        Signature::Invalid => out.push('!'),
+                    }
+                }
+            }
+            out.push_str(" >");
+            for coin in &tx.outputs {
+                write!(out, " {}:{:?}", coin.value, coin.owner).unwrap();
+            }
+            out.push('\n');
+
+            for (output, coin) in tx.outputs.iter().enumerate() {
+                let reference = format!("{}.{}.{}", index + 1, tx_index, output);
+                coins
+                    .entry(tx.coin_id(block.height, output))
+                    .or_insert((reference, coin.owner));
+            }
+        }
+    }
+    writeln!(out, "best {}", numbers[&record.best]).unwrap();
+    out
+}
+
+/// Every block after its parent and after the blocks creating the coins it spends. Among the
+/// blocks that can go next the lowest comes first, then the smallest id, so a tree always gives
+/// the same file. Blocks spending each other's coins across forks are left for last, in the same
+/// order, and spend those coins by id.
+fn write_order(record: &ChainRecord) -> Vec<BlockId> {
+    let key = |block_id: BlockId| (record.height_of(block_id), format!("{:?}", block_id));
+    let mut creators: HashMap<CoinId, BlockId> = HashMap::new();
+    for (block_id, block) in &record.blocks {
+        for tx in &block.transactions {
+            for output in 0..tx.outputs.len() {
+                let creator = creators
+                    .entry(tx.coin_id(block.height, output))
+                    .or_insert(*block_id);
+                if key(*block_id) < key(*creator) {
+                    *creator = *block_id;
+                }
+            }
+        }
+    }
+
+    let mut waiting: HashMap<BlockId, usize> = HashMap::new();
+    let mut unblocks: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+    let mut ready = BTreeMap::new();
+    for (block_id, block) in &record.blocks {
+        let mut needs: HashSet<BlockId> = block
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.inputs)
+            .filter_map(|input| creators.get(&input.coin_id).copied())
+            .collect();
+        needs.insert(block.parent);
+        needs.remove(&Block::genesis().id());
+        needs.remove(block_id);
+        if needs.is_empty() {
+            ready.insert(key(*block_id), *block_id);
+        }
+        waiting.insert(*block_id, needs.len());
+        for need in needs {
+            unblocks.entry(need).or_default().push(*block_id);
+        }
+    }
+
+    let mut order = Vec::new();
+    while let Some((_, block_id)) = ready.pop_first() {
+        order.push(block_id);
+        waiting.remove(&block_id);
+        for next in unblocks.remove(&block_id).unwrap_or_default() {
+            let count = waiting.get_mut(&next).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.insert(key(next), next);
+            }
+        }
+    }
+    let mut stuck: Vec<BlockId> = waiting.into_keys().collect();
+    stuck.sort_by_cached_key(|block_id| key(*block_id));
+    order.extend(stuck);
+    order
+}
+
+/// A block being read: its parent number, height and transactions.
+struct ParsedBlock {
+    parent: usize,
+    height: u64,
+    transactions: Vec<Transaction>,
+}
+
+/// Read a tree file into a fresh node. Returns the node and the ids of the blocks in file
+/// order, so `ids[n - 1]` is block `n`.
+pub fn import_tree(text: &str) -> Result<(RecordingNode, Vec<BlockId>), TreeFileError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()));
+    match lines.next() {
+        Some((_, TREE_HEADER)) => {}
+        Some((line, header)) => {
+            return Err(TreeFileError::Parse {
+                line,
+                message: format!("expected `{}`, found `{}`", TREE_HEADER, header),
+            })
+        }
+        None => {
+            return Err(TreeFileError::Parse {
+                line: 1,
+                message: "empty file".to_string(),
+            })
+        }
+    }
+
+    let mut blocks: Vec<ParsedBlock> = Vec::new();
+    let mut best = None;
+    for (line, text) in lines {
+        let error = |message: String| TreeFileError::Parse { line, message };
+        if text.is_empty() {
+            continue;
+        }
+        if best.is_some() {
+            return Err(error("nothing may follow the best block".to_string()));
+        }
+        let (kind, rest) = text.split_once(' ').unwrap_or((text, ""));
+        match kind {
+            "b" => {
+                let parent = parse_block_number(rest, blocks.len()).map_err(error)?;
+                let height = if parent == 0 {
+                    1
+                } else {
+                    blocks[parent - 1].height + 1
+                };
+                blocks.push(ParsedBlock {
+                    parent,
+                    height,
+                    transactions: Vec::new(),
+                });
+            }
+            "t" => {
+                if blocks.is_empty() {
+                    return Err(error("transaction before the first block".to_string()));
+                }
+                let tx = parse_tx(rest, &blocks).map_err(error)?;
+                blocks.last_mut().unwrap().transactions.push(tx);
+            }
+            "best" => best = Some(parse_block_number(rest, blocks.len()).map_err(error)?),
+            _ => return Err(error(format!("unknown line `{}`", text))),
+        }
+    }
+    let best = best.ok_or(TreeFileError::Parse {
+        line: text.lines().count(),
+        message: "missing best block".to_string(),
+    })?;
+
+    let mut node = RecordingNode::new();
+    let mut ids = Vec::new();
+    for block in blocks {
+        let parent = if block.parent == 0 {
+            Block::genesis().id()
+        } else {
+            ids[block.parent - 1]
+        };
+        ids.push(node.add_block(parent, block.transactions));
+    }
+    node.set_best(if best == 0 {
+        Block::genesis().id()
+    } else {
+        ids[best - 1]
+    });
+    Ok((node, ids))
+}
+
+/// A block number no higher than `last`.
+fn parse_block_number(text: &str, last: usize) -> Result<usize, String> {
+    match text.trim().parse() {
+        Ok(number) if number <= last => Ok(number),
+        Ok(number) => Err(format!("block {} does not exist yet", number)),
+        Err(_) => Err(format!("`{}` is not a block number", text)),
+    }
+}
+
+fn parse_address(text: &str) -> Result<Address, String> {
+    try_parse_address(text).ok_or_else(|| format!("unknown address `{}`", text))
+}
+
+/// A transaction of the last block in `blocks`.
+fn parse_tx(text: &str, blocks: &[ParsedBlock]) -> Result<Transaction, String> {
+    let (inputs, outputs) = text
+        .split_once('>')
+        .ok_or_else(|| "missing `>` between inputs and outputs".to_string())?;
+
+    let inputs = inputs
+        .split_whitespace()
+        .map(|input| parse_input(input, blocks))
+        .collect::<Result<_, _>>()?;
+    let outputs = outputs
+        .split_whitespace()
+        .map(|output| {
+            let (value, owner) = output
+                .split_once(':')
+                .ok_or_else(|| format!("`{}` is not `<value>:<owner>`", output))?;
+            Ok(Coin {
+                value: value
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a coin value", value))?,
+                owner: parse_address(owner)?,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(Transaction { inputs, outputs })
+}
+
+fn parse_input(text: &str, blocks: &[ParsedBlock]) -> Result<Input, String> {
+    let (coin, signature) = match text.find(['!', '@']) {
+        Some(at) => text.split_at(at),
+        None => (text, ""),
+    };
+
+    let (coin_id, owner_signature) = if coin == "d" {
+        let dummy = Input::dummy();
+        (dummy.coin_id, Some(dummy.signature))
+    } else if let Some(coin_id) = coin.strip_prefix('#') {
+        let coin_id = coin_id
+            .parse()
+            .map_err(|_| format!("`{}` is not a coin id", coin_id))?;
+        (coin_id, None)
+    } else {
+        let numbers: Vec<usize> = coin
+            .split('.')
+            .map(|number| number.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| {
+                format!(
+                    "`{}` is not `d`, `<block>.<tx>.<output>` or `#<coin id>`",
+                    coin
+                )
+            })?;
+        let [block, tx, output] = numbers[..] else {
+            return Err(format!(
+                "`{}` is not `d`, `<block>.<tx>.<output>` or `#<coin id>`",
+                coin
+            ));
+        };
+        let source = block
+            .checked_sub(1)
+            .and_then(|block| blocks.get(block))
+            .ok_or_else(|| format!("{} spends from a block that does not exist yet", coin))?;
+        let created = source
+            .transactions
+            .get(tx)
+            .and_then(|tx| Some((tx, tx.outputs.get(output)?)))
+            .ok_or_else(|| format!("{} spends a coin that is not created", coin))?;
+        let (created_by, coin) = created;
+        (
+            created_by.coin_id(source.height, output),
+            Some(Signature::Valid(coin.owner)),
+        )
+    };
+
+    let signature = match signature {
+        "" => owner_signature
+            .ok_or_else(|| format!("`{}` needs a signature, `!` or `@<address>`", coin))?,
+        "!" => Signature::Invalid,
+        signer => Signature::Valid(parse_address(&signer[1..])?),
+    };
+    Ok(Input { coin_id, signature })
+}
+
+/// Every tree file in `dir`, sorted by name.
+pub fn tree_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.expect("unreadable directory entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "tree"))
+        .collect();
+    files.sort();
+    files
+}
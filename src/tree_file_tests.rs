@@ -0,0 +1,151 @@
+//! Block trees written to and read from files, see `tree_file.rs` for the format
+
+use crate::differential::{DifferentialWallet, WalletState};
+use crate::harness::WalletFactory;
+use crate::random_scenario::{for_each_seed, RandomConfig, Scenario, OWNED, RECIPIENTS};
+use crate::recording_node::RecordingNode;
+use crate::tree_file::{self, export_tree, import_tree, TREE_DIR};
+use bonecoin_core::*;
+use std::collections::*;
+use std::fs;
+use std::path::Path;
+
+pub fn exported_trees_keep_their_block_ids<F: WalletFactory>() {
+    let config = RandomConfig::reorg_heavy();
+    for_each_seed(20, |seed| {
+        let scenario = Scenario::generate(seed, &config);
+        let mut node = RecordingNode::new();
+        let chain = scenario.replay(&mut node, |_| {});
+
+        let text = export_tree(&node.record().borrow());
+        let (imported, ids) = import_tree(&text).unwrap_or_else(|e| panic!("seed {}: {}", seed, e));
+        assert_eq!(
+            ids.iter().copied().collect::<HashSet<_>>(),
+            chain.ids.iter().copied().collect::<HashSet<_>>(),
+            "seed {}: imported block ids differ",
+            seed
+        );
+        assert_eq!(
+            imported.record().borrow().best,
+            node.record().borrow().best,
+            "seed {}: imported best block differs",
+            seed
+        );
+        assert_eq!(
+            export_tree(&imported.record().borrow()),
+            text,
+            "seed {}: exporting the imported tree gives another file",
+            seed
+        );
+
+        let mut original = F::new_wallet(OWNED.into_iter());
+        original.sync(&node);
+        let mut wallet = F::new_wallet(OWNED.into_iter());
+        wallet.sync(&imported);
+        assert_eq!(
+            WalletState::of(&wallet, &RECIPIENTS),
+            WalletState::of(&original, &RECIPIENTS),
+            "seed {}: wallet on the imported tree differs",
+            seed
+        );
+    });
+}
+
+/// A block spending coins no block of the tree creates is written with their ids, and its
+/// signatures are kept.
+pub fn spends_of_coins_never_created_are_exported<F: WalletFactory>() {
+    let never_mined = Transaction {
+        inputs: vec![Input::dummy()],
+        outputs: vec![Coin {
+            value: 5,
+            owner: Address::Alice,
+        }],
+    };
+    let mut node = RecordingNode::new();
+    let mint = Transaction {
+        inputs: vec![Input::dummy()],
+        outputs: vec![Coin {
+            value: 10,
+            owner: Address::Alice,
+        }],
+    };
+    let b1 = node.add_block_as_best(Block::genesis().id(), vec![mint.clone()]);
+    let spend = Transaction {
+        inputs: vec![
+            Input {
+                coin_id: mint.coin_id(1, 0),
+                signature: Signature::Valid(Address::Alice),
+            },
+            Input {
+                coin_id: never_mined.coin_id(1, 0),
+                signature: Signature::Valid(Address::Alice),
+            },
+            Input {
+                coin_id: never_mined.coin_id(2, 0),
+                signature: Signature::Invalid,
+            },
+        ],
+        outputs: vec![Coin {
+            value: 12,
+            owner: Address::Bob,
+        }],
+    };
+    let b2 = node.add_block_as_best(b1, vec![spend]);
+
+    let text = export_tree(&node.record().borrow());
+    assert!(
+        text.contains(&format!("#{:?}@Alice", never_mined.coin_id(1, 0)))
+            && text.contains(&format!("#{:?}!", never_mined.coin_id(2, 0))),
+        "coins never created are not written by id:\n{}",
+        text
+    );
+    let (imported, ids) = import_tree(&text).unwrap();
+    assert_eq!(ids, vec![b1, b2]);
+    assert_eq!(imported.record().borrow().best, b2);
+    assert_eq!(export_tree(&imported.record().borrow()), text);
+
+    let mut original = F::new_wallet(OWNED.into_iter());
+    original.sync(&node);
+    let mut wallet = F::new_wallet(OWNED.into_iter());
+    wallet.sync(&imported);
+    assert_eq!(
+        WalletState::of(&wallet, &RECIPIENTS),
+        WalletState::of(&original, &RECIPIENTS)
+    );
+}
+
+/// Every tree under [`TREE_DIR`], checked against the oracle at the best block, at the highest
+/// leaf of another branch and back.
+pub fn tree_files<F: WalletFactory>() {
+    for path in tree_file::tree_files(Path::new(TREE_DIR)) {
+        let text = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+        let (mut node, _) =
+            import_tree(&text).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+        let record = node.record();
+        let best = record.borrow().best;
+        let other_leaf = {
+            let record = record.borrow();
+            let best_chain: HashSet<BlockId> = record.chain_to(best).into_iter().collect();
+            let parents: HashSet<BlockId> =
+                record.blocks.values().map(|block| block.parent).collect();
+            record
+                .blocks
+                .keys()
+                .filter(|block_id| !parents.contains(block_id) && !best_chain.contains(block_id))
+                .max_by_key(|block_id| (record.height_of(**block_id), format!("{:?}", block_id)))
+                .copied()
+        };
+
+        let wallet = F::new_wallet(OWNED.into_iter());
+        let mut wallet = DifferentialWallet::new(wallet, OWNED.to_vec(), &node);
+        wallet.sync(&node);
+        if let Some(leaf) = other_leaf {
+            node.set_best(leaf);
+            wallet.sync(&node);
+            node.set_best(best);
+            wallet.sync(&node);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Structural checks on the transactions a wallet creates.
+//!
+//! Tests mostly look at how many inputs and outputs a created transaction has. The harness runs
+//! every test against a [`ValidatedWallet`] instead, which checks each transaction the wallet
+//! returns from `create_manual_transaction` or `create_automatic_transaction` with
+//! [`validate_transaction`] and panics on the first violation. Errors returned by the wallet are
+//! passed through untouched.
+//!
+//! Inputs are checked against what the wallet itself reports through `coin_details`, since that is
+//! the only view of the chain the wallet has when it builds the transaction.
+
+use crate::harness::WalletFactory;
+use crate::spec_profile::{Burn, SpecProfile};
+use bonecoin_core::*;
+use std::collections::*;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// What the wallet was asked to build.
+#[derive(Clone, Copy, Debug)]
+pub enum TxRequest<'a> {
+    Manual {
+        inputs: &'a [CoinId],
+        outputs: &'a [Coin],
+    },
+    Automatic {
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    },
+}
+
+/// The first thing found wrong with a created transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TxViolation {
+    /// An input is not an unspent coin of one of the wallet's addresses.
+    UnknownInput(CoinId),
+    DuplicateInput(CoinId),
+    /// An input is not signed by the owner of the coin it spends.
+    WrongSignature {
+        coin_id: CoinId,
+        owner: Address,
+    },
+    ZeroValueOutput(usize),
+    /// Inputs do not add up to the outputs plus the burn.
+    ValueMismatch {
+        inputs: u128,
+        outputs: u128,
+        burn: u128,
+    },
+    MissingRecipientOutput {
+        recipient: Address,
+        value: u64,
+    },
+    MissingBurnOutput {
+        address: Address,
+        value: u64,
+    },
+    /// An output that is neither the payment nor the burn goes to an address the profile does
+    /// not allow change for.
+    UnexpectedChange(Coin),
+    /// A manual transaction does not spend exactly the requested coins.
+    InputsDiffer,
+    /// A manual transaction does not create exactly the requested coins.
+    OutputsDiffer,
+}
+
+impl fmt::Display for TxViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TxViolation::UnknownInput(coin_id) => {
+                write!(
+                    f,
+                    "input {:?} is not an unspent coin of the wallet",
+                    coin_id
+                )
+            }
+            TxViolation::DuplicateInput(coin_id) => write!(f, "input {:?} is spent twice", coin_id),
+            TxViolation::WrongSignature { coin_id, owner } => {
+                write!(f, "input {:?} is not signed as Valid({:?})", coin_id, owner)
+            }
+            TxViolation::ZeroValueOutput(index) => write!(f, "output {} has no value", index),
+            TxViolation::ValueMismatch {
+                inputs,
+                outputs,
+                burn,
+            } => write!(
+                f,
+                "inputs add up to {} but outputs to {} with {} burned",
+                inputs, outputs, burn
+            ),
+            TxViolation::MissingRecipientOutput { recipient, value } => {
+                write!(f, "no output of {} to {:?}", value, recipient)
+            }
+            TxViolation::MissingBurnOutput { address, value } => {
+                write!(f, "no burn output of {} to {:?}", value, address)
+            }
+            TxViolation::UnexpectedChange(coin) => write!(
+                f,
+                "change of {} goes to {:?}, which the profile does not allow",
+                coin.value, coin.owner
+            ),
+            TxViolation::InputsDiffer => write!(f, "inputs differ from the requested coins"),
+            TxViolation::OutputsDiffer => write!(f, "outputs differ from the requested coins"),
+        }
+    }
+}
+
+/// Check `tx`, built by `wallet` for `request`. `owned` are the addresses of the wallet and
+/// `profile` decides where burned value and change may go.
+pub fn validate_transaction(
+    wallet: &impl WalletApi,
+    owned: &[Address],
+    profile: &SpecProfile,
+    request: &TxRequest,
+    tx: &Transaction,
+) -> Result<(), TxViolation> {
+    let mut spent = HashSet::new();
+    let mut inputs: u128 = 0;
+    for input in &tx.inputs {
+        let coin = match wallet.coin_details(&input.coin_id) {
+            Ok(coin) if owned.contains(&coin.owner) => coin,
+            _ => return Err(TxViolation::UnknownInput(input.coin_id)),
+        };
+        if !spent.insert(input.coin_id) {
+            return Err(TxViolation::DuplicateInput(input.coin_id));
+        }
+        if input.signature != Signature::Valid(coin.owner) {
+            return Err(TxViolation::WrongSignature {
+                coin_id: input.coin_id,
+                owner: coin.owner,
+            });
+        }
+        inputs += coin.value as u128;
+    }
+
+    if let Some(index) = tx.outputs.iter().position(|coin| coin.value == 0) {
+        return Err(TxViolation::ZeroValueOutput(index));
+    }
+    let outputs: u128 = tx.outputs.iter().map(|coin| coin.value as u128).sum();
+
+    match *request {
+        TxRequest::Manual {
+            inputs: requested_inputs,
+            outputs: requested_outputs,
+        } => {
+            if tx.inputs.len() != requested_inputs.len()
+                || requested_inputs
+                    .iter()
+                    .any(|coin_id| !spent.contains(coin_id))
+            {
+                return Err(TxViolation::InputsDiffer);
+            }
+            if tx.outputs != requested_outputs {
+                return Err(TxViolation::OutputsDiffer);
+            }
+            // Whatever the outputs leave over is burned.
+            if outputs > inputs {
+                return Err(TxViolation::ValueMismatch {
+                    inputs,
+                    outputs,
+                    burn: 0,
+                });
+            }
+        }
+        TxRequest::Automatic {
+            recipient,
+            output_value,
+            burn,
+        } => {
+            let change_owners = profile.change_owners(owned);
+            let mut rest: Vec<&Coin> = tx.outputs.iter().collect();
+
+            // The payment may be merged with the change when the recipient can receive change.
+            let payment = rest
+                .iter()
+                .position(|coin| coin.owner == recipient && coin.value == output_value)
+                .or_else(|| {
+                    rest.iter().position(|coin| {
+                        coin.owner == recipient
+                            && coin.value > output_value
+                            && change_owners.contains(&recipient)
+                    })
+                })
+                .ok_or(TxViolation::MissingRecipientOutput {
+                    recipient,
+                    value: output_value,
+                })?;
+            rest.remove(payment);
+
+            let implicit_burn = match profile.burn {
+                Burn::Output(address) if burn > 0 => {
+                    let burned = rest
+                        .iter()
+                        .position(|coin| coin.owner == address && coin.value == burn)
+                        .ok_or(TxViolation::MissingBurnOutput {
+                            address,
+                            value: burn,
+                        })?;
+                    rest.remove(burned);
+                    0
+                }
+                _ => burn as u128,
+            };
+
+            if let Some(change) = rest
+                .iter()
+                .find(|coin| !change_owners.contains(&coin.owner))
+            {
+                return Err(TxViolation::UnexpectedChange((*change).clone()));
+            }
+            if inputs != outputs + implicit_burn {
+                return Err(TxViolation::ValueMismatch {
+                    inputs,
+                    outputs,
+                    burn: implicit_burn,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A wallet under test that validates every transaction it creates.
+pub struct ValidatedWallet<W: WalletApi> {
+    wallet: W,
+    owned: Vec<Address>,
+    profile: SpecProfile,
+}
+
+impl<W: WalletApi> ValidatedWallet<W> {
+    /// Wrap `wallet`, which must own exactly `owned` and follow `profile`.
+    pub fn new(wallet: W, owned: Vec<Address>, profile: SpecProfile) -> Self {
+        ValidatedWallet {
+            wallet,
+            owned,
+            profile,
+        }
+    }
+
+    /// The addresses transactions are validated against.
+    pub fn owned(&self) -> &[Address] {
+        &self.owned
+    }
+
+    /// Validate against `owned` from now on, for a wallet whose addresses changed, like one that
+    /// was [restored](crate::persistence::PersistentWalletApi::restore).
+    pub fn set_owned(&mut self, owned: Vec<Address>) {
+        self.owned = owned;
+    }
+
+    pub fn wallet(&self) -> &W {
+        &self.wallet
+    }
+
+    pub fn wallet_mut(&mut self) -> &mut W {
+        &mut self.wallet
+    }
+
+    /// Panic if `result` is a transaction that fails [`validate_transaction`] for `request`.
+    pub fn validated(
+        &self,
+        request: TxRequest,
+        result: Result<Transaction, WalletError>,
+    ) -> Result<Transaction, WalletError> {
+        if let Ok(tx) = &result {
+            if let Err(violation) =
+                validate_transaction(&self.wallet, &self.owned, &self.profile, &request, tx)
+            {
+                panic!(
+                    "invalid transaction for {:?}: {}\n{:?}",
+                    request, violation, tx
+                );
+            }
+        }
+        result
+    }
+}
+
+impl<W: WalletApi> WalletApi for ValidatedWallet<W> {
+    fn sync(&mut self, node: &MockNode) {
+        self.wallet.sync(node)
+    }
+
+    fn best_height(&self) -> u64 {
+        self.wallet.best_height()
+    }
+
+    fn best_hash(&self) -> BlockId {
+        self.wallet.best_hash()
+    }
+
+    fn total_assets_of(&self, address: Address) -> Result<u64, WalletError> {
+        self.wallet.total_assets_of(address)
+    }
+
+    fn net_worth(&self) -> u64 {
+        self.wallet.net_worth()
+    }
+
+    fn all_coins_of(&self, address: Address) -> Result<HashSet<(CoinId, u64)>, WalletError> {
+        self.wallet.all_coins_of(address)
+    }
+
+    fn coin_details(&self, coin_id: &CoinId) -> Result<Coin, WalletError> {
+        self.wallet.coin_details(coin_id)
+    }
+
+    fn create_manual_transaction(
+        &self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError> {
+        let request = TxRequest::Manual {
+            inputs: &input_coin_ids,
+            outputs: &output_coins,
+        };
+        let result = self
+            .wallet
+            .create_manual_transaction(input_coin_ids.clone(), output_coins.clone());
+        self.validated(request, result)
+    }
+
+    fn create_automatic_transaction(
+        &self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    ) -> Result<Transaction, WalletError> {
+        let request = TxRequest::Automatic {
+            recipient,
+            output_value,
+            burn,
+        };
+        let result = self
+            .wallet
+            .create_automatic_transaction(recipient, output_value, burn);
+        self.validated(request, result)
+    }
+}
+
+/// The factory the harness runs every test with: wallets from `F`, wrapped in a
+/// [`ValidatedWallet`].
+pub struct Validated<F>(PhantomData<F>);
+
+impl<F: WalletFactory> WalletFactory for Validated<F> {
+    type Wallet = ValidatedWallet<F::Wallet>;
+
+    const PROFILE: SpecProfile = F::PROFILE;
+
+    fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet {
+        let owned: Vec<Address> = addresses.collect();
+        ValidatedWallet::new(F::new_wallet(owned.clone().into_iter()), owned, F::PROFILE)
+    }
+}
@@ -0,0 +1,204 @@
+//! The transaction validator, fed transactions that are wrong on purpose
+//!
+//! [`Forged`] wallets sync like the wallet they wrap but answer every transaction request with
+//! the same prepared transaction, which [`ValidatedWallet`] must refuse.
+
+use crate::harness::WalletFactory;
+use crate::shrink::panics_quietly;
+use crate::tx_validation::{validate_transaction, TxRequest, TxViolation, ValidatedWallet};
+use bonecoin_core::*;
+use std::collections::*;
+
+/// A wallet returning `tx` for any transaction it is asked to create.
+pub struct Forged<W: WalletApi> {
+    wallet: W,
+    tx: Transaction,
+}
+
+impl<W: WalletApi> WalletApi for Forged<W> {
+    fn sync(&mut self, node: &MockNode) {
+        self.wallet.sync(node)
+    }
+
+    fn best_height(&self) -> u64 {
+        self.wallet.best_height()
+    }
+
+    fn best_hash(&self) -> BlockId {
+        self.wallet.best_hash()
+    }
+
+    fn total_assets_of(&self, address: Address) -> Result<u64, WalletError> {
+        self.wallet.total_assets_of(address)
+    }
+
+    fn net_worth(&self) -> u64 {
+        self.wallet.net_worth()
+    }
+
+    fn all_coins_of(&self, address: Address) -> Result<HashSet<(CoinId, u64)>, WalletError> {
+        self.wallet.all_coins_of(address)
+    }
+
+    fn coin_details(&self, coin_id: &CoinId) -> Result<Coin, WalletError> {
+        self.wallet.coin_details(coin_id)
+    }
+
+    fn create_manual_transaction(
+        &self,
+        _input_coin_ids: Vec<CoinId>,
+        _output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError> {
+        Ok(self.tx.clone())
+    }
+
+    fn create_automatic_transaction(
+        &self,
+        _recipient: Address,
+        _output_value: u64,
+        _burn: u64,
+    ) -> Result<Transaction, WalletError> {
+        Ok(self.tx.clone())
+    }
+}
+
+/// Alice's coins of 30 and 20 and Bob's coin of 50, all minted in block 1.
+struct Coins {
+    node: MockNode,
+    thirty: CoinId,
+    twenty: CoinId,
+    bobs: CoinId,
+}
+
+fn coins() -> Coins {
+    let mint = Transaction {
+        inputs: vec![],
+        outputs: vec![
+            Coin {
+                value: 30,
+                owner: Address::Alice,
+            },
+            Coin {
+                value: 20,
+                owner: Address::Alice,
+            },
+            Coin {
+                value: 50,
+                owner: Address::Bob,
+            },
+        ],
+    };
+    let mut node = MockNode::new();
+    node.add_block_as_best(Block::genesis().id(), vec![mint.clone()]);
+    Coins {
+        node,
+        thirty: mint.coin_id(1, 0),
+        twenty: mint.coin_id(1, 1),
+        bobs: mint.coin_id(1, 2),
+    }
+}
+
+fn spend(coin_id: CoinId, owner: Address) -> Input {
+    Input {
+        coin_id,
+        signature: Signature::Valid(owner),
+    }
+}
+
+fn coin(value: u64, owner: Address) -> Coin {
+    Coin { value, owner }
+}
+
+/// Check that a wallet of Alice from `F`, synced on `node`, is refused `tx` when asked to pay 25
+/// to Dave: [`validate_transaction`] finds `violation` and [`ValidatedWallet`] panics.
+fn assert_refused<F: WalletFactory>(node: &MockNode, tx: Transaction, violation: TxViolation) {
+    let mut wallet = F::new_wallet(vec![Address::Alice].into_iter());
+    wallet.sync(node);
+    let forged = Forged { wallet, tx };
+
+    let request = TxRequest::Automatic {
+        recipient: Address::Dave,
+        output_value: 25,
+        burn: 0,
+    };
+    assert_eq!(
+        validate_transaction(
+            &forged,
+            &[Address::Alice],
+            &F::PROFILE,
+            &request,
+            &forged.tx
+        ),
+        Err(violation.clone())
+    );
+
+    let validated = ValidatedWallet::new(forged, vec![Address::Alice], F::PROFILE);
+    assert!(
+        panics_quietly(|| {
+            let _ = validated.create_automatic_transaction(Address::Dave, 25, 0);
+        }),
+        "a transaction with {:?} went through",
+        violation
+    );
+}
+
+pub fn overspending_transactions_are_refused<F: WalletFactory>() {
+    let coins = coins();
+    let tx = Transaction {
+        inputs: vec![spend(coins.thirty, Address::Alice)],
+        outputs: vec![coin(25, Address::Dave), coin(10, Address::Alice)],
+    };
+    let violation = TxViolation::ValueMismatch {
+        inputs: 30,
+        outputs: 35,
+        burn: 0,
+    };
+    assert_refused::<F>(&coins.node, tx, violation);
+}
+
+pub fn foreign_inputs_are_refused<F: WalletFactory>() {
+    let coins = coins();
+    let tx = Transaction {
+        inputs: vec![spend(coins.bobs, Address::Bob)],
+        outputs: vec![coin(25, Address::Dave), coin(25, Address::Alice)],
+    };
+    assert_refused::<F>(&coins.node, tx, TxViolation::UnknownInput(coins.bobs));
+}
+
+pub fn duplicate_inputs_are_refused<F: WalletFactory>() {
+    let coins = coins();
+    let tx = Transaction {
+        inputs: vec![
+            spend(coins.twenty, Address::Alice),
+            spend(coins.twenty, Address::Alice),
+        ],
+        outputs: vec![coin(25, Address::Dave), coin(15, Address::Alice)],
+    };
+    assert_refused::<F>(&coins.node, tx, TxViolation::DuplicateInput(coins.twenty));
+}
+
+pub fn zero_value_outputs_are_refused<F: WalletFactory>() {
+    let coins = coins();
+    let tx = Transaction {
+        inputs: vec![spend(coins.thirty, Address::Alice)],
+        outputs: vec![
+            coin(25, Address::Dave),
+            coin(5, Address::Alice),
+            coin(0, Address::Eve),
+        ],
+    };
+    assert_refused::<F>(&coins.node, tx, TxViolation::ZeroValueOutput(2));
+}
+
+pub fn transactions_without_outputs_are_refused<F: WalletFactory>() {
+    let coins = coins();
+    let tx = Transaction {
+        inputs: vec![spend(coins.thirty, Address::Alice)],
+        outputs: vec![],
+    };
+    let violation = TxViolation::MissingRecipientOutput {
+        recipient: Address::Dave,
+        value: 25,
+    };
+    assert_refused::<F>(&coins.node, tx, violation);
+}
@@ -0,0 +1,21 @@
+//! Runs the whole battery against the wallet configured in `Cargo.toml`.
+//!
+//! To check another implementation side by side, add it as a dev-dependency, write a
+//! `WalletFactory` for it and invoke `wallet_test_suite!` once more with a new module name.
+
+use bonecoin_core::*;
+use bonecoin_testsuite::{install_counting_allocator, wallet_test_suite, WalletFactory};
+
+install_counting_allocator!();
+
+struct ConfiguredWallet;
+
+impl WalletFactory for ConfiguredWallet {
+    type Wallet = utxo_wallet_assignment::Wallet;
+
+    fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet {
+        utxo_wallet_assignment::Wallet::new(addresses)
+    }
+}
+
+wallet_test_suite!(configured_wallet, ConfiguredWallet);
@@ -0,0 +1,814 @@
+//! Runs the whole battery, optional extensions included, against a reference wallet.
+//!
+//! The configured wallet does not have to implement any extension, so the extension tests need
+//! something else to run against. [`ReferenceWallet`] is a plain implementation of each of them:
+//! it syncs from the `MockNode` like any wallet, keeps what every block of its best chain changed
+//! to the owned coins to undo it on a reorg, and builds automatic transactions the way
+//! [`SpecProfile::REFERENCE`] reads the spec. With a finality depth it forgets everything about
+//! final blocks, their history and the events queued for them included.
+
+use bonecoin_core::*;
+use bonecoin_testsuite::confirmations::ConfirmationsWalletApi;
+use bonecoin_testsuite::events::{EventWalletApi, WalletEvent};
+use bonecoin_testsuite::finality::{FinalityError, FinalityWalletApi};
+use bonecoin_testsuite::history::{HistoryEntry, HistoryKind, HistoryWalletApi};
+use bonecoin_testsuite::incremental::{IncrementalWalletApi, SyncProgress};
+use bonecoin_testsuite::pending::PendingWalletApi;
+use bonecoin_testsuite::persistence::PersistentWalletApi;
+use bonecoin_testsuite::scenario_file::try_parse_address;
+use bonecoin_testsuite::spec_profile::SpecProfile;
+use bonecoin_testsuite::{install_counting_allocator, wallet_test_suite, WalletFactory};
+use std::collections::*;
+use std::io::{self, Read, Write};
+use std::iter;
+use std::str::FromStr;
+
+install_counting_allocator!();
+
+/// First line of a saved [`ReferenceWallet`].
+const SAVE_HEADER: &str = "bonecoin reference wallet 1";
+
+/// What a block did to one owned coin.
+#[derive(Clone, Debug)]
+struct CoinChange {
+    /// An input spent the coin, otherwise an output created it.
+    spent: bool,
+    coin_id: CoinId,
+    coin: Coin,
+    /// Height of the block that created the coin.
+    created: u64,
+    /// Position in the block of the transaction that spent or created the coin.
+    tx_index: usize,
+}
+
+impl CoinChange {
+    /// What connecting the block, or disconnecting it, did to the coin.
+    fn event(&self, connected: bool) -> WalletEvent {
+        let (coin_id, coin) = (self.coin_id, self.coin.clone());
+        if self.spent == connected {
+            WalletEvent::CoinSpent { coin_id, coin }
+        } else {
+            WalletEvent::CoinReceived { coin_id, coin }
+        }
+    }
+}
+
+/// A block of the wallet's best chain.
+#[derive(Clone, Debug)]
+struct ConnectedBlock {
+    id: BlockId,
+    /// In chain order, so undoing the block goes through them backwards.
+    changes: Vec<CoinChange>,
+}
+
+struct ReferenceWallet {
+    addresses: Vec<Address>,
+    /// Height of the first of `blocks`, the highest final block.
+    finalized_height: u64,
+    finality_depth: Option<u64>,
+    /// The best chain from the highest final block up. The coin changes of the final block are
+    /// dropped.
+    blocks: Vec<ConnectedBlock>,
+    /// Owned unspent coins, with the height of the block that created them.
+    utxos: HashMap<CoinId, (Coin, u64)>,
+    /// Created transactions not mined yet, oldest first.
+    pending: Vec<Transaction>,
+    /// Events queued since the last `drain_events`, with the height of the block each is about.
+    events: Vec<(u64, WalletEvent)>,
+    /// The tip a reorg still in progress started from, and the block it went down to.
+    reorg: Option<(BlockId, BlockId)>,
+}
+
+impl ReferenceWallet {
+    fn new(addresses: impl Iterator<Item = Address>) -> Self {
+        ReferenceWallet {
+            addresses: addresses.collect(),
+            finalized_height: 0,
+            finality_depth: None,
+            blocks: vec![ConnectedBlock {
+                id: Block::genesis().id(),
+                changes: Vec::new(),
+            }],
+            utxos: HashMap::new(),
+            pending: Vec::new(),
+            events: Vec::new(),
+            reorg: None,
+        }
+    }
+
+    fn owns(&self, address: Address) -> bool {
+        self.addresses.contains(&address)
+    }
+
+    fn hash_at(&self, height: u64) -> BlockId {
+        self.blocks[(height - self.finalized_height) as usize].id
+    }
+
+    /// The height the wallet's best chain and the node's fork at, and the node's best chain
+    /// above it.
+    ///
+    /// The node is asked for its best block at the wallet's height first, then one height lower
+    /// until both agree, then one height higher until it has nothing more. No height is asked
+    /// twice. Below the final block, the wallet's chain goes on with the parents the node gives.
+    fn plan(&self, node: &MockNode) -> (u64, Vec<BlockId>) {
+        let mut answers = HashMap::new();
+        let mut best_at = |height: u64| {
+            *answers
+                .entry(height)
+                .or_insert_with(|| node.best_block_at_height(height))
+        };
+
+        let mut fork_height = self.best_height();
+        let mut ours = self.best_hash();
+        while fork_height > 0 && best_at(fork_height) != Some(ours) {
+            fork_height -= 1;
+            ours = if fork_height >= self.finalized_height {
+                self.hash_at(fork_height)
+            } else {
+                node.get_block(ours).parent
+            };
+        }
+        let mut branch = Vec::new();
+        while let Some(block_id) = best_at(fork_height + 1 + branch.len() as u64) {
+            branch.push(block_id);
+        }
+        (fork_height, branch)
+    }
+
+    fn connect(&mut self, node: &MockNode, block_id: BlockId) {
+        let height = self.best_height() + 1;
+        let block = node.get_block(block_id);
+        let mut changes = Vec::new();
+        for (tx_index, tx) in block.transactions.iter().enumerate() {
+            for input in &tx.inputs {
+                if let Some((coin, created)) = self.utxos.remove(&input.coin_id) {
+                    changes.push(CoinChange {
+                        spent: true,
+                        coin_id: input.coin_id,
+                        coin,
+                        created,
+                        tx_index,
+                    });
+                }
+            }
+            for (index, coin) in tx.outputs.iter().enumerate() {
+                if self.owns(coin.owner) {
+                    let coin_id = tx.coin_id(height, index);
+                    self.utxos.insert(coin_id, (coin.clone(), height));
+                    changes.push(CoinChange {
+                        spent: false,
+                        coin_id,
+                        coin: coin.clone(),
+                        created: height,
+                        tx_index,
+                    });
+                }
+            }
+        }
+        self.events.push((
+            height,
+            WalletEvent::BlockConnected {
+                height,
+                block: block_id,
+            },
+        ));
+        self.events
+            .extend(changes.iter().map(|change| (height, change.event(true))));
+        self.blocks.push(ConnectedBlock {
+            id: block_id,
+            changes,
+        });
+        self.finalize();
+    }
+
+    fn disconnect(&mut self) {
+        let height = self.best_height();
+        let old_tip = self.reorg.map_or(self.best_hash(), |(old_tip, _)| old_tip);
+        assert!(
+            height > self.finalized_height,
+            "final blocks are never disconnected"
+        );
+        let block = self.blocks.pop().unwrap();
+        self.reorg = Some((old_tip, self.best_hash()));
+        self.events.push((
+            height,
+            WalletEvent::BlockDisconnected {
+                height,
+                block: block.id,
+            },
+        ));
+        self.events.extend(
+            block
+                .changes
+                .iter()
+                .rev()
+                .map(|change| (height, change.event(false))),
+        );
+        for change in block.changes.into_iter().rev() {
+            if change.spent {
+                self.utxos
+                    .insert(change.coin_id, (change.coin, change.created));
+            } else {
+                self.utxos.remove(&change.coin_id);
+            }
+        }
+    }
+
+    /// Move the final block up to `finality_depth` blocks below the best one, and drop the
+    /// blocks below it along with the events queued up to the last one about a final block.
+    fn finalize(&mut self) {
+        let Some(depth) = self.finality_depth else {
+            return;
+        };
+        let finalized_height = self.best_height().saturating_sub(depth);
+        if finalized_height <= self.finalized_height {
+            return;
+        }
+        self.blocks
+            .drain(..(finalized_height - self.finalized_height) as usize);
+        self.blocks[0].changes = Vec::new();
+        self.finalized_height = finalized_height;
+        if let Some(last) = self
+            .events
+            .iter()
+            .rposition(|(height, _)| *height <= finalized_height)
+        {
+            self.events.drain(..=last);
+        }
+    }
+
+    /// Disconnect down to `fork_height`, then connect `branch` on top of it, `max_blocks` blocks
+    /// at most. Reorg events and dropped pending transactions wait for the step that reaches the
+    /// end of `branch`.
+    fn follow(
+        &mut self,
+        node: &MockNode,
+        fork_height: u64,
+        branch: &[BlockId],
+        max_blocks: u64,
+    ) -> SyncProgress {
+        let mut moved = 0;
+        while moved < max_blocks && self.best_height() > fork_height {
+            self.disconnect();
+            moved += 1;
+        }
+        if self.best_height() == fork_height {
+            for block_id in branch.iter().take((max_blocks - moved) as usize) {
+                self.connect(node, *block_id);
+            }
+        }
+
+        let progress = SyncProgress {
+            height: self.best_height(),
+            target_height: fork_height + branch.len() as u64,
+            reorging: self.best_height() > fork_height,
+        };
+        if progress.is_done() {
+            if let Some((old_tip, fork_point)) = self.reorg.take() {
+                let event = WalletEvent::ReorgCompleted {
+                    fork_point,
+                    old_tip,
+                    new_tip: self.best_hash(),
+                };
+                self.events.push((self.best_height(), event));
+            }
+            self.drop_settled_pending();
+        }
+        progress
+    }
+
+    /// Drop the pending transactions that are mined or spend a coin that is gone.
+    fn drop_settled_pending(&mut self) {
+        let utxos = &self.utxos;
+        self.pending.retain(|tx| {
+            tx.inputs
+                .iter()
+                .all(|input| utxos.contains_key(&input.coin_id))
+        });
+    }
+
+    fn reserved(&self) -> HashSet<CoinId> {
+        self.pending
+            .iter()
+            .flat_map(|tx| tx.inputs.iter().map(|input| input.coin_id))
+            .collect()
+    }
+
+    fn manual_transaction(
+        &self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+        reserved: &HashSet<CoinId>,
+    ) -> Result<Transaction, WalletError> {
+        if input_coin_ids.is_empty() {
+            return Err(WalletError::ZeroInputs);
+        }
+        if output_coins.iter().any(|coin| coin.value == 0) {
+            return Err(WalletError::ZeroCoinValue);
+        }
+
+        let mut seen = HashSet::new();
+        let mut inputs = Vec::new();
+        let mut input_total: u128 = 0;
+        for coin_id in input_coin_ids {
+            let (coin, _) = self.utxos.get(&coin_id).ok_or(WalletError::UnknownCoin)?;
+            if reserved.contains(&coin_id) || !seen.insert(coin_id) {
+                return Err(WalletError::UnknownCoin);
+            }
+            input_total += coin.value as u128;
+            inputs.push(Input {
+                coin_id,
+                signature: Signature::Valid(coin.owner),
+            });
+        }
+
+        let output_total: u128 = output_coins.iter().map(|coin| coin.value as u128).sum();
+        if output_total > input_total {
+            return Err(WalletError::InsufficientFunds);
+        }
+
+        Ok(Transaction {
+            inputs,
+            outputs: output_coins,
+        })
+    }
+
+    /// Spend the largest coins that are not `reserved` and send the change to the first owned
+    /// address.
+    fn automatic_transaction(
+        &self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+        reserved: &HashSet<CoinId>,
+    ) -> Result<Transaction, WalletError> {
+        let change_address = *self
+            .addresses
+            .first()
+            .ok_or(WalletError::NoOwnedAddresses)?;
+        if output_value == 0 {
+            return Err(WalletError::ZeroCoinValue);
+        }
+
+        let mut coins: Vec<(&CoinId, &Coin)> = self
+            .utxos
+            .iter()
+            .filter(|(coin_id, _)| !reserved.contains(coin_id))
+            .map(|(coin_id, (coin, _))| (coin_id, coin))
+            .collect();
+        coins.sort_by(|a, b| b.1.value.cmp(&a.1.value));
+
+        let needed = output_value as u128 + burn as u128;
+        let mut inputs = Vec::new();
+        let mut input_total: u128 = 0;
+        for (coin_id, coin) in coins {
+            if input_total >= needed {
+                break;
+            }
+            input_total += coin.value as u128;
+            inputs.push(Input {
+                coin_id: *coin_id,
+                signature: Signature::Valid(coin.owner),
+            });
+        }
+        if input_total < needed {
+            return Err(WalletError::InsufficientFunds);
+        }
+
+        let mut outputs = vec![Coin {
+            value: output_value,
+            owner: recipient,
+        }];
+        let change = input_total - needed;
+        if change > 0 {
+            outputs.push(Coin {
+                value: change as u64,
+                owner: change_address,
+            });
+        }
+
+        Ok(Transaction { inputs, outputs })
+    }
+}
+
+impl WalletApi for ReferenceWallet {
+    fn sync(&mut self, node: &MockNode) {
+        self.sync_step(node, u64::MAX);
+    }
+
+    fn best_height(&self) -> u64 {
+        self.finalized_height + self.blocks.len() as u64 - 1
+    }
+
+    fn best_hash(&self) -> BlockId {
+        self.blocks.last().unwrap().id
+    }
+
+    fn total_assets_of(&self, address: Address) -> Result<u64, WalletError> {
+        if !self.owns(address) {
+            return Err(WalletError::ForeignAddress);
+        }
+        Ok(self
+            .utxos
+            .values()
+            .filter(|(coin, _)| coin.owner == address)
+            .fold(0u64, |total, (coin, _)| total.saturating_add(coin.value)))
+    }
+
+    fn net_worth(&self) -> u64 {
+        self.utxos
+            .values()
+            .fold(0u64, |total, (coin, _)| total.saturating_add(coin.value))
+    }
+
+    fn all_coins_of(&self, address: Address) -> Result<HashSet<(CoinId, u64)>, WalletError> {
+        if !self.owns(address) {
+            return Err(WalletError::ForeignAddress);
+        }
+        Ok(self
+            .utxos
+            .iter()
+            .filter(|(_, (coin, _))| coin.owner == address)
+            .map(|(coin_id, (coin, _))| (*coin_id, coin.value))
+            .collect())
+    }
+
+    fn coin_details(&self, coin_id: &CoinId) -> Result<Coin, WalletError> {
+        self.utxos
+            .get(coin_id)
+            .map(|(coin, _)| coin.clone())
+            .ok_or(WalletError::UnknownCoin)
+    }
+
+    fn create_manual_transaction(
+        &self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError> {
+        self.manual_transaction(input_coin_ids, output_coins, &HashSet::new())
+    }
+
+    fn create_automatic_transaction(
+        &self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    ) -> Result<Transaction, WalletError> {
+        self.automatic_transaction(recipient, output_value, burn, &HashSet::new())
+    }
+}
+
+impl PendingWalletApi for ReferenceWallet {
+    fn create_pending_automatic_transaction(
+        &mut self,
+        recipient: Address,
+        output_value: u64,
+        burn: u64,
+    ) -> Result<Transaction, WalletError> {
+        let tx = self.automatic_transaction(recipient, output_value, burn, &self.reserved())?;
+        self.pending.push(tx.clone());
+        Ok(tx)
+    }
+
+    fn create_pending_manual_transaction(
+        &mut self,
+        input_coin_ids: Vec<CoinId>,
+        output_coins: Vec<Coin>,
+    ) -> Result<Transaction, WalletError> {
+        let tx = self.manual_transaction(input_coin_ids, output_coins, &self.reserved())?;
+        self.pending.push(tx.clone());
+        Ok(tx)
+    }
+
+    fn pending_transactions(&self) -> Vec<Transaction> {
+        self.pending.clone()
+    }
+
+    fn confirmed_balance(&self) -> u64 {
+        self.net_worth()
+    }
+
+    fn pending_balance(&self) -> u64 {
+        let reserved: u64 = self
+            .reserved()
+            .iter()
+            .map(|coin_id| self.utxos[coin_id].0.value)
+            .sum();
+        let incoming: u64 = self
+            .pending
+            .iter()
+            .flat_map(|tx| &tx.outputs)
+            .filter(|coin| self.owns(coin.owner))
+            .map(|coin| coin.value)
+            .sum();
+        self.net_worth() - reserved + incoming
+    }
+}
+
+impl HistoryWalletApi for ReferenceWallet {
+    /// The coin changes of the blocks of the best chain above the final block, which are in chain
+    /// order already.
+    fn history_of(&self, address: Address) -> Result<Vec<HistoryEntry>, WalletError> {
+        if !self.owns(address) {
+            return Err(WalletError::ForeignAddress);
+        }
+        let mut history = Vec::new();
+        for (height, block) in (self.finalized_height..).zip(&self.blocks) {
+            for change in &block.changes {
+                if change.coin.owner != address {
+                    continue;
+                }
+                history.push(HistoryEntry {
+                    kind: if change.spent {
+                        HistoryKind::Spent
+                    } else {
+                        HistoryKind::Received
+                    },
+                    coin_id: change.coin_id,
+                    value: change.coin.value,
+                    height,
+                    block: block.id,
+                    tx_index: change.tx_index,
+                });
+            }
+        }
+        Ok(history)
+    }
+}
+
+impl EventWalletApi for ReferenceWallet {
+    fn drain_events(&mut self) -> Vec<WalletEvent> {
+        self.events.drain(..).map(|(_, event)| event).collect()
+    }
+}
+
+impl IncrementalWalletApi for ReferenceWallet {
+    /// A best chain forking below the final block is never reached, the wallet stays where it is.
+    fn sync_step(&mut self, node: &MockNode, max_blocks: u64) -> SyncProgress {
+        let (fork_height, branch) = self.plan(node);
+        let max_blocks = if fork_height < self.finalized_height {
+            0
+        } else {
+            max_blocks
+        };
+        self.follow(node, fork_height, &branch, max_blocks)
+    }
+}
+
+impl ConfirmationsWalletApi for ReferenceWallet {
+    fn total_assets_of_with_confirmations(
+        &self,
+        address: Address,
+        min_confirmations: u64,
+    ) -> Result<u64, WalletError> {
+        Ok(self
+            .coins_with_confirmations_of(address)?
+            .iter()
+            .filter(|(_, _, confirmations)| *confirmations >= min_confirmations)
+            .fold(0u64, |total, (_, value, _)| total.saturating_add(*value)))
+    }
+
+    fn net_worth_with_confirmations(&self, min_confirmations: u64) -> u64 {
+        self.utxos
+            .values()
+            .filter(|(_, created)| self.best_height() - created + 1 >= min_confirmations)
+            .fold(0u64, |total, (coin, _)| total.saturating_add(coin.value))
+    }
+
+    fn coins_with_confirmations_of(
+        &self,
+        address: Address,
+    ) -> Result<HashSet<(CoinId, u64, u64)>, WalletError> {
+        if !self.owns(address) {
+            return Err(WalletError::ForeignAddress);
+        }
+        Ok(self
+            .utxos
+            .iter()
+            .filter(|(_, (coin, _))| coin.owner == address)
+            .map(|(coin_id, (coin, created))| {
+                (*coin_id, coin.value, self.best_height() - created + 1)
+            })
+            .collect())
+    }
+}
+
+impl FinalityWalletApi for ReferenceWallet {
+    fn set_finality_depth(&mut self, depth: u64) {
+        self.finality_depth = Some(depth);
+        self.finalize();
+    }
+
+    fn finalized_height(&self) -> u64 {
+        self.finalized_height
+    }
+
+    fn try_sync(&mut self, node: &MockNode) -> Result<(), FinalityError> {
+        let (fork_height, branch) = self.plan(node);
+        if fork_height < self.finalized_height {
+            return Err(FinalityError::ReorgTooDeep {
+                fork_height,
+                finalized_height: self.finalized_height,
+            });
+        }
+        self.follow(node, fork_height, &branch, u64::MAX);
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn next_word<'a>(words: &mut impl Iterator<Item = &'a str>, what: &str) -> io::Result<&'a str> {
+    words
+        .next()
+        .ok_or_else(|| invalid(format!("missing {}", what)))
+}
+
+/// Parse a word of a saved wallet, an id in its `Debug` form or a number.
+fn parse_word<T: FromStr>(word: &str, what: &str) -> io::Result<T> {
+    word.parse()
+        .map_err(|_| invalid(format!("invalid {} `{}`", what, word)))
+}
+
+fn parse_saved_address(word: &str) -> io::Result<Address> {
+    try_parse_address(word).ok_or_else(|| invalid(format!("unknown address `{}`", word)))
+}
+
+/// Parse a coin saved as `<coin id> <value> <owner> <height it was created at>`.
+fn parse_saved_coin<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+) -> io::Result<(CoinId, Coin, u64)> {
+    let coin_id = parse_word(next_word(words, "coin id")?, "coin id")?;
+    let value = parse_word(next_word(words, "value")?, "value")?;
+    let owner = parse_saved_address(next_word(words, "owner")?)?;
+    let created = parse_word(next_word(words, "height")?, "height")?;
+    Ok((coin_id, Coin { value, owner }, created))
+}
+
+/// Saved as text, one line per item: the owned addresses, the finality depth if one is set and
+/// the height of the final block, then each block of the best chain from it up followed by its
+/// coin changes, the unspent coins and the pending transactions. The last line is `end`, so a
+/// saved wallet that was cut short is refused. Events not drained yet are not saved.
+impl PersistentWalletApi for ReferenceWallet {
+    fn save(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "{}", SAVE_HEADER)?;
+        write!(writer, "addresses")?;
+        for address in &self.addresses {
+            write!(writer, " {:?}", address)?;
+        }
+        writeln!(writer)?;
+        if let Some(depth) = self.finality_depth {
+            writeln!(writer, "depth {}", depth)?;
+        }
+        writeln!(writer, "finalized {}", self.finalized_height)?;
+        for block in &self.blocks {
+            writeln!(writer, "block {:?}", block.id)?;
+            for change in &block.changes {
+                writeln!(
+                    writer,
+                    "{} {:?} {} {:?} {} {}",
+                    if change.spent { "spent" } else { "created" },
+                    change.coin_id,
+                    change.coin.value,
+                    change.coin.owner,
+                    change.created,
+                    change.tx_index
+                )?;
+            }
+        }
+        for (coin_id, (coin, created)) in &self.utxos {
+            writeln!(
+                writer,
+                "utxo {:?} {} {:?} {}",
+                coin_id, coin.value, coin.owner, created
+            )?;
+        }
+        for tx in &self.pending {
+            write!(writer, "pending")?;
+            for input in &tx.inputs {
+                write!(writer, " {:?}", input.coin_id)?;
+            }
+            write!(writer, " >")?;
+            for coin in &tx.outputs {
+                write!(writer, " {}:{:?}", coin.value, coin.owner)?;
+            }
+            writeln!(writer)?;
+        }
+        writeln!(writer, "end")
+    }
+
+    fn restore(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut lines = text.lines();
+        if lines.next() != Some(SAVE_HEADER) || !text.ends_with("\nend\n") {
+            return Err(invalid("not a complete saved reference wallet".to_string()));
+        }
+
+        let mut restored = ReferenceWallet::new(iter::empty());
+        restored.blocks.clear();
+        for line in lines {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("addresses") => {
+                    restored.addresses =
+                        words.map(parse_saved_address).collect::<Result<_, _>>()?;
+                }
+                Some("depth") => {
+                    restored.finality_depth =
+                        Some(parse_word(next_word(&mut words, "depth")?, "depth")?);
+                }
+                Some("finalized") => {
+                    restored.finalized_height =
+                        parse_word(next_word(&mut words, "height")?, "height")?;
+                }
+                Some("block") => restored.blocks.push(ConnectedBlock {
+                    id: parse_word(next_word(&mut words, "block id")?, "block id")?,
+                    changes: Vec::new(),
+                }),
+                Some(kind @ ("spent" | "created")) => {
+                    let (coin_id, coin, created) = parse_saved_coin(&mut words)?;
+                    let tx_index = parse_word(next_word(&mut words, "tx index")?, "tx index")?;
+                    let block = restored
+                        .blocks
+                        .last_mut()
+                        .ok_or_else(|| invalid(format!("`{}` before any block", line)))?;
+                    block.changes.push(CoinChange {
+                        spent: kind == "spent",
+                        coin_id,
+                        coin,
+                        created,
+                        tx_index,
+                    });
+                }
+                Some("utxo") => {
+                    let (coin_id, coin, created) = parse_saved_coin(&mut words)?;
+                    restored.utxos.insert(coin_id, (coin, created));
+                }
+                Some("pending") => {
+                    let mut inputs = Vec::new();
+                    for word in words.by_ref().take_while(|word| *word != ">") {
+                        let coin_id = parse_word(word, "coin id")?;
+                        let (coin, _) = restored.utxos.get(&coin_id).ok_or_else(|| {
+                            invalid(format!("pending transaction spends unknown {}", word))
+                        })?;
+                        inputs.push(Input {
+                            coin_id,
+                            signature: Signature::Valid(coin.owner),
+                        });
+                    }
+                    let mut outputs = Vec::new();
+                    for word in words {
+                        let (value, owner) = word
+                            .split_once(':')
+                            .ok_or_else(|| invalid(format!("invalid output `{}`", word)))?;
+                        outputs.push(Coin {
+                            value: parse_word(value, "value")?,
+                            owner: parse_saved_address(owner)?,
+                        });
+                    }
+                    restored.pending.push(Transaction { inputs, outputs });
+                }
+                Some("end") => break,
+                _ => return Err(invalid(format!("unexpected line `{}`", line))),
+            }
+        }
+        let Some(first) = restored.blocks.first() else {
+            return Err(invalid("the saved chain has no blocks".to_string()));
+        };
+        if restored.finalized_height == 0 && first.id != Block::genesis().id() {
+            return Err(invalid(
+                "the saved chain does not start at genesis".to_string(),
+            ));
+        }
+
+        *self = restored;
+        Ok(())
+    }
+}
+
+struct Reference;
+
+impl WalletFactory for Reference {
+    type Wallet = ReferenceWallet;
+
+    const PROFILE: SpecProfile = SpecProfile::REFERENCE;
+
+    fn new_wallet(addresses: impl Iterator<Item = Address>) -> Self::Wallet {
+        ReferenceWallet::new(addresses)
+    }
+}
+
+wallet_test_suite!(
+    reference,
+    Reference,
+    pending,
+    persistence,
+    history,
+    events,
+    incremental,
+    confirmations,
+    finality
+);